@@ -89,7 +89,7 @@ fn main() {
         // DeltaTime: 0, TrackName, len 6 bytes, "Singer"
         0x00, 0xFF, 0x03, 0x06, 0x53, 0x69, 0x6E, 0x67, 0x65, 0x72, //
         // DeltaTime: 0, TimeSignature
-        0x00, 0xFF, 0x58, 0x04, 0x06, 0x03, 0x20, 0x00, //
+        0x00, 0xFF, 0x58, 0x04, 0x06, 0x03, 0x24, 0x00, //
         // DeltaTime: 0, SetTempo
         0x00, 0xFF, 0x51, 0x03, 0x07, 0xE4, 0x79, //
         // DeltaTime: 0, Lyric: "Row"
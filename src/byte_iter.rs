@@ -1,27 +1,127 @@
 //! The `byte_iter` module provides a wrapper for iterating over the bytes of a MIDI file.
 
 use crate::core::vlq::{decode_slice, VlqError, CONTINUE};
+use crate::text::TextEncoding;
 use log::trace;
 use snafu::{ensure, OptionExt, ResultExt, Snafu};
 use std::fs::File;
-use std::io::{BufReader, Bytes, ErrorKind, Read};
+use std::io::{BufReader, ErrorKind, Read};
 use std::path::{Path, PathBuf};
 use std::str::{from_utf8, Utf8Error};
 
+/// Something that `ByteIter` can pull bytes from, either a `Read` object or an in-memory slice.
+pub(crate) trait ByteSource {
+    /// Returns the next byte, or `None` when there are no more bytes.
+    fn next_byte(&mut self) -> std::io::Result<Option<u8>>;
+
+    /// Fills as much of `buf` as possible and returns the number of bytes written. The return value
+    /// is less than `buf.len()` only if there are no more bytes.
+    fn read_bulk(&mut self, buf: &mut [u8]) -> std::io::Result<usize>;
+}
+
+/// A `ByteSource` that reads from any `Read` object. This should usually be buffered.
+pub(crate) struct ReadSource<R: Read> {
+    r: R,
+}
+
+impl<R: Read> ByteSource for ReadSource<R> {
+    fn next_byte(&mut self) -> std::io::Result<Option<u8>> {
+        let mut byte = 0u8;
+        loop {
+            return match self.r.read(std::slice::from_mut(&mut byte)) {
+                Ok(0) => Ok(None),
+                Ok(_) => Ok(Some(byte)),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+                Err(e) => Err(e),
+            };
+        }
+    }
+
+    fn read_bulk(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.r.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
+    }
+}
+
+/// A `ByteSource` that indexes directly into an in-memory slice, which is much faster than going
+/// through `Read` one byte at a time.
+pub(crate) struct SliceSource<'a> {
+    data: &'a [u8],
+    index: usize,
+}
+
+impl ByteSource for SliceSource<'_> {
+    #[inline]
+    fn next_byte(&mut self) -> std::io::Result<Option<u8>> {
+        let byte = self.data.get(self.index).copied();
+        if byte.is_some() {
+            self.index += 1;
+        }
+        Ok(byte)
+    }
+
+    fn read_bulk(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = &self.data[self.index..];
+        let n = remaining.len().min(buf.len());
+        buf[..n].copy_from_slice(&remaining[..n]);
+        self.index += n;
+        Ok(n)
+    }
+}
+
+/// Options that change how strictly the bytes are parsed.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub(crate) struct ReadSettings {
+    pub(crate) repair_missing_end_of_track: bool,
+    pub(crate) preserve_status_bytes: bool,
+    pub(crate) allow_empty_tracks: bool,
+    pub(crate) clamp_track_lengths: bool,
+    pub(crate) ignore_trailing_data: bool,
+    pub(crate) text_encoding: TextEncoding,
+}
+
+impl Default for ReadSettings {
+    fn default() -> Self {
+        Self {
+            repair_missing_end_of_track: false,
+            preserve_status_bytes: false,
+            allow_empty_tracks: false,
+            clamp_track_lengths: false,
+            ignore_trailing_data: true,
+            text_encoding: TextEncoding::default(),
+        }
+    }
+}
+
 // TODO - make this less weird
 /// The internals are weird, it's basically a debugging thing to be able to see the next few values
 /// at a breakpoint.
-pub(crate) struct ByteIter<R: Read> {
-    iter: Bytes<R>,
+pub(crate) struct ByteIter<S: ByteSource> {
+    source: S,
     position: Option<u64>,
     current: Option<u8>,
     peek1: Option<u8>,
     peek2: Option<u8>,
     peek3: Option<u8>,
     position_limit: Option<u64>,
+    /// The `size` that was passed to `set_size_limit`.
+    size_limit: Option<u64>,
+    /// The total number of bytes in the source, if it is known up front.
+    source_len: Option<u64>,
     /// To help with 'running status', you can save a byte you need to remember here.
     latest_message_byte: Option<u8>,
     running_status_detected: bool,
+    settings: ReadSettings,
 }
 
 #[derive(Debug, Snafu)]
@@ -79,48 +179,100 @@ pub(crate) enum ByteError {
     },
 }
 
+impl ByteError {
+    /// The byte position near which the error occurred, if any.
+    pub(crate) fn position(&self) -> Option<u64> {
+        match self {
+            ByteError::Io { position, .. }
+            | ByteError::End { position }
+            | ByteError::Str { position, .. }
+            | ByteError::Tag { position, .. }
+            | ByteError::VlqTooBig { position }
+            | ByteError::VlqDecode { position, .. }
+            | ByteError::ReadExpect { position, .. } => Some(*position),
+            ByteError::FileOpen { .. } => None,
+        }
+    }
+
+    /// Whether the error came from the underlying file or stream rather than from the bytes.
+    pub(crate) fn is_io(&self) -> bool {
+        matches!(self, ByteError::Io { .. } | ByteError::FileOpen { .. })
+    }
+}
+
 pub(crate) type ByteResult<T> = std::result::Result<T, ByteError>;
 
 const BYTE_SIZE: usize = 8;
 const KB: usize = BYTE_SIZE * 1024;
 const MB: usize = KB * 1024;
 
-impl ByteIter<BufReader<File>> {
+impl ByteIter<ReadSource<BufReader<File>>> {
     pub(crate) fn new_file<P: AsRef<Path>>(path: P) -> ByteResult<Self> {
         let path = path.as_ref();
         let f = File::open(path).context(FileOpenSnafu { path })?;
+        let source_len = f.metadata().ok().map(|metadata| metadata.len());
         let buf = BufReader::with_capacity(MB, f);
-        Self::new(buf.bytes())
+        let mut iter = Self::new(buf)?;
+        iter.source_len = source_len;
+        Ok(iter)
     }
 }
 
-impl<R: Read> ByteIter<R> {
-    pub(crate) fn new(mut iter: Bytes<R>) -> ByteResult<Self> {
-        let peek1 = Self::next_impl(&mut iter, 0)?;
-        let peek2 = Self::next_impl(&mut iter, 0)?;
-        let peek3 = Self::next_impl(&mut iter, 0)?;
+/// Memory-map the file at `path`.
+#[cfg(feature = "mmap")]
+pub(crate) fn map_file<P: AsRef<Path>>(path: P) -> ByteResult<memmap2::Mmap> {
+    let path = path.as_ref();
+    let f = File::open(path).context(FileOpenSnafu { path })?;
+    // SAFETY: the map is only read while parsing, and the caller is warned that the file must not
+    // be modified during this time, see `MidiFile::load_mmap`.
+    unsafe { memmap2::Mmap::map(&f) }.context(FileOpenSnafu { path })
+}
+
+/// Read the entire contents of the file at `path` into memory.
+pub(crate) fn read_file<P: AsRef<Path>>(path: P) -> ByteResult<Vec<u8>> {
+    let path = path.as_ref();
+    std::fs::read(path).context(FileOpenSnafu { path })
+}
+
+impl<R: Read> ByteIter<ReadSource<R>> {
+    pub(crate) fn new(r: R) -> ByteResult<Self> {
+        Self::with_source(ReadSource { r })
+    }
+}
+
+impl<'a> ByteIter<SliceSource<'a>> {
+    /// Create a `ByteIter` that reads directly from `data` without going through `Read`.
+    pub(crate) fn from_slice(data: &'a [u8]) -> ByteResult<Self> {
+        let mut iter = Self::with_source(SliceSource { data, index: 0 })?;
+        iter.source_len = Some(data.len() as u64);
+        Ok(iter)
+    }
+}
+
+impl<S: ByteSource> ByteIter<S> {
+    fn with_source(mut source: S) -> ByteResult<Self> {
+        let peek1 = Self::next_impl(&mut source, 0)?;
+        let peek2 = Self::next_impl(&mut source, 0)?;
+        let peek3 = Self::next_impl(&mut source, 0)?;
         Ok(Self {
-            iter,
+            source,
             position: None,
             current: None,
             peek1,
             peek2,
             peek3,
             position_limit: None,
+            size_limit: None,
+            source_len: None,
             latest_message_byte: None,
             running_status_detected: false,
+            settings: ReadSettings::default(),
         })
     }
 
-    fn next_impl(iter: &mut Bytes<R>, position: u64) -> ByteResult<Option<u8>> {
-        match iter.next() {
-            None => Ok(None),
-            Some(result) => match result {
-                Ok(val) => Ok(Some(val)),
-                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
-                Err(e) => Err(e).context(IoSnafu { position }),
-            },
-        }
+    #[inline]
+    fn next_impl(source: &mut S, position: u64) -> ByteResult<Option<u8>> {
+        source.next_byte().context(IoSnafu { position })
     }
 
     /// Read a single byte and advance the iter.
@@ -141,46 +293,13 @@ impl<R: Read> ByteIter<R> {
         self.current = self.peek1;
         self.peek1 = self.peek2;
         self.peek2 = self.peek3;
-        let next_opt = self.iter.next();
-        let next_result = match next_opt {
-            None => {
-                self.peek3 = None;
-                trace!(
-                    "read {:#x} at position {}",
-                    return_val.unwrap_or(0),
-                    self.position.unwrap_or(0)
-                );
-                return Ok(return_val);
-            }
-            Some(r) => r,
-        };
-
-        let e = match next_result {
-            Ok(ok) => {
-                self.peek3 = Some(ok);
-                trace!(
-                    "read {:#x} at position {}",
-                    return_val.unwrap_or(0),
-                    self.position.unwrap_or(0)
-                );
-                return Ok(return_val);
-            }
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                    self.peek3 = None;
-                    trace!(
-                        "read {:#x} at position {}",
-                        return_val.unwrap_or(0),
-                        self.position.unwrap_or(0)
-                    );
-                    return Ok(return_val);
-                }
-                e
-            }
-        };
-        Err(e).context(IoSnafu {
-            position: self.position.unwrap_or(0),
-        })
+        self.peek3 = Self::next_impl(&mut self.source, self.position.unwrap_or(0))?;
+        trace!(
+            "read {:#x} at position {}",
+            return_val.unwrap_or(0),
+            self.position.unwrap_or(0)
+        );
+        Ok(return_val)
     }
 
     pub(crate) fn read_or_die(&mut self) -> ByteResult<u8> {
@@ -259,6 +378,10 @@ impl<R: Read> ByteIter<R> {
         self.current
     }
 
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.peek1
+    }
+
     pub(crate) fn peek_or_die(&self) -> ByteResult<u8> {
         self.peek1.context(EndSnafu {
             position: self.position.unwrap_or(0),
@@ -295,11 +418,38 @@ impl<R: Read> ByteIter<R> {
     /// When this is set, the ByteIter will report that it is at the end when `size` bytes have been
     /// read.
     pub(crate) fn set_size_limit(&mut self, size: u64) {
-        self.position_limit = Some(self.position.unwrap_or(0) + size)
+        self.position_limit = Some(self.position.unwrap_or(0) + size);
+        self.size_limit = Some(size);
     }
 
     pub(crate) fn clear_size_limit(&mut self) {
-        self.position_limit = None
+        self.position_limit = None;
+        self.size_limit = None;
+    }
+
+    /// The size of the current limit, if one is set.
+    pub(crate) fn size_limit(&self) -> Option<u64> {
+        self.size_limit
+    }
+
+    /// The number of bytes that can be read before the size limit is reached, if one is set.
+    pub(crate) fn remaining(&self) -> Option<u64> {
+        self.position_limit
+            .map(|limit| limit.saturating_sub(self.position.unwrap_or(0)))
+    }
+
+    /// The number of bytes that have not been read yet, if the length of the source is known.
+    pub(crate) fn available(&self) -> Option<u64> {
+        let consumed = match self.current {
+            None => 0,
+            Some(_) => self.position.unwrap_or(0) + 1,
+        };
+        self.source_len.map(|len| len.saturating_sub(consumed))
+    }
+
+    /// The position of the current byte, if any bytes have been read.
+    pub(crate) fn position(&self) -> Option<u64> {
+        self.position
     }
 
     pub(crate) fn read_expect(&mut self, expected: u8) -> ByteResult<()> {
@@ -315,7 +465,65 @@ impl<R: Read> ByteIter<R> {
         Ok(())
     }
 
+    /// Reads exactly `num_bytes` or returns an `End` error. The bytes waiting in the peek slots are
+    /// taken first, then the remainder is read from the source in bulk.
     pub(crate) fn read_n(&mut self, num_bytes: usize) -> ByteResult<Vec<u8>> {
+        let (peek1, peek2, peek3) = match (self.peek1, self.peek2, self.peek3) {
+            (Some(a), Some(b), Some(c)) if num_bytes > 3 => (a, b, c),
+            // near the end of the data, or for tiny reads, go one byte at a time
+            _ => return self.read_n_bytewise(num_bytes),
+        };
+        let first_position = match self.current {
+            None => 0,
+            Some(_) => self.position.unwrap_or(0) + 1,
+        };
+        let last_position = first_position + num_bytes as u64 - 1;
+        if let Some(limit) = self.position_limit {
+            // the bytewise read will stop at the same place it always has
+            if last_position > limit {
+                return self.read_n_bytewise(num_bytes);
+            }
+        }
+        let mut bytes = vec![0u8; num_bytes];
+        bytes[0] = peek1;
+        bytes[1] = peek2;
+        bytes[2] = peek3;
+        let filled = 3 + self.source.read_bulk(&mut bytes[3..]).context(IoSnafu {
+            position: first_position + 3,
+        })?;
+        self.position = Some(first_position + filled as u64 - 1);
+        self.current = Some(bytes[filled - 1]);
+        if filled < num_bytes {
+            self.peek1 = None;
+            self.peek2 = None;
+            self.peek3 = None;
+            return EndSnafu {
+                position: self.position.unwrap_or(0),
+            }
+            .fail();
+        }
+        let position = self.position.unwrap_or(0);
+        self.peek1 = Self::next_impl(&mut self.source, position)?;
+        self.peek2 = Self::next_impl(&mut self.source, position)?;
+        self.peek3 = Self::next_impl(&mut self.source, position)?;
+        trace!("read {} bytes ending at position {}", num_bytes, position);
+        Ok(bytes)
+    }
+
+    /// Advances past `num_bytes` without keeping them, or returns an `End` error. The bytes are read
+    /// in bounded pieces so that skipping a large chunk does not allocate a buffer of its size.
+    pub(crate) fn skip(&mut self, num_bytes: u64) -> ByteResult<()> {
+        const PIECE: u64 = 64 * 1024;
+        let mut remaining = num_bytes;
+        while remaining > 0 {
+            let n = remaining.min(PIECE);
+            self.read_n(n as usize)?;
+            remaining -= n;
+        }
+        Ok(())
+    }
+
+    fn read_n_bytewise(&mut self, num_bytes: usize) -> ByteResult<Vec<u8>> {
         let mut bytes = Vec::with_capacity(num_bytes);
         for _ in 0..num_bytes {
             bytes.push(self.read_or_die()?)
@@ -336,6 +544,14 @@ impl<R: Read> ByteIter<R> {
         self.running_status_detected = true;
     }
 
+    pub(crate) fn set_settings(&mut self, settings: ReadSettings) {
+        self.settings = settings;
+    }
+
+    pub(crate) fn settings(&self) -> ReadSettings {
+        self.settings
+    }
+
     pub(crate) fn is_running_status_detected(&self) -> bool {
         self.running_status_detected
     }
@@ -346,7 +562,7 @@ fn byte_iter_test() {
     use std::io::Cursor;
     let bytes = [0x00u8, 0x01, 0x02, 0x03, 0x04, 0x10, 0x20, 0x30, 0x40];
     let cursor = Cursor::new(bytes);
-    let mut iter = ByteIter::new(cursor.bytes()).unwrap();
+    let mut iter = ByteIter::new(cursor).unwrap();
     assert!(iter.current.is_none());
     assert_eq!(0x00, iter.read().unwrap().unwrap());
     assert_eq!(0x00, iter.current.unwrap());
@@ -365,3 +581,63 @@ fn byte_iter_test() {
     iter.clear_size_limit();
     assert_eq!(0x10, iter.read().unwrap().unwrap());
 }
+
+#[test]
+fn byte_iter_from_slice_test() {
+    use std::io::Cursor;
+    let bytes: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+    let mut from_read = ByteIter::new(Cursor::new(bytes.as_slice())).unwrap();
+    let mut from_slice = ByteIter::from_slice(&bytes).unwrap();
+    assert_eq!(from_read.read2().unwrap(), from_slice.read2().unwrap());
+    assert_eq!(
+        from_read.read_u32().unwrap(),
+        from_slice.read_u32().unwrap()
+    );
+    assert_eq!(
+        from_read.peek_or_die().unwrap(),
+        from_slice.peek_or_die().unwrap()
+    );
+    assert_eq!(
+        from_read.read_n(994).unwrap(),
+        from_slice.read_n(994).unwrap()
+    );
+    assert!(from_read.read_or_die().is_err());
+    assert!(from_slice.read_or_die().is_err());
+}
+
+#[test]
+fn read_n_bulk_test() {
+    use std::io::Cursor;
+    let data: Vec<u8> = (0..100 * 1024).map(|i| (i % 251) as u8).collect();
+
+    fn check<S: ByteSource>(mut bulk: ByteIter<S>, mut naive: ByteIter<S>) {
+        for size in [0usize, 1, 3, 4, 7, 50_000, 2] {
+            assert_eq!(
+                bulk.read_n(size).unwrap(),
+                naive.read_n_bytewise(size).unwrap()
+            );
+            assert_eq!(bulk.position, naive.position);
+            assert_eq!(bulk.current, naive.current);
+            assert_eq!(bulk.peek1, naive.peek1);
+        }
+        // the size limit is respected
+        bulk.set_size_limit(10);
+        naive.set_size_limit(10);
+        assert!(bulk.read_n(11).is_err());
+        assert!(naive.read_n_bytewise(11).is_err());
+        assert_eq!(bulk.position, naive.position);
+        bulk.clear_size_limit();
+        naive.clear_size_limit();
+        // reading past the end is an error
+        assert!(bulk.read_n(100 * 1024).is_err());
+    }
+
+    check(
+        ByteIter::new(Cursor::new(data.as_slice())).unwrap(),
+        ByteIter::new(Cursor::new(data.as_slice())).unwrap(),
+    );
+    check(
+        ByteIter::from_slice(&data).unwrap(),
+        ByteIter::from_slice(&data).unwrap(),
+    );
+}
@@ -1,9 +1,12 @@
+use crate::core::DurationName;
+use std::fmt::{Display, Formatter};
+
 /// There are 24 MIDI Clocks in every quarter note. (12 MIDI Clocks in an eighth note, 6 MIDI Clocks
 /// in a 16th, etc). One example of using this enum is in the `TimeSignature`, where we can specify
 /// the frequency of the metronome click.
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash, Default)]
 pub enum Clocks {
-    /// 142 MIDI clocks.
+    /// 144 MIDI clocks.
     DottedWhole,
 
     /// 96 MIDI clocks.
@@ -15,7 +18,7 @@ pub enum Clocks {
     /// 48 MIDI clocks.
     Half,
 
-    /// 32 MIDI clocks.
+    /// 36 MIDI clocks.
     DottedQuarter,
 
     /// 24 MIDI clocks.
@@ -43,11 +46,11 @@ impl Clocks {
     /// falling back to `Other` if the value does not correspond to one of the named variants.
     pub(crate) fn from_u8(v: u8) -> Clocks {
         match v {
-            142 => Clocks::DottedWhole,
+            144 => Clocks::DottedWhole,
             96 => Clocks::Whole,
             72 => Clocks::DottedHalf,
             48 => Clocks::Half,
-            32 => Clocks::DottedQuarter,
+            36 => Clocks::DottedQuarter,
             24 => Clocks::Quarter,
             18 => Clocks::DottedEighth,
             12 => Clocks::Eighth,
@@ -60,11 +63,11 @@ impl Clocks {
     // Get the `u8` value represented by the enum.
     pub(crate) fn to_u8(self) -> u8 {
         match self {
-            Clocks::DottedWhole => 142,
+            Clocks::DottedWhole => 144,
             Clocks::Whole => 96,
             Clocks::DottedHalf => 72,
             Clocks::Half => 48,
-            Clocks::DottedQuarter => 32,
+            Clocks::DottedQuarter => 36,
             Clocks::Quarter => 24,
             Clocks::DottedEighth => 18,
             Clocks::Eighth => 12,
@@ -91,4 +94,59 @@ impl Clocks {
     pub fn resolve(&mut self) {
         *self = Self::from_u8(self.to_u8())
     }
+
+    /// The number of MIDI clocks in a quarter note, which is always 24.
+    pub const fn per_quarter() -> u8 {
+        24
+    }
+
+    /// The number of MIDI clocks represented by the value.
+    pub fn get(&self) -> u8 {
+        self.to_u8()
+    }
+
+    /// The `Clocks` value for the length of a `DurationName`. Durations that are shorter than a
+    /// sixteenth note are returned as `Other`, and those shorter than a sixty-fourth note are less
+    /// than one MIDI clock and are rounded down to zero.
+    pub fn from_duration(duration: DurationName) -> Clocks {
+        let whole = u16::from(Self::per_quarter()) * 4;
+        Self::from_u8((whole >> (duration as u8)) as u8)
+    }
+}
+
+impl Display for Clocks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Clocks::DottedWhole => f.write_str("dotted whole"),
+            Clocks::Whole => f.write_str("whole"),
+            Clocks::DottedHalf => f.write_str("dotted half"),
+            Clocks::Half => f.write_str("half"),
+            Clocks::DottedQuarter => f.write_str("dotted quarter"),
+            Clocks::Quarter => f.write_str("quarter"),
+            Clocks::DottedEighth => f.write_str("dotted eighth"),
+            Clocks::Eighth => f.write_str("eighth"),
+            Clocks::DottedSixteenth => f.write_str("dotted sixteenth"),
+            Clocks::Sixteenth => f.write_str("sixteenth"),
+            Clocks::Other(v) => write!(f, "{} clocks", v),
+        }
+    }
+}
+
+#[test]
+fn clocks_values_test() {
+    assert_eq!(Clocks::DottedQuarter.get(), 36);
+    assert_eq!(Clocks::DottedWhole.get(), 144);
+    assert_eq!(Clocks::new(36), Clocks::DottedQuarter);
+    assert_eq!(Clocks::Quarter.get(), Clocks::per_quarter());
+    assert_eq!(Clocks::DottedQuarter.to_string(), "dotted quarter");
+    assert_eq!(Clocks::Other(7).to_string(), "7 clocks");
+}
+
+#[test]
+fn clocks_from_duration_test() {
+    assert_eq!(Clocks::from_duration(DurationName::Eighth), Clocks::Eighth);
+    assert_eq!(Clocks::from_duration(DurationName::Eighth).get(), 12);
+    assert_eq!(Clocks::from_duration(DurationName::Whole), Clocks::Whole);
+    assert_eq!(Clocks::from_duration(DurationName::D32), Clocks::Other(3));
+    assert_eq!(Clocks::from_duration(DurationName::D1024), Clocks::Other(0));
 }
@@ -1,4 +1,5 @@
 use crate::error::LibResult;
+use crate::file::QuarterNoteDivision;
 use crate::Error;
 use std::convert::TryFrom;
 
@@ -63,6 +64,35 @@ impl DurationName {
             _ => crate::error::OtherSnafu { site: site!() }.fail(),
         }
     }
+
+    /// The length of this duration in delta-time ticks, given the number of ticks per quarter note.
+    /// For example, with a `ppq` of 1024, a `Quarter` is 1024 ticks, an `Eighth` is 512 and a
+    /// `Whole` is 4096. Short durations are rounded down, so they may be zero at a low `ppq`.
+    /// Dotted and tuplet durations are not represented by `DurationName`.
+    pub fn ticks(&self, ppq: QuarterNoteDivision) -> u32 {
+        (u32::from(ppq.get()) * 4) >> (*self as u8)
+    }
+
+    /// Find the `DurationName` whose length in ticks is closest to `ticks`, given the number of
+    /// ticks per quarter note. The `bool` is `true` if the length matches exactly. When `ticks` is
+    /// halfway between two durations, the longer one is returned.
+    pub fn nearest(ticks: u32, ppq: QuarterNoteDivision) -> (DurationName, bool) {
+        let mut nearest = DurationName::Whole;
+        let mut nearest_distance = u32::MAX;
+        for v in DurationName::Whole as u8..=DurationName::D1024 as u8 {
+            // the values in this range are all valid
+            let duration = match Self::from_u8(v) {
+                Ok(ok) => ok,
+                Err(_) => continue,
+            };
+            let distance = ticks.abs_diff(duration.ticks(ppq));
+            if distance < nearest_distance {
+                nearest = duration;
+                nearest_distance = distance;
+            }
+        }
+        (nearest, nearest_distance == 0)
+    }
 }
 
 impl TryFrom<u8> for DurationName {
@@ -72,3 +102,35 @@ impl TryFrom<u8> for DurationName {
         Ok(Self::from_u8(value)?)
     }
 }
+
+#[test]
+fn duration_name_ticks_test() {
+    let ppq = QuarterNoteDivision::new(1024);
+    assert_eq!(DurationName::Whole.ticks(ppq), 4096);
+    assert_eq!(DurationName::Half.ticks(ppq), 2048);
+    assert_eq!(DurationName::Quarter.ticks(ppq), 1024);
+    assert_eq!(DurationName::Eighth.ticks(ppq), 512);
+    assert_eq!(DurationName::D1024.ticks(ppq), 4);
+}
+
+#[test]
+fn duration_name_nearest_test() {
+    let ppq = QuarterNoteDivision::new(1024);
+    assert_eq!(
+        DurationName::nearest(512, ppq),
+        (DurationName::Eighth, true)
+    );
+    assert_eq!(
+        DurationName::nearest(4096, ppq),
+        (DurationName::Whole, true)
+    );
+    assert_eq!(
+        DurationName::nearest(1000, ppq),
+        (DurationName::Quarter, false)
+    );
+    assert_eq!(
+        DurationName::nearest(99999, ppq),
+        (DurationName::Whole, false)
+    );
+    assert_eq!(DurationName::nearest(0, ppq), (DurationName::D1024, false));
+}
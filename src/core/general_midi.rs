@@ -1,3 +1,6 @@
+use crate::core::{NoteNumber, Program};
+use std::fmt::{Display, Formatter};
+
 /// `GeneralMidi` represents the standard set of instruments that are intended to be available by
 /// all MIDI implementations.
 #[repr(u8)]
@@ -277,3 +280,229 @@ impl From<GeneralMidi> for u8 {
         gm as u8
     }
 }
+
+impl GeneralMidi {
+    /// The inverse of the conversion used by [`crate::file::Track::set_general_midi`], i.e. this
+    /// library numbers the General MIDI patches from `1` to `128` and writes that number as the
+    /// program value. Returns `None` if the program value is `0`, which is not a patch number.
+    pub fn from_program(program: Program) -> Option<GeneralMidi> {
+        match program.get() {
+            0 => None,
+            x => Some(GeneralMidi::from(x)),
+        }
+    }
+
+    /// The official General MIDI name of the patch, e.g. `"Acoustic Grand Piano"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeneralMidi::AcousticGrandPiano => "Acoustic Grand Piano",
+            GeneralMidi::BrightAcousticPiano => "Bright Acoustic Piano",
+            GeneralMidi::ElectricGrandPiano => "Electric Grand Piano",
+            GeneralMidi::HonkyTonkPiano => "Honky-tonk Piano",
+            GeneralMidi::ElectricPiano1 => "Electric Piano 1",
+            GeneralMidi::ElectricPiano2 => "Electric Piano 2",
+            GeneralMidi::Harpsichord => "Harpsichord",
+            GeneralMidi::Clavi => "Clavi",
+            GeneralMidi::Celesta => "Celesta",
+            GeneralMidi::Glockenspiel => "Glockenspiel",
+            GeneralMidi::MusicBox => "Music Box",
+            GeneralMidi::Vibraphone => "Vibraphone",
+            GeneralMidi::Marimba => "Marimba",
+            GeneralMidi::Xylophone => "Xylophone",
+            GeneralMidi::TubularBells => "Tubular Bells",
+            GeneralMidi::Dulcimer => "Dulcimer",
+            GeneralMidi::DrawbarOrgan => "Drawbar Organ",
+            GeneralMidi::PercussiveOrgan => "Percussive Organ",
+            GeneralMidi::RockOrgan => "Rock Organ",
+            GeneralMidi::ChurchOrgan => "Church Organ",
+            GeneralMidi::ReedOrgan => "Reed Organ",
+            GeneralMidi::Accordion => "Accordion",
+            GeneralMidi::Harmonica => "Harmonica",
+            GeneralMidi::TangoAccordion => "Tango Accordion",
+            GeneralMidi::AcousticGuitarNylon => "Acoustic Guitar (nylon)",
+            GeneralMidi::AcousticGuitarSteel => "Acoustic Guitar (steel)",
+            GeneralMidi::ElectricGuitarJazz => "Electric Guitar (jazz)",
+            GeneralMidi::ElectricGuitarClean => "Electric Guitar (clean)",
+            GeneralMidi::ElectricGuitarMuted => "Electric Guitar (muted)",
+            GeneralMidi::OverdrivenGuitar => "Overdriven Guitar",
+            GeneralMidi::DistortionGuitar => "Distortion Guitar",
+            GeneralMidi::GuitarHarmonics => "Guitar harmonics",
+            GeneralMidi::AcousticBass => "Acoustic Bass",
+            GeneralMidi::ElectricBassFinger => "Electric Bass (finger)",
+            GeneralMidi::ElectricBassPick => "Electric Bass (pick)",
+            GeneralMidi::FretlessBass => "Fretless Bass",
+            GeneralMidi::SlapBass1 => "Slap Bass 1",
+            GeneralMidi::SlapBass2 => "Slap Bass 2",
+            GeneralMidi::SynthBass1 => "Synth Bass 1",
+            GeneralMidi::SynthBass2 => "Synth Bass 2",
+            GeneralMidi::Violin => "Violin",
+            GeneralMidi::Viola => "Viola",
+            GeneralMidi::Cello => "Cello",
+            GeneralMidi::Contrabass => "Contrabass",
+            GeneralMidi::TremoloStrings => "Tremolo Strings",
+            GeneralMidi::PizzicatoStrings => "Pizzicato Strings",
+            GeneralMidi::OrchestralHarp => "Orchestral Harp",
+            GeneralMidi::Timpani => "Timpani",
+            GeneralMidi::StringEnsemble1 => "String Ensemble 1",
+            GeneralMidi::StringEnsemble2 => "String Ensemble 2",
+            GeneralMidi::SynthStrings1 => "SynthStrings 1",
+            GeneralMidi::SynthStrings2 => "SynthStrings 2",
+            GeneralMidi::ChoirAahs => "Choir Aahs",
+            GeneralMidi::VoiceOohs => "Voice Oohs",
+            GeneralMidi::SynthVoice => "Synth Voice",
+            GeneralMidi::OrchestraHit => "Orchestra Hit",
+            GeneralMidi::Trumpet => "Trumpet",
+            GeneralMidi::Trombone => "Trombone",
+            GeneralMidi::Tuba => "Tuba",
+            GeneralMidi::MutedTrumpet => "Muted Trumpet",
+            GeneralMidi::FrenchHorn => "French Horn",
+            GeneralMidi::BrassSection => "Brass Section",
+            GeneralMidi::SynthBrass1 => "SynthBrass 1",
+            GeneralMidi::SynthBrass2 => "SynthBrass 2",
+            GeneralMidi::SopranoSax => "Soprano Sax",
+            GeneralMidi::AltoSax => "Alto Sax",
+            GeneralMidi::TenorSax => "Tenor Sax",
+            GeneralMidi::BaritoneSax => "Baritone Sax",
+            GeneralMidi::Oboe => "Oboe",
+            GeneralMidi::EnglishHorn => "English Horn",
+            GeneralMidi::Bassoon => "Bassoon",
+            GeneralMidi::Clarinet => "Clarinet",
+            GeneralMidi::Piccolo => "Piccolo",
+            GeneralMidi::Flute => "Flute",
+            GeneralMidi::Recorder => "Recorder",
+            GeneralMidi::PanFlute => "Pan Flute",
+            GeneralMidi::BlownBottle => "Blown Bottle",
+            GeneralMidi::Shakuhachi => "Shakuhachi",
+            GeneralMidi::Whistle => "Whistle",
+            GeneralMidi::Ocarina => "Ocarina",
+            GeneralMidi::Lead1Square => "Lead 1 (square)",
+            GeneralMidi::Lead2Sawtooth => "Lead 2 (sawtooth)",
+            GeneralMidi::Lead3Calliope => "Lead 3 (calliope)",
+            GeneralMidi::Lead4Chiff => "Lead 4 (chiff)",
+            GeneralMidi::Lead5Charang => "Lead 5 (charang)",
+            GeneralMidi::Lead6Voice => "Lead 6 (voice)",
+            GeneralMidi::Lead7Fifths => "Lead 7 (fifths)",
+            GeneralMidi::Lead8BassPlusLead => "Lead 8 (bass + lead)",
+            GeneralMidi::Pad1Newage => "Pad 1 (new age)",
+            GeneralMidi::Pad2Warm => "Pad 2 (warm)",
+            GeneralMidi::Pad3Polysynth => "Pad 3 (polysynth)",
+            GeneralMidi::Pad4Choir => "Pad 4 (choir)",
+            GeneralMidi::Pad5Bowed => "Pad 5 (bowed)",
+            GeneralMidi::Pad6Metallic => "Pad 6 (metallic)",
+            GeneralMidi::Pad7Halo => "Pad 7 (halo)",
+            GeneralMidi::Pad8Sweep => "Pad 8 (sweep)",
+            GeneralMidi::Fx1Rain => "FX 1 (rain)",
+            GeneralMidi::Fx2Soundtrack => "FX 2 (soundtrack)",
+            GeneralMidi::Fx3Crystal => "FX 3 (crystal)",
+            GeneralMidi::Fx4Atmosphere => "FX 4 (atmosphere)",
+            GeneralMidi::Fx5Brightness => "FX 5 (brightness)",
+            GeneralMidi::Fx6Goblins => "FX 6 (goblins)",
+            GeneralMidi::Fx7Echoes => "FX 7 (echoes)",
+            GeneralMidi::Fx8SciFi => "FX 8 (sci-fi)",
+            GeneralMidi::Sitar => "Sitar",
+            GeneralMidi::Banjo => "Banjo",
+            GeneralMidi::Shamisen => "Shamisen",
+            GeneralMidi::Koto => "Koto",
+            GeneralMidi::Kalimba => "Kalimba",
+            GeneralMidi::Bagpipe => "Bag pipe",
+            GeneralMidi::Fiddle => "Fiddle",
+            GeneralMidi::Shanai => "Shanai",
+            GeneralMidi::TinkleBell => "Tinkle Bell",
+            GeneralMidi::Agogo => "Agogo",
+            GeneralMidi::SteelDrums => "Steel Drums",
+            GeneralMidi::Woodblock => "Woodblock",
+            GeneralMidi::TaikoDrum => "Taiko Drum",
+            GeneralMidi::MelodicTom => "Melodic Tom",
+            GeneralMidi::SynthDrum => "Synth Drum",
+            GeneralMidi::ReverseCymbal => "Reverse Cymbal",
+            GeneralMidi::GuitarFretNoise => "Guitar Fret Noise",
+            GeneralMidi::BreathNoise => "Breath Noise",
+            GeneralMidi::Seashore => "Seashore",
+            GeneralMidi::BirdTweet => "Bird Tweet",
+            GeneralMidi::TelephoneRing => "Telephone Ring",
+            GeneralMidi::Helicopter => "Helicopter",
+            GeneralMidi::Applause => "Applause",
+            GeneralMidi::Gunshot => "Gunshot",
+        }
+    }
+
+    /// The General MIDI percussion key map. On the percussion channel (channel 10, i.e.
+    /// `Channel::new(9)`) each note number plays a different drum sound. Returns the name of that
+    /// sound, or `None` if the note number is outside the General MIDI percussion range (`35` to
+    /// `81`).
+    pub fn percussion_name(note: NoteNumber) -> Option<&'static str> {
+        match note.get() {
+            35 => Some("Acoustic Bass Drum"),
+            36 => Some("Bass Drum 1"),
+            37 => Some("Side Stick"),
+            38 => Some("Acoustic Snare"),
+            39 => Some("Hand Clap"),
+            40 => Some("Electric Snare"),
+            41 => Some("Low Floor Tom"),
+            42 => Some("Closed Hi Hat"),
+            43 => Some("High Floor Tom"),
+            44 => Some("Pedal Hi-Hat"),
+            45 => Some("Low Tom"),
+            46 => Some("Open Hi-Hat"),
+            47 => Some("Low-Mid Tom"),
+            48 => Some("Hi-Mid Tom"),
+            49 => Some("Crash Cymbal 1"),
+            50 => Some("High Tom"),
+            51 => Some("Ride Cymbal 1"),
+            52 => Some("Chinese Cymbal"),
+            53 => Some("Ride Bell"),
+            54 => Some("Tambourine"),
+            55 => Some("Splash Cymbal"),
+            56 => Some("Cowbell"),
+            57 => Some("Crash Cymbal 2"),
+            58 => Some("Vibraslap"),
+            59 => Some("Ride Cymbal 2"),
+            60 => Some("Hi Bongo"),
+            61 => Some("Low Bongo"),
+            62 => Some("Mute Hi Conga"),
+            63 => Some("Open Hi Conga"),
+            64 => Some("Low Conga"),
+            65 => Some("High Timbale"),
+            66 => Some("Low Timbale"),
+            67 => Some("High Agogo"),
+            68 => Some("Low Agogo"),
+            69 => Some("Cabasa"),
+            70 => Some("Maracas"),
+            71 => Some("Short Whistle"),
+            72 => Some("Long Whistle"),
+            73 => Some("Short Guiro"),
+            74 => Some("Long Guiro"),
+            75 => Some("Claves"),
+            76 => Some("Hi Wood Block"),
+            77 => Some("Low Wood Block"),
+            78 => Some("Mute Cuica"),
+            79 => Some("Open Cuica"),
+            80 => Some("Mute Triangle"),
+            81 => Some("Open Triangle"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GeneralMidi {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[test]
+fn general_midi_program_round_trip_test() {
+    let program = Program::new(GeneralMidi::SynthVoice.into());
+    assert_eq!(0x37, program.get());
+    assert_eq!(
+        Some(GeneralMidi::SynthVoice),
+        GeneralMidi::from_program(program)
+    );
+    assert_eq!("Synth Voice", format!("{}", GeneralMidi::SynthVoice));
+    assert_eq!(None, GeneralMidi::from_program(Program::new(0)));
+    assert_eq!(
+        Some("Acoustic Snare"),
+        GeneralMidi::percussion_name(NoteNumber::new(38))
+    );
+    assert_eq!(None, GeneralMidi::percussion_name(NoteNumber::new(34)));
+}
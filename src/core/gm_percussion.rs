@@ -0,0 +1,86 @@
+use crate::core::{GeneralMidi, NoteNumber};
+use std::fmt::{Display, Formatter};
+
+/// The General MIDI percussion key map. On the percussion channel (channel 10, i.e.
+/// `Channel::new(9)`) each note number plays a different drum sound. The value of each variant is
+/// its note number.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[allow(missing_docs)]
+#[derive(Default)]
+pub enum GmPercussion {
+    AcousticBassDrum = 35,
+    #[default]
+    BassDrum1 = 36,
+    SideStick = 37,
+    AcousticSnare = 38,
+    HandClap = 39,
+    ElectricSnare = 40,
+    LowFloorTom = 41,
+    ClosedHiHat = 42,
+    HighFloorTom = 43,
+    PedalHiHat = 44,
+    LowTom = 45,
+    OpenHiHat = 46,
+    LowMidTom = 47,
+    HiMidTom = 48,
+    CrashCymbal1 = 49,
+    HighTom = 50,
+    RideCymbal1 = 51,
+    ChineseCymbal = 52,
+    RideBell = 53,
+    Tambourine = 54,
+    SplashCymbal = 55,
+    Cowbell = 56,
+    CrashCymbal2 = 57,
+    Vibraslap = 58,
+    RideCymbal2 = 59,
+    HiBongo = 60,
+    LowBongo = 61,
+    MuteHiConga = 62,
+    OpenHiConga = 63,
+    LowConga = 64,
+    HighTimbale = 65,
+    LowTimbale = 66,
+    HighAgogo = 67,
+    LowAgogo = 68,
+    Cabasa = 69,
+    Maracas = 70,
+    ShortWhistle = 71,
+    LongWhistle = 72,
+    ShortGuiro = 73,
+    LongGuiro = 74,
+    Claves = 75,
+    HiWoodBlock = 76,
+    LowWoodBlock = 77,
+    MuteCuica = 78,
+    OpenCuica = 79,
+    MuteTriangle = 80,
+    OpenTriangle = 81,
+}
+
+impl GmPercussion {
+    /// The note number that plays this sound on the percussion channel.
+    pub fn note_number(&self) -> NoteNumber {
+        NoteNumber::new(*self as u8)
+    }
+
+    /// The name of the sound, see [`GeneralMidi::percussion_name`].
+    pub fn name(&self) -> &'static str {
+        GeneralMidi::percussion_name(self.note_number()).unwrap_or_default()
+    }
+}
+
+impl Display for GmPercussion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[test]
+fn gm_percussion_test() {
+    assert_eq!(GmPercussion::AcousticSnare.note_number().get(), 38);
+    assert_eq!(GmPercussion::AcousticSnare.to_string(), "Acoustic Snare");
+    assert_eq!(GmPercussion::AcousticBassDrum.name(), "Acoustic Bass Drum");
+    assert_eq!(GmPercussion::OpenTriangle.name(), "Open Triangle");
+}
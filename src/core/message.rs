@@ -1,4 +1,4 @@
-use crate::byte_iter::ByteIter;
+use crate::byte_iter::{ByteIter, ByteSource};
 use crate::core::bits::{decode_14_bit_number, encode_14_bit_number};
 use crate::core::{
     Channel, ControlValue, MonoModeChannels, NoteNumber, PitchBendValue, Program, StatusType,
@@ -9,15 +9,19 @@ use crate::scribe::Scribe;
 use log::{trace, warn};
 use snafu::{OptionExt, ResultExt};
 use std::convert::TryFrom;
-use std::io::{Read, Write};
+use std::io::Write;
 
 pub(crate) trait WriteBytes {
     fn write<W: Write>(&self, w: &mut Scribe<W>) -> LibResult<()>;
 }
 
 /// Represents the data that is common, and required for both [`Message::NoteOn`] and
-/// [`Message::NoteOff`] messages.
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+/// [`Message::NoteOff`] messages. It is also used for [`Message::PolyPressure`], in which case the
+/// `velocity` is the pressure amount.
+///
+/// The `Debug` output shows the note by name, e.g.
+/// `NoteMessage { channel: 0, note: C4, velocity: 64 }`.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct NoteMessage {
     pub(crate) channel: Channel,
     pub(crate) note_number: NoteNumber,
@@ -25,6 +29,15 @@ pub struct NoteMessage {
 }
 
 impl NoteMessage {
+    /// Create a new `NoteMessage`.
+    pub fn new(channel: Channel, note_number: NoteNumber, velocity: Velocity) -> Self {
+        Self {
+            channel,
+            note_number,
+            velocity,
+        }
+    }
+
     /// Getter for the `channel` field.
     pub fn channel(&self) -> Channel {
         self.channel
@@ -35,12 +48,13 @@ impl NoteMessage {
         self.note_number
     }
 
-    /// Getter for the `velocity` field.
+    /// Getter for the `velocity` field. For a [`Message::PolyPressure`] message this is the
+    /// pressure amount.
     pub fn velocity(&self) -> Velocity {
         self.velocity
     }
 
-    fn parse<R: Read>(iter: &mut ByteIter<R>, channel: Channel) -> LibResult<Self> {
+    fn parse<S: ByteSource>(iter: &mut ByteIter<S>, channel: Channel) -> LibResult<Self> {
         Ok(NoteMessage {
             channel,
             note_number: iter.read_or_die().context(io!())?.into(),
@@ -58,6 +72,16 @@ impl NoteMessage {
     }
 }
 
+impl std::fmt::Debug for NoteMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NoteMessage")
+            .field("channel", &self.channel.get())
+            .field("note", &format_args!("{}", self.note_number.name()))
+            .field("velocity", &self.velocity.get())
+            .finish()
+    }
+}
+
 /// Provides the ability to change an instrument (sound, patch, etc.) by specifying the affected
 /// channel number and the new program value.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -67,6 +91,11 @@ pub struct ProgramChangeValue {
 }
 
 impl ProgramChangeValue {
+    /// Create a new `ProgramChangeValue`.
+    pub fn new(channel: Channel, program: Program) -> Self {
+        Self { channel, program }
+    }
+
     /// Get the channel value.
     pub fn channel(&self) -> &Channel {
         &self.channel
@@ -86,10 +115,38 @@ impl WriteBytes for ProgramChangeValue {
     }
 }
 
-// TODO - unused?
-/// Maybe unused.
+/// Channel pressure (aftertouch) applies a single pressure value to every note sounding on the
+/// channel, unlike [`Message::PolyPressure`] which has a value for each note.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct ChannelPressureMessage {}
+pub struct ChannelPressureMessage {
+    pub(crate) channel: Channel,
+    pub(crate) pressure: Velocity,
+}
+
+impl ChannelPressureMessage {
+    /// Create a new `ChannelPressureMessage`.
+    pub fn new(channel: Channel, pressure: Velocity) -> Self {
+        Self { channel, pressure }
+    }
+
+    /// A getter for the `channel` field.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// A getter for the `pressure` field.
+    pub fn pressure(&self) -> Velocity {
+        self.pressure
+    }
+}
+
+impl WriteBytes for ChannelPressureMessage {
+    fn write<W: Write>(&self, w: &mut Scribe<W>) -> LibResult<()> {
+        write_status_byte(w, StatusType::ChannelPressure, self.channel)?;
+        write_u8!(w, self.pressure.get())?;
+        Ok(())
+    }
+}
 
 /// Provides the ability to pitch bend a channel by specifying a pitch bend value between
 /// 0 and 16383 where 8192 (the middle) is no pitch bend. Above 8192 bends the note up and
@@ -102,6 +159,14 @@ pub struct PitchBendMessage {
 }
 
 impl PitchBendMessage {
+    /// Create a new `PitchBendMessage`.
+    pub fn new(channel: Channel, pitch_bend: PitchBendValue) -> Self {
+        Self {
+            channel,
+            pitch_bend,
+        }
+    }
+
     /// Get the channel value.
     pub fn channel(&self) -> &Channel {
         &self.channel
@@ -139,12 +204,15 @@ pub enum ModeMessage {
     PolyModeOn,
 }
 
+/// The setting of an on/off channel mode message such as Local Control.
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[allow(dead_code)]
 #[derive(Default)]
 pub enum OnOff {
+    /// Sent as a value of `127`.
     On = 127,
+    /// Sent as a value of `0`.
     #[default]
     Off = 0,
 }
@@ -172,6 +240,11 @@ impl Default for ModeMessage {
 }
 
 impl LocalControlValue {
+    /// Create a new `LocalControlValue`.
+    pub fn new(channel: Channel, on_off: OnOff) -> Self {
+        Self { channel, on_off }
+    }
+
     /// A getter for the `channel` field.
     pub fn channel(&self) -> Channel {
         self.channel
@@ -192,6 +265,15 @@ pub struct MonoModeOnValue {
 }
 
 impl MonoModeOnValue {
+    /// Create a new `MonoModeOnValue`. A `mono_mode_channels` value of `0` means that the number
+    /// of channels is equal to the number of voices in the receiver.
+    pub fn new(channel: Channel, mono_mode_channels: MonoModeChannels) -> Self {
+        Self {
+            channel,
+            mono_mode_channels,
+        }
+    }
+
     /// A getter for the `channel` field.
     pub fn channel(&self) -> Channel {
         self.channel
@@ -290,6 +372,11 @@ pub enum Message {
     SongPositionPointer(SongPositionPointerMessage),
     SongSelect(SongSelectMessage),
     TuneRequest,
+    /// A bare `0xF7` (EOX) which ends a system exclusive message in a stream of MIDI bytes. In a
+    /// file, an event that begins with `0xF7` is an F7 sysex event instead, see [`SysexEvent`], so
+    /// this message cannot be written to a file.
+    ///
+    /// [`SysexEvent`]: crate::file::SysexEvent
     EndOfSysexFlag,
     TimingClock,
     Undefined1,
@@ -298,6 +385,8 @@ pub enum Message {
     Stop,
     Undefined2,
     ActiveSensing,
+    /// `0xFF`, which can be read from a stream of MIDI bytes but cannot be written to a file, where
+    /// the same byte begins a meta event.
     SystemReset,
 }
 
@@ -308,7 +397,40 @@ impl Default for Message {
 }
 
 impl Message {
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
+    /// The channel of a channel voice or channel mode message, or `None` for a system message.
+    pub fn channel(&self) -> Option<Channel> {
+        match self {
+            Message::NoteOff(m) | Message::NoteOn(m) | Message::PolyPressure(m) => Some(m.channel),
+            Message::Control(c) => Some(c.channel),
+            Message::ProgramChange(p) => Some(p.channel),
+            Message::ChannelPressure(c) => Some(c.channel),
+            Message::PitchBend(p) => Some(p.channel),
+            Message::MonoModeOn(m) => Some(m.channel),
+            Message::AllSoundsOff(c)
+            | Message::ResetAllControllers(c)
+            | Message::LocalControlOff(c)
+            | Message::LocalControlOn(c)
+            | Message::AllNotesOff(c)
+            | Message::OmniModeOff(c)
+            | Message::OmniModeOn(c)
+            | Message::PolyModeOn(c) => Some(*c),
+            Message::MidiTimeCodeQuarterFrame(_)
+            | Message::SongPositionPointer(_)
+            | Message::SongSelect(_)
+            | Message::TuneRequest
+            | Message::EndOfSysexFlag
+            | Message::TimingClock
+            | Message::Undefined1
+            | Message::Start
+            | Message::Continue
+            | Message::Stop
+            | Message::Undefined2
+            | Message::ActiveSensing
+            | Message::SystemReset => None,
+        }
+    }
+
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
         // check if the first byte is a status byte. if not, then this should be a running status
         // message.
         let byte = if matches!(iter.peek_or_die().context(io!())?, 0x00..=0x7F) {
@@ -337,6 +459,9 @@ impl Message {
             }
             x if SystemRealtimeMessage::SystemReset as u8 == x => return Ok(Message::SystemReset),
             0xf0 => noimpl!("sysex: https://github.com/webern/midi_file/issues/7"),
+            // an F7 at the start of a file event is dispatched to `SysexEvent` before we get here,
+            // so this is only reached for a bare EOX in a stream of messages.
+            0xf7 => return Ok(Message::EndOfSysexFlag),
             _ => {}
         }
         // now check if it is a channel voice message or channel mode message
@@ -356,10 +481,14 @@ impl Message {
                 }))
             }
             StatusType::ChannelPressure => {
-                noimpl!("channel pressure: https://github.com/webern/midi_file/issues/X")
+                let pressure: Velocity = iter.read_or_die().context(io!())?.into();
+                Ok(Message::ChannelPressure(ChannelPressureMessage {
+                    channel,
+                    pressure,
+                }))
             }
             StatusType::PitchBend => {
-                let value = iter.read_u16().unwrap();
+                let value = iter.read_u16().context(io!())?;
                 let decoded = decode_14_bit_number(value);
                 Ok(Message::PitchBend(PitchBendMessage {
                     channel,
@@ -372,14 +501,15 @@ impl Message {
 
     pub(crate) fn write<W: Write>(&self, w: &mut Scribe<W>) -> LibResult<()> {
         match self {
+            Message::NoteOff(value) if w.compress_note_offs() && value.velocity.get() == 0 => {
+                value.write(w, StatusType::NoteOn)
+            }
             Message::NoteOff(value) => value.write(w, StatusType::NoteOff),
             Message::NoteOn(value) => value.write(w, StatusType::NoteOn),
             Message::PolyPressure(value) => value.write(w, StatusType::PolyPressure),
             Message::Control(value) => value.write(w),
             Message::ProgramChange(value) => value.write(w),
-            Message::ChannelPressure(_) => {
-                noimpl!("ChannelPressure: https://github.com/webern/midi_file/issues/X")
-            }
+            Message::ChannelPressure(value) => value.write(w),
             Message::PitchBend(value) => value.write(w),
             Message::AllSoundsOff(channel) => write_chanmod(w, *channel, CONTROL_ALL_SOUNDS_OFF, 0),
             Message::ResetAllControllers(channel) => {
@@ -413,25 +543,25 @@ impl Message {
             Message::TuneRequest => {
                 noimpl!("TuneRequest: https://github.com/webern/midi_file/issues/10")
             }
-            Message::EndOfSysexFlag => {
-                noimpl!("EndOfSysexFlag: https://github.com/webern/midi_file/issues/10")
-            }
-            Message::TimingClock => {
-                noimpl!("TimingClock: https://github.com/webern/midi_file/issues/10")
-            }
-            Message::Undefined1 => {
-                noimpl!("Undefined1: https://github.com/webern/midi_file/issues/10")
-            }
-            Message::Start => noimpl!("Start: https://github.com/webern/midi_file/issues/10"),
-            Message::Continue => noimpl!("Continue: https://github.com/webern/midi_file/issues/10"),
-            Message::Stop => noimpl!("Stop: https://github.com/webern/midi_file/issues/10"),
-            Message::Undefined2 => noimpl!(""),
-            Message::ActiveSensing => {
-                noimpl!("ActiveSensing: https://github.com/webern/midi_file/issues/10")
+            Message::EndOfSysexFlag => error::UnwritableSnafu {
+                site: site!(),
+                description:
+                    "a bare EndOfSysexFlag, 0xF7, would be read as the start of an F7 sysex \
+                    event",
             }
-            Message::SystemReset => {
-                noimpl!("SystemReset: https://github.com/webern/midi_file/issues/10")
+            .fail(),
+            Message::TimingClock => write_realtime(w, SystemRealtimeMessage::TimingClock),
+            Message::Undefined1 => write_realtime(w, SystemRealtimeMessage::Undefined1),
+            Message::Start => write_realtime(w, SystemRealtimeMessage::Start),
+            Message::Continue => write_realtime(w, SystemRealtimeMessage::Continue),
+            Message::Stop => write_realtime(w, SystemRealtimeMessage::Stop),
+            Message::Undefined2 => write_realtime(w, SystemRealtimeMessage::Undefined2),
+            Message::ActiveSensing => write_realtime(w, SystemRealtimeMessage::ActiveSensing),
+            Message::SystemReset => error::UnwritableSnafu {
+                site: site!(),
+                description: "a SystemReset byte, 0xFF, would be read as the start of a meta event",
             }
+            .fail(),
         }
     }
 }
@@ -472,7 +602,7 @@ fn write_status_byte<W: Write>(
     w.write_status_byte(data)
 }
 
-fn parse_0xb<R: Read>(iter: &mut ByteIter<R>, channel: Channel) -> LibResult<Message> {
+fn parse_0xb<S: ByteSource>(iter: &mut ByteIter<S>, channel: Channel) -> LibResult<Message> {
     let first_data_byte = iter.read_or_die().context(io!())?;
     match first_data_byte {
         0..=119 => parse_control(iter, channel, first_data_byte),
@@ -481,9 +611,9 @@ fn parse_0xb<R: Read>(iter: &mut ByteIter<R>, channel: Channel) -> LibResult<Mes
     }
 }
 
-fn parse_chanmod<R>(it: &mut ByteIter<R>, chan: Channel, first_byte: u8) -> LibResult<Message>
+fn parse_chanmod<S>(it: &mut ByteIter<S>, chan: Channel, first_byte: u8) -> LibResult<Message>
 where
-    R: Read,
+    S: ByteSource,
 {
     let second_byte = it.read_or_die().context(io!())?;
     match first_byte {
@@ -527,9 +657,22 @@ where
     Ok(())
 }
 
-fn parse_control<R>(it: &mut ByteIter<R>, chan: Channel, first_data_byte: u8) -> LibResult<Message>
+/// Writes the single status byte of a realtime message. The reader remembers any status byte as the
+/// running status, so the byte is recorded here too, forcing the next channel message to write its
+/// own status byte.
+fn write_realtime<W>(w: &mut Scribe<W>, message: SystemRealtimeMessage) -> LibResult<()>
 where
-    R: Read,
+    W: Write,
+{
+    let status_byte = message as u8;
+    write_u8!(w, status_byte)?;
+    w.set_running_status(status_byte);
+    Ok(())
+}
+
+fn parse_control<S>(it: &mut ByteIter<S>, chan: Channel, first_data_byte: u8) -> LibResult<Message>
+where
+    S: ByteSource,
 {
     let control = Control::try_from_u8(first_data_byte)?;
     let value: ControlValue = it.read_or_die().context(io!())?.into();
@@ -820,11 +963,12 @@ impl TryFrom<u8> for Control {
     }
 }
 
+/// A control change message: the `control` on the given `channel` is set to `value`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct ControlChangeValue {
-    channel: Channel,
-    control: Control,
-    value: ControlValue,
+    pub(crate) channel: Channel,
+    pub(crate) control: Control,
+    pub(crate) value: ControlValue,
 }
 
 impl ControlChangeValue {
@@ -852,3 +996,46 @@ impl WriteBytes for ControlChangeValue {
         Ok(())
     }
 }
+
+#[test]
+fn parse_mono_mode_on_test() {
+    use std::io::Cursor;
+    let mut iter = ByteIter::new(Cursor::new([0xB5u8, 0x7E, 0x03])).unwrap();
+    let message = Message::parse(&mut iter).unwrap();
+    let expected = MonoModeOnValue::new(Channel::new(5), MonoModeChannels::new(3));
+    assert_eq!(message, Message::MonoModeOn(expected));
+    match message {
+        Message::MonoModeOn(value) => {
+            assert_eq!(value.channel().get(), 5);
+            assert_eq!(value.mono_mode_channels().get(), 3);
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn end_of_sysex_flag_test() {
+    use crate::error::LibError;
+    use crate::scribe::ScribeSettings;
+    use std::io::Cursor;
+    let mut iter = ByteIter::new(Cursor::new([0xF7u8])).unwrap();
+    let message = Message::parse(&mut iter).unwrap();
+    assert_eq!(message, Message::EndOfSysexFlag);
+    // in a file the byte would begin an F7 sysex event, so the message is not written
+    let mut bytes = Vec::new();
+    let mut scribe = Scribe::new(&mut bytes, ScribeSettings::default());
+    let error = message.write(&mut scribe).unwrap_err();
+    assert!(matches!(error, LibError::Unwritable { .. }));
+    assert!(bytes.is_empty());
+}
+
+#[test]
+fn note_message_debug_test() {
+    let message = NoteMessage::new(Channel::new(0), NoteNumber::new(60), Velocity::new(64));
+    assert_eq!(
+        format!("{:?}", message),
+        "NoteMessage { channel: 0, note: C4, velocity: 64 }"
+    );
+    let message = Message::NoteOn(message);
+    assert!(format!("{:?}", message).contains("C4"));
+}
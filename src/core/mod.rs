@@ -7,20 +7,24 @@ mod bits;
 mod clocks;
 mod duration_name;
 mod general_midi;
+mod gm_percussion;
 mod message;
 mod numbers;
 mod status_type;
+mod velocity_curve;
 pub(crate) mod vlq;
 
 pub use clocks::Clocks;
 pub use duration_name::DurationName;
 pub use general_midi::GeneralMidi;
+pub use gm_percussion::GmPercussion;
 pub use message::{
-    Control, LocalControlValue, Message, MonoModeOnValue, NoteMessage, PitchBendMessage,
-    ProgramChangeValue,
+    ChannelPressureMessage, Control, ControlChangeValue, LocalControlValue, Message,
+    MonoModeOnValue, NoteMessage, OnOff, PitchBendMessage, ProgramChangeValue,
 };
 pub use numbers::{
     Channel, ControlValue, MonoModeChannels, NoteNumber, PitchBendValue, PortValue, Program,
     Velocity,
 };
 pub use status_type::StatusType;
+pub use velocity_curve::VelocityCurve;
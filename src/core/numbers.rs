@@ -20,6 +20,42 @@ clamp!(
     pub
 );
 
+impl NoteNumber {
+    /// The pitch class of the note, from `0` (C) to `11` (B).
+    pub fn pitch_class(&self) -> u8 {
+        self.0 % 12
+    }
+
+    /// The octave of the note, where middle C (`60`) is in octave `4` and note `0` is in octave
+    /// `-1`.
+    pub fn octave(&self) -> i8 {
+        (self.0 / 12) as i8 - 1
+    }
+
+    /// Returns `true` if the note is C#, D#, F#, G# or A#.
+    pub fn is_black_key(&self) -> bool {
+        matches!(self.pitch_class(), 1 | 3 | 6 | 8 | 10)
+    }
+
+    /// Returns `true` if the note is C, D, E, F, G, A or B.
+    pub fn is_white_key(&self) -> bool {
+        !self.is_black_key()
+    }
+
+    /// The name of the note in scientific pitch notation, spelled with sharps, e.g. `C4` for `60`
+    /// and `F#-1` for `6`.
+    pub fn name(&self) -> String {
+        const PITCH_CLASSES: [&str; 12] = [
+            "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+        ];
+        format!(
+            "{}{}",
+            PITCH_CLASSES[usize::from(self.pitch_class())],
+            self.octave()
+        )
+    }
+}
+
 clamp!(
     /// Represents the MIDI velocity. The minimum value is `0`, the maximum value is `127` (i.e.
     /// `u7`). This type will clamp values to the valid range.
@@ -85,3 +121,68 @@ clamp!(
     8192,
     pub
 );
+
+impl PitchBendValue {
+    /// The value that represents no pitch bend, `8192`.
+    pub const fn center() -> Self {
+        Self(8192)
+    }
+
+    /// Create a value from a signed offset from the center, where `-8192` is the lowest bend and
+    /// `8191` is the highest. Values outside of that range are clamped.
+    pub fn from_signed(value: i16) -> Self {
+        let value = value.clamp(-8192, 8191);
+        Self((value + 8192) as u16)
+    }
+
+    /// The signed offset from the center, from `-8192` to `8191`.
+    pub fn as_signed(&self) -> i16 {
+        self.0 as i16 - 8192
+    }
+}
+
+#[test]
+fn note_number_keys_test() {
+    let c_sharp = NoteNumber::new(61);
+    assert!(c_sharp.is_black_key());
+    assert!(!c_sharp.is_white_key());
+    assert_eq!(c_sharp.octave(), 4);
+    assert_eq!(c_sharp.pitch_class(), 1);
+    let c = NoteNumber::new(60);
+    assert!(c.is_white_key());
+    assert_eq!(c.octave(), 4);
+    assert_eq!(c.pitch_class(), 0);
+    assert_eq!(NoteNumber::new(0).octave(), -1);
+    assert_eq!(NoteNumber::new(127).octave(), 9);
+    assert!(NoteNumber::new(70).is_black_key());
+    assert!(NoteNumber::new(71).is_white_key());
+    assert_eq!(c.name(), "C4");
+    assert_eq!(c_sharp.name(), "C#4");
+    assert_eq!(NoteNumber::new(6).name(), "F#-1");
+    assert_eq!(NoteNumber::new(127).name(), "G9");
+}
+
+#[test]
+fn try_new_test() {
+    assert_eq!(Velocity::try_new(128), None);
+    assert_eq!(Velocity::new(128).get(), 127);
+    assert_eq!(Velocity::try_new(127), Some(Velocity::new(127)));
+    assert_eq!(NoteNumber::try_new(128), None);
+    assert_eq!(NoteNumber::try_new(60), Some(NoteNumber::new(60)));
+    assert_eq!(Channel::try_new(16), None);
+    assert_eq!(Channel::new(16).get(), 15);
+    assert_eq!(PitchBendValue::try_new(16384), None);
+}
+
+#[test]
+fn pitch_bend_signed_test() {
+    assert_eq!(PitchBendValue::from_signed(0).get(), 8192);
+    assert_eq!(PitchBendValue::from_signed(0), PitchBendValue::center());
+    assert_eq!(PitchBendValue::from_signed(-8192).get(), 0);
+    assert_eq!(PitchBendValue::from_signed(8191).get(), 16383);
+    assert_eq!(PitchBendValue::from_signed(i16::MAX).get(), 16383);
+    assert_eq!(PitchBendValue::from_signed(i16::MIN).get(), 0);
+    assert_eq!(PitchBendValue::new(16383).as_signed(), 8191);
+    assert_eq!(PitchBendValue::new(0).as_signed(), -8192);
+    assert_eq!(PitchBendValue::center().as_signed(), 0);
+}
@@ -0,0 +1,62 @@
+/// A mapping of velocities, such as a gamma curve, for use with
+/// [`crate::file::Track::remap_velocity_curve`].
+///
+/// # Example
+/// ```
+/// use midi_file::core::VelocityCurve;
+///
+/// // raise the soft notes and leave the loudest where it is
+/// let curve = VelocityCurve::exponential(0.5);
+/// assert_eq!(curve.apply(32), 64);
+/// assert_eq!(curve.apply(127), 127);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct VelocityCurve {
+    kind: CurveKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum CurveKind {
+    Linear(f64),
+    Exponential(f64),
+}
+
+impl VelocityCurve {
+    /// Multiply every velocity by `scale`.
+    pub fn linear(scale: f64) -> Self {
+        Self {
+            kind: CurveKind::Linear(scale),
+        }
+    }
+
+    /// Map velocity `v` to `127 * (v / 127) ^ gamma`. A `gamma` less than one raises the soft
+    /// velocities more than the loud ones, which evens out a performance, and a `gamma` greater
+    /// than one does the opposite. `127` always maps to `127`.
+    pub fn exponential(gamma: f64) -> Self {
+        Self {
+            kind: CurveKind::Exponential(gamma),
+        }
+    }
+
+    /// Map `velocity` through the curve. The result is rounded and clamped to `0..=127`.
+    pub fn apply(&self, velocity: u8) -> u8 {
+        let v = f64::from(velocity);
+        let mapped = match self.kind {
+            CurveKind::Linear(scale) => v * scale,
+            CurveKind::Exponential(gamma) => 127.0 * (v / 127.0).powf(gamma),
+        };
+        // the cast saturates, and maps NaN to zero
+        (mapped.round() as u8).min(127)
+    }
+}
+
+#[test]
+fn velocity_curve_test() {
+    let identity = VelocityCurve::linear(1.0);
+    assert!((0..=127).all(|v| identity.apply(v) == v));
+    assert_eq!(VelocityCurve::linear(0.5).apply(101), 51);
+    assert_eq!(VelocityCurve::linear(2.0).apply(100), 127);
+    assert_eq!(VelocityCurve::exponential(1.0).apply(90), 90);
+    assert_eq!(VelocityCurve::exponential(2.0).apply(64), 32);
+    assert_eq!(VelocityCurve::exponential(2.0).apply(127), 127);
+}
@@ -89,6 +89,16 @@ impl Error for VlqError {}
 /// 0x7f, 127: The largest 7 bit number.
 const MAX_7BIT: u8 = 0b0111_1111;
 
+/// The largest value that fits in a variable length quantity of four bytes, which is the most that
+/// the MIDI file spec allows.
+pub(crate) const MAX_VLQ_VALUE: u32 = 0x0fff_ffff;
+
+/// Clamp `value` to [`MAX_VLQ_VALUE`] for the paths that cannot return an error.
+pub(crate) fn clamp_vlq(value: u64) -> u32 {
+    // the clamped value fits in a u32
+    value.min(u64::from(MAX_VLQ_VALUE)) as u32
+}
+
 /// 0x80, 128: The highest bit is set, this bit indicates the last byte of a sequence.
 pub(crate) const CONTINUE: u8 = 0b1000_0000;
 
@@ -0,0 +1,168 @@
+//! The `csv` module writes a `MidiFile` in the text format used by the `midicsv` program. See
+//! <https://www.fourmilab.ch/webtools/midicsv/> for a description of the format.
+
+use crate::core::{Channel, Message};
+use crate::error::LibResult;
+use crate::file::{Division, Event, KeyMode, MetaEvent};
+use crate::{MidiFile, Text};
+use snafu::ResultExt;
+use std::io::Write;
+
+pub(crate) fn write_csv<W: Write>(mfile: &MidiFile, w: &mut W) -> LibResult<()> {
+    let division = match mfile.header().division() {
+        Division::QuarterNote(q) => q.get(),
+        Division::Smpte(_) => noimpl!("midicsv SMPTE division"),
+    };
+    writeln!(
+        w,
+        "0, 0, Header, {}, {}, {}",
+        *mfile.header().format() as u16,
+        mfile.tracks_len(),
+        division
+    )
+    .context(wr!())?;
+    for (ix, track) in mfile.tracks().enumerate() {
+        let track_number = ix + 1;
+        let mut tick = 0u64;
+        writeln!(w, "{}, 0, Start_track", track_number).context(wr!())?;
+        for track_event in track.events() {
+            tick += u64::from(track_event.delta_time());
+            write!(w, "{}, {}, ", track_number, tick).context(wr!())?;
+            write_event(w, track_event.event())?;
+            writeln!(w).context(wr!())?;
+        }
+    }
+    writeln!(w, "0, 0, End_of_file").context(wr!())?;
+    Ok(())
+}
+
+fn write_event<W: Write>(w: &mut W, event: &Event) -> LibResult<()> {
+    match event {
+        Event::Midi(message) => write_message(w, message),
+        Event::Sysex(_) => noimpl!("midicsv System_exclusive"),
+        Event::Meta(meta) => write_meta(w, meta),
+    }
+}
+
+fn write_message<W: Write>(w: &mut W, message: &Message) -> LibResult<()> {
+    match message {
+        Message::NoteOff(m) => write!(
+            w,
+            "Note_off_c, {}, {}, {}",
+            m.channel, m.note_number, m.velocity
+        ),
+        Message::NoteOn(m) => write!(
+            w,
+            "Note_on_c, {}, {}, {}",
+            m.channel, m.note_number, m.velocity
+        ),
+        Message::PolyPressure(m) => write!(
+            w,
+            "Poly_aftertouch_c, {}, {}, {}",
+            m.channel, m.note_number, m.velocity
+        ),
+        Message::Control(c) => write!(
+            w,
+            "Control_c, {}, {}, {}",
+            c.channel(),
+            c.control() as u8,
+            c.value()
+        ),
+        Message::ProgramChange(p) => write!(w, "Program_c, {}, {}", p.channel, p.program),
+        Message::ChannelPressure(p) => {
+            write!(w, "Channel_aftertouch_c, {}, {}", p.channel, p.pressure)
+        }
+        Message::PitchBend(p) => write!(w, "Pitch_bend_c, {}, {}", p.channel, p.pitch_bend),
+        Message::AllSoundsOff(c) => write_control(w, *c, 120, 0),
+        Message::ResetAllControllers(c) => write_control(w, *c, 121, 0),
+        Message::LocalControlOff(c) => write_control(w, *c, 122, 0),
+        Message::LocalControlOn(c) => write_control(w, *c, 122, 127),
+        Message::AllNotesOff(c) => write_control(w, *c, 123, 0),
+        Message::OmniModeOff(c) => write_control(w, *c, 124, 0),
+        Message::OmniModeOn(c) => write_control(w, *c, 125, 0),
+        Message::MonoModeOn(m) => write_control(w, m.channel(), 126, m.mono_mode_channels().get()),
+        Message::PolyModeOn(c) => write_control(w, *c, 127, 0),
+        other => noimpl!(format!("midicsv {:?}", other)),
+    }
+    .context(wr!())
+}
+
+fn write_control<W: Write>(
+    w: &mut W,
+    channel: Channel,
+    control: u8,
+    value: u8,
+) -> std::io::Result<()> {
+    write!(w, "Control_c, {}, {}, {}", channel, control, value)
+}
+
+fn write_meta<W: Write>(w: &mut W, meta: &MetaEvent) -> LibResult<()> {
+    match meta {
+        MetaEvent::SequenceNumber => noimpl!("midicsv Sequence_number"),
+        MetaEvent::OtherText(t) => write_text(w, "Text_t", t),
+        MetaEvent::Copyright(t) => write_text(w, "Copyright_t", t),
+        MetaEvent::TrackName(t) => write_text(w, "Title_t", t),
+        MetaEvent::InstrumentName(t) => write_text(w, "Instrument_name_t", t),
+        MetaEvent::Lyric(t) => write_text(w, "Lyric_t", t),
+        MetaEvent::Marker(t) => write_text(w, "Marker_t", t),
+        MetaEvent::CuePoint(t) => write_text(w, "Cue_point_t", t),
+        // midicsv has no names for these, it writes them as unknown meta events
+        MetaEvent::ProgramName(t) => write_unknown_meta(w, 0x08, t.as_bytes()),
+        MetaEvent::DeviceName(t) => write_unknown_meta(w, 0x09, t.as_bytes()),
+        MetaEvent::MidiChannelPrefix(c) => write!(w, "Channel_prefix, {}", c).context(wr!()),
+        MetaEvent::EndOfTrack => write!(w, "End_track").context(wr!()),
+        MetaEvent::SetTempo(t) => write!(w, "Tempo, {}", t).context(wr!()),
+        MetaEvent::SmpteOffset(s) => write!(
+            w,
+            "SMPTE_offset, {}, {}, {}, {}, {}",
+            s.hr, s.mn, s.se, s.fr, s.ff
+        )
+        .context(wr!()),
+        MetaEvent::TimeSignature(t) => write!(
+            w,
+            "Time_signature, {}, {}, {}, {}",
+            t.numerator(),
+            t.denominator() as u8,
+            t.click().to_u8(),
+            t.tpq
+        )
+        .context(wr!()),
+        MetaEvent::KeySignature(k) => write!(
+            w,
+            "Key_signature, {}, \"{}\"",
+            k.accidentals(),
+            match k.mode() {
+                KeyMode::Major => "major",
+                KeyMode::Minor => "minor",
+            }
+        )
+        .context(wr!()),
+        MetaEvent::Sequencer => noimpl!("midicsv Sequencer_specific"),
+        MetaEvent::Port(p) => write!(w, "MIDI_port, {}", p).context(wr!()),
+        MetaEvent::Unknown { meta_type, data } => write_unknown_meta(w, *meta_type, data),
+    }
+}
+
+fn write_unknown_meta<W: Write>(w: &mut W, meta_type: u8, data: &[u8]) -> LibResult<()> {
+    write!(w, "Unknown_meta_event, {}, {}", meta_type, data.len()).context(wr!())?;
+    for byte in data {
+        write!(w, ", {}", byte).context(wr!())?;
+    }
+    Ok(())
+}
+
+/// Writes a quoted string. As in `midicsv`, quotes are doubled, backslashes are escaped, and bytes
+/// that are not printable ASCII are written as backslash-escaped octal.
+fn write_text<W: Write>(w: &mut W, name: &str, text: &Text) -> LibResult<()> {
+    write!(w, "{}, \"", name).context(wr!())?;
+    for &byte in text.as_bytes() {
+        match byte {
+            b'"' => write!(w, "\"\""),
+            b'\\' => write!(w, "\\\\"),
+            0x20..=0x7e => w.write_all(&[byte]),
+            _ => write!(w, "\\{:03o}", byte),
+        }
+        .context(wr!())?;
+    }
+    write!(w, "\"").context(wr!())
+}
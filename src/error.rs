@@ -11,6 +11,91 @@ pub struct Error(LibError);
 /// The public Result type for this library.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The general category of an [`Error`], for callers that need to react to different failures
+/// differently.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ErrorKind {
+    /// An error occurred while opening, reading or writing a file or stream.
+    Io,
+    /// The MIDI data is malformed.
+    InvalidFile,
+    /// The MIDI data uses a feature that this library does not yet support.
+    Unimplemented,
+    /// A value was too large to be represented in the MIDI file format.
+    Overflow,
+    /// Any other error.
+    Other,
+}
+
+impl Error {
+    /// The general category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match &self.0 {
+            LibError::Create { .. } | LibError::Write { .. } => ErrorKind::Io,
+            LibError::Read { source, .. } if source.is_io() => ErrorKind::Io,
+            LibError::Read { .. }
+            | LibError::InvalidFile { .. }
+            | LibError::RunningStatus { .. }
+            | LibError::TruncatedTrackChunk { .. } => ErrorKind::InvalidFile,
+            LibError::Unimplemented { .. } => ErrorKind::Unimplemented,
+            LibError::DeltaTimeTooBig { .. }
+            | LibError::StringTooLong { .. }
+            | LibError::TextTooLong { .. }
+            | LibError::TooManyTracks { .. }
+            | LibError::TrackCount { .. }
+            | LibError::TrackTooLong { .. } => ErrorKind::Overflow,
+            LibError::Other { .. } | LibError::Unwritable { .. } => ErrorKind::Other,
+        }
+    }
+
+    /// The approximate byte offset in the MIDI data where reading failed, if known.
+    pub fn byte_position(&self) -> Option<u64> {
+        match &self.0 {
+            LibError::Read { source, .. } => source.position(),
+            LibError::TruncatedTrackChunk { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+}
+
+/// A track that could not be read by [`crate::MidiFile::load_lossy`].
+#[derive(Debug)]
+pub struct TrackError {
+    track: u32,
+    error: Error,
+}
+
+impl TrackError {
+    pub(crate) fn new(track: u32, error: LibError) -> Self {
+        Self {
+            track,
+            error: Error(error),
+        }
+    }
+
+    /// The zero-based index of the track chunk.
+    pub fn track(&self) -> u32 {
+        self.track
+    }
+
+    /// The error that occurred while reading the track.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+impl std::fmt::Display for TrackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "track {}: {}", self.track, self.error)
+    }
+}
+
+impl std::error::Error for TrackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 /// The internal Result type for this library.
 pub(crate) type LibResult<T> = std::result::Result<T, LibError>;
 
@@ -25,6 +110,14 @@ pub(crate) enum LibError {
         source: std::io::Error,
     },
 
+    #[snafu(display(
+        "{} The delta time {} is larger than the maximum of {}",
+        site,
+        delta_time,
+        crate::core::vlq::MAX_VLQ_VALUE
+    ))]
+    DeltaTimeTooBig { site: String, delta_time: u32 },
+
     #[snafu(display("{}: The MIDI file is invalid: {}", site, description))]
     InvalidFile { site: String, description: String },
 
@@ -46,21 +139,55 @@ pub(crate) enum LibError {
         source: TryFromIntError,
     },
 
+    #[snafu(display(
+        "{} The text is {} bytes long, more than the maximum of {}",
+        site,
+        len,
+        crate::MAX_TEXT_LEN
+    ))]
+    TextTooLong { site: String, len: usize },
+
     #[snafu(display("{} There are too many tracks for a 16-byte uint: {}", site, source))]
     TooManyTracks {
         site: String,
         source: TryFromIntError,
     },
 
+    #[snafu(display(
+        "{} There are {} tracks, more than the maximum of {}",
+        site,
+        count,
+        u16::MAX
+    ))]
+    TrackCount { site: String, count: usize },
+
     #[snafu(display("{} The track is too long and overflows a u32: {}", site, source))]
     TrackTooLong {
         site: String,
         source: TryFromIntError,
     },
 
+    #[snafu(display(
+        "{} The track chunk declares a length of {} bytes but only {} bytes are available, the \
+        data ends near byte {}",
+        site,
+        declared,
+        available,
+        position
+    ))]
+    TruncatedTrackChunk {
+        site: String,
+        declared: u64,
+        available: u64,
+        position: u64,
+    },
+
     #[snafu(display("{} The '{}' feature is not yet implemented", site, feature))]
     Unimplemented { site: String, feature: String },
 
+    #[snafu(display("{} The event cannot be written to a MIDI file: {}", site, description))]
+    Unwritable { site: String, description: String },
+
     #[snafu(display("{} Error while writing data: {}", site, source))]
     Write {
         site: String,
@@ -0,0 +1,170 @@
+use crate::core::{
+    Channel, Clocks, Control, ControlChangeValue, ControlValue, DurationName, GeneralMidi, Message,
+    NoteNumber, PitchBendValue, Velocity,
+};
+use crate::file::{ensure_end_of_track, Event, QuartersPerMinute, Track};
+use crate::Error;
+
+/// A 'builder' for a [`Track`]. Each method adds to the track and returns the builder so that calls
+/// can be chained. The first error encountered is held and returned by [`TrackBuilder::build`].
+///
+/// # Example
+/// ```
+/// use midi_file::core::{Channel, NoteNumber, Velocity};
+/// use midi_file::file::{QuartersPerMinute, TrackBuilder};
+///
+/// let ch = Channel::new(0);
+/// let track = TrackBuilder::new()
+///     .name("Singer")
+///     .tempo(0, QuartersPerMinute::new(116))
+///     .lyric(0, "Row")
+///     .note(0, ch, NoteNumber::new(72), Velocity::new(64), 1536)
+///     .build()
+///     .unwrap();
+/// assert_eq!(track.events_len(), 6);
+/// ```
+#[derive(Debug, Default)]
+pub struct TrackBuilder {
+    track: Track,
+    error: Option<Error>,
+}
+
+impl TrackBuilder {
+    /// Create a `TrackBuilder` with no events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add, or replace, the track name at the beginning of the track, see [`Track::set_name`].
+    pub fn name<S: Into<String>>(self, name: S) -> Self {
+        self.apply(|track| track.set_name(name))
+    }
+
+    /// Add, or replace, the instrument name at the beginning of the track, see
+    /// [`Track::set_instrument_name`].
+    pub fn instrument_name<S: Into<String>>(self, name: S) -> Self {
+        self.apply(|track| track.set_instrument_name(name))
+    }
+
+    /// Add, or replace, the general midi program at the beginning of the track, see
+    /// [`Track::set_general_midi`].
+    pub fn general_midi(self, channel: Channel, value: GeneralMidi) -> Self {
+        self.apply(|track| track.set_general_midi(channel, value))
+    }
+
+    /// Add a tempo message.
+    pub fn tempo(self, delta_time: u32, quarters_per_minute: QuartersPerMinute) -> Self {
+        self.apply(|track| track.push_tempo(delta_time, quarters_per_minute))
+    }
+
+    /// Add a time signature.
+    pub fn time_signature(
+        self,
+        delta_time: u32,
+        numerator: u8,
+        denominator: DurationName,
+        click: Clocks,
+    ) -> Self {
+        self.apply(|track| track.push_time_signature(delta_time, numerator, denominator, click))
+    }
+
+    /// Add a note on message.
+    pub fn note_on(
+        self,
+        delta_time: u32,
+        channel: Channel,
+        note_number: NoteNumber,
+        velocity: Velocity,
+    ) -> Self {
+        self.apply(|track| track.push_note_on(delta_time, channel, note_number, velocity))
+    }
+
+    /// Add a note off message.
+    pub fn note_off(
+        self,
+        delta_time: u32,
+        channel: Channel,
+        note_number: NoteNumber,
+        velocity: Velocity,
+    ) -> Self {
+        self.apply(|track| track.push_note_off(delta_time, channel, note_number, velocity))
+    }
+
+    /// Add a note on message followed by a note off message `duration` ticks later. The note off
+    /// uses the same `velocity` as the note on.
+    pub fn note(
+        self,
+        delta_time: u32,
+        channel: Channel,
+        note_number: NoteNumber,
+        velocity: Velocity,
+        duration: u32,
+    ) -> Self {
+        self.note_on(delta_time, channel, note_number, velocity)
+            .note_off(duration, channel, note_number, velocity)
+    }
+
+    /// Add a chord, see [`Track::push_chord`].
+    pub fn chord(
+        self,
+        delta_time: u32,
+        duration: u32,
+        channel: Channel,
+        notes: &[NoteNumber],
+        velocity: Velocity,
+    ) -> Self {
+        self.apply(|track| track.push_chord(delta_time, duration, channel, notes, velocity))
+    }
+
+    /// Add a lyric.
+    pub fn lyric<S: Into<String>>(self, delta_time: u32, lyric: S) -> Self {
+        self.apply(|track| track.push_lyric(delta_time, lyric))
+    }
+
+    /// Add a control change message.
+    pub fn control_change(
+        self,
+        delta_time: u32,
+        channel: Channel,
+        control: Control,
+        value: ControlValue,
+    ) -> Self {
+        let event = Event::Midi(Message::Control(ControlChangeValue {
+            channel,
+            control,
+            value,
+        }));
+        self.event(delta_time, event)
+    }
+
+    /// Add a pitch bend value.
+    pub fn pitch_bend(self, delta_time: u32, channel: Channel, pitch_bend: PitchBendValue) -> Self {
+        self.apply(|track| track.push_pitch_bend(delta_time, channel, pitch_bend))
+    }
+
+    /// Add any event.
+    pub fn event(self, delta_time: u32, event: Event) -> Self {
+        self.apply(|track| track.push_event(delta_time, event))
+    }
+
+    /// Return the first error that occurred while building, or the track with an `EndOfTrack`
+    /// event appended if it does not already end with one.
+    pub fn build(self) -> crate::Result<Track> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(ensure_end_of_track(self.track)?),
+        }
+    }
+
+    fn apply<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Track) -> crate::Result<()>,
+    {
+        if self.error.is_none() {
+            if let Err(e) = f(&mut self.track) {
+                self.error = Some(e);
+            }
+        }
+        self
+    }
+}
@@ -0,0 +1,103 @@
+use crate::file::{
+    Event, KeySignatureValue, MetaEvent, MicrosecondsPerQuarter, SmpteOffsetValue,
+    TimeSignatureValue,
+};
+
+/// A span of time, in ticks, during which a single tempo is in effect.
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct TempoSegment {
+    start: u64,
+    end: Option<u64>,
+    tempo: MicrosecondsPerQuarter,
+}
+
+impl TempoSegment {
+    /// The absolute tick at which the tempo takes effect.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// The absolute tick at which the next tempo takes effect, or `None` if this is the last tempo
+    /// in the file.
+    pub fn end(&self) -> Option<u64> {
+        self.end
+    }
+
+    /// A getter for the `tempo` field.
+    pub fn tempo(&self) -> MicrosecondsPerQuarter {
+        self.tempo
+    }
+}
+
+/// The "conductor" information of a file, that is, the tempo map, meter changes, key changes and
+/// SMPTE offset, each with the absolute tick at which it occurs. See
+/// [`crate::MidiFile::conductor_summary`].
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct ConductorSummary {
+    tempo_segments: Vec<TempoSegment>,
+    time_signatures: Vec<(u64, TimeSignatureValue)>,
+    key_signatures: Vec<(u64, KeySignatureValue)>,
+    smpte_offset: Option<SmpteOffsetValue>,
+}
+
+impl ConductorSummary {
+    /// Build the summary from events that have already been sorted by absolute tick.
+    pub(crate) fn from_sorted<'a, I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, &'a Event)>,
+    {
+        let mut summary = Self::default();
+        for (tick, event) in events {
+            match event {
+                Event::Meta(MetaEvent::SetTempo(tempo)) => {
+                    match summary.tempo_segments.last_mut() {
+                        // a later tempo at the same tick replaces the earlier one
+                        Some(previous) if previous.start == tick => {
+                            previous.tempo = *tempo;
+                            continue;
+                        }
+                        Some(previous) => previous.end = Some(tick),
+                        None => {}
+                    }
+                    summary.tempo_segments.push(TempoSegment {
+                        start: tick,
+                        end: None,
+                        tempo: *tempo,
+                    });
+                }
+                Event::Meta(MetaEvent::TimeSignature(value)) => {
+                    summary.time_signatures.push((tick, *value))
+                }
+                Event::Meta(MetaEvent::KeySignature(value)) => {
+                    summary.key_signatures.push((tick, *value))
+                }
+                Event::Meta(MetaEvent::SmpteOffset(value)) if summary.smpte_offset.is_none() => {
+                    summary.smpte_offset = Some(*value)
+                }
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    /// The tempo changes in the file, in order. When there is more than one tempo change at the same
+    /// tick, only the last of them is kept, so no segment is empty.
+    pub fn tempo_segments(&self) -> &[TempoSegment] {
+        &self.tempo_segments
+    }
+
+    /// The time signature changes in the file as `(absolute_tick, time_signature)`.
+    pub fn time_signatures(&self) -> &[(u64, TimeSignatureValue)] {
+        &self.time_signatures
+    }
+
+    /// The key signature changes in the file as `(absolute_tick, key_signature)`.
+    pub fn key_signatures(&self) -> &[(u64, KeySignatureValue)] {
+        &self.key_signatures
+    }
+
+    /// The first SMPTE offset found in the file, if any.
+    pub fn smpte_offset(&self) -> Option<SmpteOffsetValue> {
+        self.smpte_offset
+    }
+}
@@ -0,0 +1,126 @@
+use crate::file::{Event, TrackEvent};
+use crate::MidiFile;
+use std::fmt::{Display, Formatter};
+
+/// A difference between the events of two files, found by [`MidiFile::diff`].
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct Difference {
+    track: usize,
+    event: usize,
+    description: String,
+}
+
+impl Difference {
+    /// The zero-based index of the track.
+    pub fn track(&self) -> usize {
+        self.track
+    }
+
+    /// The zero-based index of the event within the track.
+    pub fn event(&self) -> usize {
+        self.event
+    }
+
+    /// A human-readable description of the difference.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "track {}, event {}: {}",
+            self.track, self.event, self.description
+        )
+    }
+}
+
+pub(crate) fn diff(a: &MidiFile, b: &MidiFile) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let mut differ = |track: usize, event: usize, description: String| {
+        differences.push(Difference {
+            track,
+            event,
+            description,
+        })
+    };
+    let a_tracks: Vec<_> = a.tracks().collect();
+    let b_tracks: Vec<_> = b.tracks().collect();
+    for track_ix in 0..a_tracks.len().max(b_tracks.len()) {
+        let (a_track, b_track) = match (a_tracks.get(track_ix), b_tracks.get(track_ix)) {
+            (Some(a_track), Some(b_track)) => (a_track, b_track),
+            (Some(_), None) => {
+                differ(
+                    track_ix,
+                    0,
+                    "the track is missing from the second file".into(),
+                );
+                continue;
+            }
+            (None, _) => {
+                differ(
+                    track_ix,
+                    0,
+                    "the track is missing from the first file".into(),
+                );
+                continue;
+            }
+        };
+        let a_events: Vec<&TrackEvent> = a_track.events().collect();
+        let b_events: Vec<&TrackEvent> = b_track.events().collect();
+        for event_ix in 0..a_events.len().max(b_events.len()) {
+            let description = match (a_events.get(event_ix), b_events.get(event_ix)) {
+                (Some(a_event), Some(b_event)) => describe(a_event, b_event),
+                (Some(a_event), None) => Some(format!(
+                    "{:?} is missing from the second file",
+                    a_event.event()
+                )),
+                (None, Some(b_event)) => Some(format!(
+                    "{:?} is missing from the first file",
+                    b_event.event()
+                )),
+                (None, None) => None,
+            };
+            if let Some(description) = description {
+                differ(track_ix, event_ix, description);
+            }
+        }
+    }
+    differences
+}
+
+/// Describe how two events differ, or return `None` if they are the same.
+fn describe(a: &TrackEvent, b: &TrackEvent) -> Option<String> {
+    let delta = if a.delta_time() == b.delta_time() {
+        None
+    } else {
+        Some(format!(
+            "delta time {} != {}",
+            a.delta_time(),
+            b.delta_time()
+        ))
+    };
+    let event = if a.event() == b.event() {
+        None
+    } else if same_kind(a.event(), b.event()) {
+        Some(format!("fields {:?} != {:?}", a.event(), b.event()))
+    } else {
+        Some(format!("kind {:?} != {:?}", a.event(), b.event()))
+    };
+    match (delta, event) {
+        (Some(delta), Some(event)) => Some(format!("{}, {}", delta, event)),
+        (delta, event) => delta.or(event),
+    }
+}
+
+/// Returns `true` if both events are the same variant, e.g. both are note-on messages.
+fn same_kind(a: &Event, b: &Event) -> bool {
+    match (a, b) {
+        (Event::Midi(a), Event::Midi(b)) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        (Event::Meta(a), Event::Meta(b)) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        (Event::Sysex(_), Event::Sysex(_)) => true,
+        _ => false,
+    }
+}
@@ -82,6 +82,26 @@ pub enum FrameRate {
     N30,
 }
 
+impl FrameRate {
+    /// The actual number of frames per second. For `N29`, which is 30 drop-frame timecode, this is
+    /// 29.97 because the frames are labelled as if there were 30 per second, but they run slightly
+    /// slower.
+    pub fn frames_per_second(&self) -> f64 {
+        match self {
+            FrameRate::N24 => 24.0,
+            FrameRate::N25 => 25.0,
+            FrameRate::N29 => 29.97,
+            FrameRate::N30 => 30.0,
+        }
+    }
+
+    /// Returns `true` for `N29`, the only drop-frame timecode format. In drop-frame timecode, some
+    /// frame numbers are skipped so that the timecode keeps up with the clock.
+    pub fn is_drop_frame(&self) -> bool {
+        matches!(self, FrameRate::N29)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
 pub struct SmpteRate {
     /// The number of frames per second.
@@ -116,3 +136,13 @@ impl SmpteRate {
         self.resolution
     }
 }
+
+#[test]
+fn frame_rate_test() {
+    assert!((FrameRate::N29.frames_per_second() - 29.97).abs() < f64::EPSILON);
+    assert!(FrameRate::N29.is_drop_frame());
+    assert!((FrameRate::N30.frames_per_second() - 30.0).abs() < f64::EPSILON);
+    assert!(!FrameRate::N30.is_drop_frame());
+    assert!(!FrameRate::N24.is_drop_frame());
+    assert!(!FrameRate::N25.is_drop_frame());
+}
@@ -1,12 +1,14 @@
-use crate::byte_iter::ByteIter;
-use crate::core::vlq::Vlq;
+use crate::byte_iter::{ByteIter, ByteSource};
+use crate::core::vlq::{clamp_vlq, Vlq};
 use crate::core::Message;
 use crate::error::LibResult;
 use crate::file::{MetaEvent, SysexEvent};
 use crate::scribe::Scribe;
 use log::trace;
 use snafu::ResultExt;
-use std::io::{Read, Write};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
 
 /// `0xFF`: File Spec: All meta-events begin with FF, then have an event type byte (which is always
 /// less than 128)
@@ -36,7 +38,7 @@ impl Default for Event {
 }
 
 impl Event {
-    fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
+    fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
         let status_byte = iter.peek_or_die().context(io!())?;
         match status_byte {
             FILE_SYSEX_F7 | FILE_SYSEX_F0 => {
@@ -66,20 +68,59 @@ impl Event {
 }
 
 /// <MTrk event> = <delta-time> <event>
-#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
+///
+/// Equality, ordering and hashing only consider the delta time and the event, so an event that was
+/// read with [`crate::Settings::preserve_status_bytes`] equals the same event read without it.
+#[derive(Clone, Debug, Default)]
 pub struct TrackEvent {
     /// <delta-time> is stored as a variable-length quantity. It represents the amount of time
     /// before the following event. If the first event in a track occurs at the very beginning of a
     /// track, or if two events occur simultaneously, a delta-time of zero is used. Delta-times are
     /// always present. Delta-time is in ticks as specified in the header chunk.
-    delta_time: u32,
-    event: Event,
+    pub(crate) delta_time: u32,
+    pub(crate) event: Event,
+    /// Whether a channel message was read with its own status byte, `Some(false)` meaning that it
+    /// used running status. This is only recorded when reading with
+    /// [`crate::Settings::preserve_status_bytes`].
+    explicit_status: Option<bool>,
+}
+
+impl PartialEq for TrackEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.delta_time == other.delta_time && self.event == other.event
+    }
+}
+
+impl Eq for TrackEvent {}
+
+impl PartialOrd for TrackEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TrackEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.delta_time, &self.event).cmp(&(other.delta_time, &other.event))
+    }
+}
+
+impl Hash for TrackEvent {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.delta_time.hash(state);
+        self.event.hash(state);
+    }
 }
 
 impl TrackEvent {
-    /// Create a new track event.
+    /// Create a new track event. A `delta_time` larger than `0x0FFFFFFF`, the largest that can be
+    /// written to a file, is clamped to `0x0FFFFFFF`.
     pub fn new(delta_time: u32, event: Event) -> Self {
-        Self { delta_time, event }
+        Self {
+            delta_time: clamp_vlq(u64::from(delta_time)),
+            event,
+            explicit_status: None,
+        }
     }
 
     /// A getter for the `delta_time` field.
@@ -87,26 +128,61 @@ impl TrackEvent {
         self.delta_time
     }
 
+    /// A setter for the `delta_time` field. A `delta_time` larger than `0x0FFFFFFF` is clamped to
+    /// `0x0FFFFFFF`.
+    pub fn set_delta_time(&mut self, delta_time: u32) {
+        self.delta_time = clamp_vlq(u64::from(delta_time));
+    }
+
     /// A getter for the `event` field.
     pub fn event(&self) -> &Event {
         &self.event
     }
 
+    /// A mutable getter for the `event` field.
+    pub fn event_mut(&mut self) -> &mut Event {
+        &mut self.event
+    }
+
+    /// A setter for the `event` field.
+    pub fn set_event(&mut self, event: Event) {
+        self.event = event;
+    }
+
+    /// Whether the event was read with its own status byte, `Some(true)`, or used running status,
+    /// `Some(false)`. This is `None` unless the event is a channel message that was read with
+    /// [`crate::Settings::preserve_status_bytes`]. When the event is written, a recorded status
+    /// byte is always written, and a recorded running status is used whenever the previous status
+    /// byte allows it, regardless of the `running_status` setting.
+    pub fn explicit_status(&self) -> Option<bool> {
+        self.explicit_status
+    }
+
     /// Returns true if the track event is a [`MetaEvent::EndOfTrack`].
     pub(crate) fn is_end(&self) -> bool {
         matches!(&self.event, Event::Meta(meta) if matches!(meta, MetaEvent::EndOfTrack))
     }
 
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
         let delta_time = iter.read_vlq_u32().context(io!())?;
         trace!("delta_time {}", delta_time);
+        let first_byte = iter.peek_or_die().context(io!())?;
         let event = Event::parse(iter)?;
-        Ok(Self { delta_time, event })
+        let explicit_status = match event {
+            Event::Midi(_) if iter.settings().preserve_status_bytes => Some(first_byte >= 0x80),
+            _ => None,
+        };
+        Ok(Self {
+            delta_time,
+            event,
+            explicit_status,
+        })
     }
 
     pub(crate) fn write<W: Write>(&self, w: &mut Scribe<W>) -> LibResult<()> {
         let delta = Vlq::new(self.delta_time).to_bytes();
         w.write_all(&delta).context(wr!())?;
+        w.set_explicit_status(self.explicit_status);
         self.event.write(w)
     }
 }
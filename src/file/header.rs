@@ -1,6 +1,8 @@
+use crate::byte_iter::{ByteIter, ByteSource};
 use crate::error::LibResult;
 use crate::scribe::Scribe;
 use crate::{Division, Error};
+use log::trace;
 use snafu::ResultExt;
 use std::convert::TryFrom;
 use std::io::Write;
@@ -38,6 +40,31 @@ impl Header {
         &self.division
     }
 
+    /// The number of ticks per quarter note, or `None` if the division is SMPTE based.
+    pub fn ppq(&self) -> Option<u16> {
+        match self.division {
+            Division::QuarterNote(q) => Some(q.get()),
+            Division::Smpte(_) => None,
+        }
+    }
+
+    /// Parses the header chunk and returns the `Header` along with the number of tracks.
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<(Self, u16)> {
+        trace!("parsing header chunk");
+        iter.expect_tag("MThd").context(io!())?;
+        let chunk_length = iter.read_u32().context(io!())?;
+        // header chunk length is always 6
+        if chunk_length != 6 {
+            return crate::error::OtherSnafu { site: site!() }.fail();
+        }
+        let format_word = iter.read_u16().context(io!())?;
+        let num_tracks = iter.read_u16().context(io!())?;
+        let division_data = iter.read_u16().context(io!())?;
+        let format = Format::from_u16(format_word)?;
+        let header = Header::new(format, Division::from_u16(division_data)?);
+        Ok((header, num_tracks))
+    }
+
     pub(crate) fn write<W: Write>(&self, w: &mut Scribe<W>, ntracks: u16) -> LibResult<()> {
         // write the header chunk identifier
         write!(w, "MThd").context(wr!())?;
@@ -94,3 +121,11 @@ impl TryFrom<u16> for Format {
         Ok(Self::from_u16(value)?)
     }
 }
+
+#[test]
+fn ppq_test() {
+    let header = Header::new(Format::Single, Division::default());
+    assert_eq!(header.ppq(), Some(1024));
+    let header = Header::new(Format::Single, Division::Smpte(Default::default()));
+    assert_eq!(header.ppq(), None);
+}
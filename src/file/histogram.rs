@@ -0,0 +1,86 @@
+use crate::core::{Control, Message};
+use crate::file::Event;
+use std::collections::BTreeMap;
+
+/// The number of events of each type in a file, see [`crate::MidiFile::event_histogram`].
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct EventHistogram {
+    note_ons: usize,
+    note_offs: usize,
+    control_changes: BTreeMap<Control, usize>,
+    program_changes: usize,
+    pitch_bends: usize,
+    other_messages: usize,
+    meta_events: BTreeMap<u8, usize>,
+    sysex_events: usize,
+}
+
+impl EventHistogram {
+    /// Count the events.
+    pub(crate) fn from_events<'a, I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Event>,
+    {
+        let mut histogram = Self::default();
+        for event in events {
+            match event {
+                Event::Midi(Message::NoteOn(m)) if m.velocity.get() > 0 => histogram.note_ons += 1,
+                Event::Midi(Message::NoteOn(_)) | Event::Midi(Message::NoteOff(_)) => {
+                    histogram.note_offs += 1
+                }
+                Event::Midi(Message::Control(cc)) => {
+                    *histogram.control_changes.entry(cc.control()).or_default() += 1
+                }
+                Event::Midi(Message::ProgramChange(_)) => histogram.program_changes += 1,
+                Event::Midi(Message::PitchBend(_)) => histogram.pitch_bends += 1,
+                Event::Midi(_) => histogram.other_messages += 1,
+                Event::Meta(meta) => {
+                    *histogram.meta_events.entry(meta.meta_type()).or_default() += 1
+                }
+                Event::Sysex(_) => histogram.sysex_events += 1,
+            }
+        }
+        histogram
+    }
+
+    /// The number of note-on messages, not counting those with a velocity of zero.
+    pub fn note_ons(&self) -> usize {
+        self.note_ons
+    }
+
+    /// The number of note-off messages, including note-ons with a velocity of zero.
+    pub fn note_offs(&self) -> usize {
+        self.note_offs
+    }
+
+    /// The number of control change messages for each controller.
+    pub fn control_changes(&self) -> &BTreeMap<Control, usize> {
+        &self.control_changes
+    }
+
+    /// The number of program change messages.
+    pub fn program_changes(&self) -> usize {
+        self.program_changes
+    }
+
+    /// The number of pitch bend messages.
+    pub fn pitch_bends(&self) -> usize {
+        self.pitch_bends
+    }
+
+    /// The number of all other messages, such as pressure, channel mode and system messages.
+    pub fn other_messages(&self) -> usize {
+        self.other_messages
+    }
+
+    /// The number of meta events for each meta event type byte, e.g. `0x51` for `SetTempo`, see
+    /// [`crate::file::MetaEvent::meta_type`].
+    pub fn meta_events(&self) -> &BTreeMap<u8, usize> {
+        &self.meta_events
+    }
+
+    /// The number of sysex events.
+    pub fn sysex_events(&self) -> usize {
+        self.sysex_events
+    }
+}
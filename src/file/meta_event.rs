@@ -1,12 +1,12 @@
-use crate::byte_iter::ByteIter;
+use crate::byte_iter::{ByteIter, ByteSource};
 use crate::core::vlq::Vlq;
 use crate::core::{Channel, Clocks, DurationName, PortValue};
 use crate::error::{self, LibResult};
 use crate::scribe::Scribe;
-use crate::{Result, Text};
+use crate::{Result, Text, MAX_TEXT_LEN};
 use snafu::{ensure, OptionExt, ResultExt};
 use std::convert::TryFrom;
-use std::io::{Read, Write};
+use std::io::Write;
 
 /// Meta Events seem to only exist in the MIDI File Spec. Here is what it says about them:
 /// A few meta-events are defined herein. It is not required for every program to support every meta-event.
@@ -147,11 +147,48 @@ pub enum MetaEvent {
     Sequencer, // TODO - value
 
     /// `FF 0x21 0x01 value`: https://mido.readthedocs.io/en/latest/meta_message_types.html
+    ///
+    /// This obsolete event, sometimes called the MIDI cable event, is not in the spec. It is only
+    /// read when it has exactly one byte of data, any other length is an error.
     Port(PortValue),
+
+    /// `FF type len data`: A meta event with a type byte that this library does not recognize. The
+    /// data is preserved so that the event can be written back out unchanged.
+    Unknown {
+        /// The meta event type byte.
+        meta_type: u8,
+        /// The bytes that follow the length.
+        data: Vec<u8>,
+    },
 }
 
 impl MetaEvent {
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
+    /// The meta event type byte that follows `FF` in the file, e.g. `0x51` for `SetTempo`.
+    pub fn meta_type(&self) -> u8 {
+        match self {
+            MetaEvent::SequenceNumber => META_SEQUENCE_NUM,
+            MetaEvent::OtherText(_) => META_TEXT,
+            MetaEvent::Copyright(_) => META_COPYRIGHT,
+            MetaEvent::TrackName(_) => META_TRACK_NAME,
+            MetaEvent::InstrumentName(_) => META_INSTR_NAME,
+            MetaEvent::Lyric(_) => META_LYRIC,
+            MetaEvent::Marker(_) => META_MARKER,
+            MetaEvent::CuePoint(_) => META_CUE_POINT,
+            MetaEvent::ProgramName(_) => META_PROG_NAME,
+            MetaEvent::DeviceName(_) => META_DEVICE_NAME,
+            MetaEvent::MidiChannelPrefix(_) => META_CHAN_PREFIX,
+            MetaEvent::EndOfTrack => META_END_OF_TRACK,
+            MetaEvent::SetTempo(_) => META_SET_TEMPO,
+            MetaEvent::SmpteOffset(_) => META_SMTPE_OFFSET,
+            MetaEvent::TimeSignature(_) => META_TIME_SIG,
+            MetaEvent::KeySignature(_) => META_KEY_SIG,
+            MetaEvent::Sequencer => META_SEQ_SPECIFIC,
+            MetaEvent::Port(_) => META_PORT,
+            MetaEvent::Unknown { meta_type, .. } => *meta_type,
+        }
+    }
+
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
         iter.read_expect(0xff).context(io!())?;
         let meta_type_byte = iter.read_or_die().context(io!())?;
         match meta_type_byte {
@@ -160,7 +197,7 @@ impl MetaEvent {
             }
             META_TEXT..=META_DEVICE_NAME => MetaEvent::parse_text(iter),
             META_CHAN_PREFIX => {
-                iter.read_expect(LEN_META_CHAN_PREFIX).context(io!())?;
+                expect_length(iter, LEN_META_CHAN_PREFIX)?;
                 Ok(MetaEvent::MidiChannelPrefix(Channel::new(
                     iter.read_or_die().context(io!())?,
                 )))
@@ -173,11 +210,15 @@ impl MetaEvent {
             META_SEQ_SPECIFIC => {
                 noimpl!("Sequencer-Specific: https://github.com/webern/midi_file/issues/9")
             }
-            META_PORT => Ok(MetaEvent::Port(PortValue::new({
-                iter.read_expect(1).context(io!())?;
-                iter.read_or_die().context(io!())?
-            }))),
-            _ => invalid_file!("unrecognized byte {:#04X}", meta_type_byte),
+            META_PORT => MetaEvent::parse_port(iter),
+            _ => {
+                let length = read_length(iter)?;
+                let data = iter.read_n(length as usize).context(io!())?;
+                Ok(MetaEvent::Unknown {
+                    meta_type: meta_type_byte,
+                    data,
+                })
+            }
         }
     }
 
@@ -226,27 +267,46 @@ impl MetaEvent {
             }
             MetaEvent::Port(value) => {
                 write_u8!(w, META_PORT)?;
-                write_u8!(w, 1)?;
+                write_u8!(w, LEN_META_PORT)?;
                 write_u8!(w, value.get())
             }
+            MetaEvent::Unknown { meta_type, data } => write_data(w, *meta_type, data),
+        }
+    }
+
+    /// The port event is obsolete and was never part of the spec, so it is parsed on its own to
+    /// give a clear message when a file uses a length other than the common `FF 21 01 pp`.
+    fn parse_port<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
+        let length = read_length(iter)?;
+        if length != u32::from(LEN_META_PORT) {
+            invalid_file!(
+                "MIDI port meta event (FF 21) should have {} byte of data but declares {}, near \
+                byte {}",
+                LEN_META_PORT,
+                length,
+                iter.position().unwrap_or(0)
+            );
         }
+        Ok(MetaEvent::Port(PortValue::new(
+            iter.read_or_die().context(io!())?,
+        )))
     }
 
-    pub(crate) fn parse_end_of_track<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
+    pub(crate) fn parse_end_of_track<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
         // after 0x2f we should see 0x00
-        iter.read_expect(0x00).context(io!())?;
+        expect_length(iter, LEN_META_END_OF_TRACK)?;
         Ok(MetaEvent::EndOfTrack)
     }
 
-    pub(crate) fn parse_text<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
+    pub(crate) fn parse_text<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
         // we should be on a type-byte with a value between 0x01 and 0x09 (the text range).
         let text_type = iter
             .current()
             .context(error::OtherSnafu { site: site!() })?;
-        let length = iter.read_vlq_u32().context(io!())?;
+        let length = read_length(iter)?;
         let bytes = iter.read_n(length as usize).context(io!())?;
         // the spec does not strictly specify what encoding should be used for strings
-        let s: Text = bytes.into();
+        let s = Text::decode(bytes, iter.settings().text_encoding);
         match text_type {
             META_TEXT => Ok(MetaEvent::OtherText(s)),
             META_COPYRIGHT => Ok(MetaEvent::Copyright(s)),
@@ -262,9 +322,56 @@ impl MetaEvent {
     }
 }
 
+/// Read the length of a meta event's data, which must fit in what remains of the track chunk.
+fn read_length<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<u32> {
+    let meta_type = iter.current().unwrap_or_default();
+    let length = iter.read_vlq_u32().context(io!())?;
+    if let Some(remaining) = iter.remaining() {
+        if u64::from(length) > remaining {
+            invalid_file!(
+                "meta event {:#04X} declares {} bytes of data but only {} remain in the track, near \
+                byte {}",
+                meta_type,
+                length,
+                remaining,
+                iter.position().unwrap_or(0)
+            );
+        }
+    }
+    Ok(length)
+}
+
+/// Read the length of a meta event whose data has a fixed size, which must be `expected`.
+fn expect_length<S: ByteSource>(iter: &mut ByteIter<S>, expected: u8) -> LibResult<()> {
+    let meta_type = iter.current().unwrap_or_default();
+    let length = read_length(iter)?;
+    if length != u32::from(expected) {
+        invalid_file!(
+            "meta event {:#04X} should have {} bytes of data but declares {}, near byte {}",
+            meta_type,
+            expected,
+            length,
+            iter.position().unwrap_or(0)
+        );
+    }
+    Ok(())
+}
+
 fn write_text<W: Write>(w: &mut Scribe<W>, text_type: u8, text: &Text) -> LibResult<()> {
-    w.write_all(&text_type.to_be_bytes()).context(wr!())?;
-    let bytes = text.as_bytes();
+    let bytes = text.encode(w.text_encoding());
+    write_data(w, text_type, &bytes)
+}
+
+/// Writes the type byte, the VLQ length of `bytes`, and then `bytes`.
+fn write_data<W: Write>(w: &mut Scribe<W>, meta_type: u8, bytes: &[u8]) -> LibResult<()> {
+    ensure!(
+        bytes.len() <= MAX_TEXT_LEN,
+        error::TextTooLongSnafu {
+            site: site!(),
+            len: bytes.len()
+        }
+    );
+    w.write_all(&meta_type.to_be_bytes()).context(wr!())?;
     let size_u32 =
         u32::try_from(bytes.len()).context(error::StringTooLongSnafu { site: site!() })?;
     let size = Vlq::new(size_u32).to_bytes();
@@ -274,6 +381,7 @@ fn write_text<W: Write>(w: &mut Scribe<W>, text_type: u8, text: &Text) -> LibRes
 }
 
 // TODO - create some interface for this, constrict it's values, etc.
+/// The SMPTE time at which a track chunk is supposed to start, see [`MetaEvent::SmpteOffset`].
 #[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
 pub struct SmpteOffsetValue {
     // TODO - these are held as raw bytes for now without caring about their meaning or signedness.
@@ -288,9 +396,9 @@ impl SmpteOffsetValue {
     // TODO - create a `new` function.
     // TODO - create getters.
 
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
         // after 0x54 we should see 0x05
-        iter.read_expect(LEN_META_SMTPE_OFFSET).context(io!())?;
+        expect_length(iter, LEN_META_SMTPE_OFFSET)?;
         Ok(Self {
             hr: iter.read_or_die().context(io!())?,
             mn: iter.read_or_die().context(io!())?,
@@ -339,6 +447,7 @@ pub(crate) const LEN_META_SET_TEMPO: u8 = 3;
 pub(crate) const LEN_META_SMTPE_OFFSET: u8 = 5;
 pub(crate) const LEN_META_TIME_SIG: u8 = 4;
 pub(crate) const LEN_META_KEY_SIG: u8 = 2;
+pub(crate) const LEN_META_PORT: u8 = 1;
 
 /// FF 58 04 nn dd cc bb Time Signature
 /// The time signature is expressed as four numbers. nn and dd represent the numerator and
@@ -378,7 +487,7 @@ pub struct TimeSignatureValue {
     /// of as a quarter-note (24 MIDI Clocks). This was added because there are already multiple
     /// programs which allow the user to specify that what MIDI thinks of as a quarter-note (24
     /// clocks) is to be notated as, or related to in terms of, something else."
-    tpq: u8,
+    pub(crate) tpq: u8,
 }
 
 impl TimeSignatureValue {
@@ -393,6 +502,16 @@ impl TimeSignatureValue {
         })
     }
 
+    /// 4/4 with a click every quarter note, which is assumed when a file has no time signature.
+    pub(crate) fn common_time() -> Self {
+        Self {
+            numerator: 4,
+            denominator: DurationName::Quarter,
+            click: Clocks::Quarter,
+            tpq: 8,
+        }
+    }
+
     /// A getter for the `numerator` field.
     pub fn numerator(&self) -> u8 {
         self.numerator
@@ -408,8 +527,16 @@ impl TimeSignatureValue {
         self.click
     }
 
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
-        iter.read_expect(LEN_META_TIME_SIG).context(io!())?;
+    /// Replace a click of `Clocks::Other` with the named variant for the same number of MIDI
+    /// clocks, if there is one, see [`Clocks::resolve`]. A parsed click is already resolved, so
+    /// this is only needed for a value that was created with `Clocks::Other`. The number of MIDI
+    /// clocks, and so the written byte, does not change.
+    pub fn resolve_click(&mut self) {
+        self.click.resolve()
+    }
+
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
+        expect_length(iter, LEN_META_TIME_SIG)?;
         Ok(Self {
             numerator: iter.read_or_die().context(io!())?,
             denominator: DurationName::from_u8(iter.read_or_die().context(io!())?)?,
@@ -441,13 +568,17 @@ clamp!(
     pub
 );
 
+/// Whether a key signature is major or minor.
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash, Default)]
 pub enum KeyMode {
+    /// `mi = 0`: a major key.
     #[default]
     Major,
+    /// `mi = 1`: a minor key.
     Minor,
 }
 
+/// The value of a [`MetaEvent::KeySignature`], i.e. the number of sharps or flats and the mode.
 #[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
 pub struct KeySignatureValue {
     accidentals: KeyAccidentals,
@@ -470,8 +601,8 @@ impl KeySignatureValue {
         self.mode
     }
 
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
-        iter.read_expect(LEN_META_KEY_SIG).context(io!())?;
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
+        expect_length(iter, LEN_META_KEY_SIG)?;
         let raw_accidentals_byte = iter.read_or_die().context(io!())?;
         let casted_accidentals = raw_accidentals_byte as i8;
         Ok(Self {
@@ -523,8 +654,8 @@ clamp!(
 );
 
 impl MicrosecondsPerQuarter {
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
-        iter.read_expect(LEN_META_SET_TEMPO).context(io!())?;
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
+        expect_length(iter, LEN_META_SET_TEMPO)?;
         let bytes = iter.read_n(LEN_META_SET_TEMPO as usize).context(io!())?;
         // bytes is a big-endian u24. fit it into a big-endian u32 then parse it
         let beu32 = [0u8, bytes[0], bytes[1], bytes[2]];
@@ -533,6 +664,15 @@ impl MicrosecondsPerQuarter {
     }
 }
 
+impl From<QuartersPerMinute> for MicrosecondsPerQuarter {
+    fn from(quarters_per_minute: QuartersPerMinute) -> Self {
+        let minutes_per_quarter = 1f64 / f64::from(quarters_per_minute.get());
+        let seconds_per_quarter = minutes_per_quarter * 60f64;
+        let microseconds_per_quarter = seconds_per_quarter * 1000000f64;
+        MicrosecondsPerQuarter::new(microseconds_per_quarter as u32)
+    }
+}
+
 clamp!(
     /// A more convenient way to specify tempo, not part of the MIDI spec. This is closer to the way
     /// we think of tempo, e.g. "120 Beats per Minute". This type is locked to quarter-notes so you
@@ -545,3 +685,36 @@ clamp!(
     120,
     pub
 );
+
+#[test]
+fn time_signature_click_test() {
+    use crate::scribe::ScribeSettings;
+    use std::io::Cursor;
+    let write = |value: &TimeSignatureValue| -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value
+            .write(&mut Scribe::new(&mut bytes, ScribeSettings::default()))
+            .unwrap();
+        bytes
+    };
+    // 6/8 with a click of 24 clocks
+    let bytes = [META_TIME_SIG, LEN_META_TIME_SIG, 6, 3, 24, 8];
+    let mut iter = ByteIter::new(Cursor::new(bytes)).unwrap();
+    iter.read_or_die().unwrap();
+    let mut parsed = TimeSignatureValue::parse(&mut iter).unwrap();
+    parsed.resolve_click();
+    assert_eq!(parsed.click(), Clocks::Quarter);
+    assert_eq!(write(&parsed), bytes);
+
+    let mut value = TimeSignatureValue::new(6, DurationName::Eighth, Clocks::Other(24)).unwrap();
+    assert_eq!(write(&value)[4], 24);
+    value.resolve_click();
+    assert_eq!(value.click(), Clocks::Quarter);
+    assert_eq!(write(&value)[..5], bytes[..5]);
+
+    // a click without a name is written unchanged
+    let mut value = TimeSignatureValue::new(6, DurationName::Eighth, Clocks::Other(7)).unwrap();
+    value.resolve_click();
+    assert_eq!(value.click(), Clocks::Other(7));
+    assert_eq!(write(&value)[4], 7);
+}
@@ -1,18 +1,41 @@
 //! The `file` module is for types and concepts strictly related to MIDI *files*.
 //! These are separated from types and concepts that are also used in realtime MIDI (`core`).
 
+mod builder;
+mod conductor;
+mod diff;
 mod division;
 mod event;
 mod header;
+mod histogram;
 mod meta_event;
+mod note;
+mod stream;
 mod sysex;
+mod timing;
 mod track;
+mod validation;
 
-pub use division::{Division, QuarterNoteDivision};
+pub use builder::TrackBuilder;
+pub use conductor::{ConductorSummary, TempoSegment};
+pub use diff::Difference;
+pub use division::{Division, FrameRate, QuarterNoteDivision};
 pub use event::{Event, TrackEvent};
 pub use header::{Format, Header};
-pub use meta_event::{MetaEvent, MicrosecondsPerQuarter, QuartersPerMinute, TimeSignatureValue};
+pub use histogram::EventHistogram;
+pub use meta_event::{
+    KeyAccidentals, KeyMode, KeySignatureValue, MetaEvent, MicrosecondsPerQuarter,
+    QuartersPerMinute, SmpteOffsetValue, TimeSignatureValue,
+};
+pub use note::Note;
+pub use stream::EventStream;
 pub use sysex::{SysexEvent, SysexEventType};
+pub use timing::{NoteEventMs, ScheduledEvent};
 pub use track::Track;
+pub use validation::{StuckNote, ValidationWarning, ValidationWarningKind};
 
-pub(crate) use track::ensure_end_of_track;
+pub(crate) use diff::diff;
+pub(crate) use note::all_notes_sorted;
+pub(crate) use timing::{note_events_ms, scheduled};
+pub(crate) use track::{ensure_end_of_track, ensure_tempo};
+pub(crate) use validation::{stuck_notes, validate};
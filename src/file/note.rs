@@ -0,0 +1,86 @@
+use crate::core::{Channel, Message, NoteNumber, Velocity};
+use crate::file::Event;
+use crate::MidiFile;
+
+/// A note-on paired with the note-off that ends it, see [`MidiFile::all_notes_sorted`].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct Note {
+    track: u32,
+    channel: Channel,
+    note_number: NoteNumber,
+    velocity: Velocity,
+    start_tick: u64,
+    end_tick: u64,
+}
+
+impl Note {
+    /// The zero-based index of the track.
+    pub fn track(&self) -> u32 {
+        self.track
+    }
+
+    /// A getter for the `channel` field.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// A getter for the `note_number` field.
+    pub fn note_number(&self) -> NoteNumber {
+        self.note_number
+    }
+
+    /// The velocity of the note-on.
+    pub fn velocity(&self) -> Velocity {
+        self.velocity
+    }
+
+    /// The absolute tick of the note-on.
+    pub fn start_tick(&self) -> u64 {
+        self.start_tick
+    }
+
+    /// The absolute tick of the note-off.
+    pub fn end_tick(&self) -> u64 {
+        self.end_tick
+    }
+
+    /// The length of the note in ticks.
+    pub fn duration_ticks(&self) -> u64 {
+        self.end_tick.saturating_sub(self.start_tick)
+    }
+}
+
+/// Collects the notes of every track, sorted by start tick, then note number, then track. A note
+/// that is never turned off ends at the end of its track.
+pub(crate) fn all_notes_sorted(mfile: &MidiFile) -> Vec<Note> {
+    let mut notes = Vec::new();
+    for (track_ix, track) in mfile.tracks().enumerate() {
+        let ticks: Vec<u64> = track
+            .events()
+            .scan(0u64, |tick, event| {
+                *tick += u64::from(event.delta_time());
+                Some(*tick)
+            })
+            .collect();
+        for (on_ix, off_ix) in track.note_pairs() {
+            if let Event::Midi(Message::NoteOn(m)) = track[on_ix].event() {
+                let end_tick = match off_ix {
+                    Some(off_ix) => ticks[off_ix],
+                    None => track.duration_ticks(),
+                };
+                notes.push(Note {
+                    // the number of tracks is limited to u32 by MidiFile
+                    track: track_ix as u32,
+                    channel: m.channel(),
+                    note_number: m.note_number(),
+                    velocity: m.velocity(),
+                    start_tick: ticks[on_ix],
+                    // a note never ends before it starts
+                    end_tick: end_tick.max(ticks[on_ix]),
+                });
+            }
+        }
+    }
+    notes.sort_by_key(|note| (note.start_tick, note.note_number, note.track));
+    notes
+}
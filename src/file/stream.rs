@@ -0,0 +1,76 @@
+use crate::byte_iter::{ByteIter, ReadSource};
+use crate::error::LibResult;
+use crate::file::{Event, Header, MetaEvent, Track, TrackEvent};
+use std::io::Read;
+
+/// An iterator that parses a MIDI file one event at a time, see
+/// [`crate::MidiFile::stream_events`]. Each item is `(track_index, event)`. Only the header and the
+/// event currently being parsed are held in memory.
+pub struct EventStream<R: Read> {
+    iter: ByteIter<ReadSource<R>>,
+    header: Header,
+    num_tracks: u16,
+    track_index: u16,
+    in_track: bool,
+    done: bool,
+}
+
+impl<R: Read> EventStream<R> {
+    pub(crate) fn new(mut iter: ByteIter<ReadSource<R>>) -> LibResult<Self> {
+        let (header, num_tracks) = Header::parse(&mut iter)?;
+        Ok(Self {
+            iter,
+            header,
+            num_tracks,
+            track_index: 0,
+            in_track: false,
+            done: num_tracks == 0,
+        })
+    }
+
+    /// The header of the file being streamed.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The number of tracks declared by the header.
+    pub fn tracks_len(&self) -> u16 {
+        self.num_tracks
+    }
+
+    fn next_event(&mut self) -> LibResult<(u32, TrackEvent)> {
+        let empty_track = if self.in_track {
+            false
+        } else {
+            self.in_track = true;
+            Track::parse_chunk_header(&mut self.iter)?
+        };
+        let event = if empty_track {
+            TrackEvent::new(0, Event::Meta(MetaEvent::EndOfTrack))
+        } else {
+            Track::parse_event(&mut self.iter)?
+        };
+        let track_index = u32::from(self.track_index);
+        if event.is_end() {
+            self.in_track = false;
+            self.track_index += 1;
+            self.done = self.track_index >= self.num_tracks;
+        }
+        Ok((track_index, event))
+    }
+}
+
+impl<R: Read> Iterator for EventStream<R> {
+    type Item = crate::Result<(u32, TrackEvent)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_event();
+        if result.is_err() {
+            self.done = true;
+        }
+        Some(result.map_err(Into::into))
+    }
+}
@@ -1,7 +1,7 @@
-use crate::byte_iter::ByteIter;
+use crate::byte_iter::{ByteIter, ByteSource};
 use crate::error::LibResult;
 use crate::scribe::Scribe;
-use std::io::{Read, Write};
+use std::io::Write;
 
 // TODO - implement sysex messages
 /// Caution: Sysex messages are [not implemented](https://github.com/webern/midi_file/issues/7) and
@@ -16,7 +16,7 @@ impl SysexEvent {
     // TODO - implement a `new` function.
     // TODO - implement getter functions.
 
-    pub(crate) fn parse<R: Read>(_first_byte: u8, _r: &mut ByteIter<R>) -> LibResult<Self> {
+    pub(crate) fn parse<S: ByteSource>(_first_byte: u8, _r: &mut ByteIter<S>) -> LibResult<Self> {
         noimpl!("SysexEvent::parse")
     }
 
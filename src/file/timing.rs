@@ -0,0 +1,187 @@
+use crate::core::{Channel, Message, NoteNumber, Velocity};
+use crate::file::note::all_notes_sorted;
+use crate::file::{Division, Event, FrameRate, MicrosecondsPerQuarter};
+use crate::MidiFile;
+use std::convert::TryFrom;
+
+/// A note with its start and end converted from ticks to milliseconds, see
+/// [`MidiFile::note_events_ms`].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct NoteEventMs {
+    track: u32,
+    channel: Channel,
+    note_number: NoteNumber,
+    velocity: Velocity,
+    start_ms: u64,
+    end_ms: u64,
+}
+
+impl NoteEventMs {
+    /// The zero-based index of the track.
+    pub fn track(&self) -> u32 {
+        self.track
+    }
+
+    /// A getter for the `channel` field.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// A getter for the `note_number` field.
+    pub fn note_number(&self) -> NoteNumber {
+        self.note_number
+    }
+
+    /// The velocity of the note-on.
+    pub fn velocity(&self) -> Velocity {
+        self.velocity
+    }
+
+    /// The time of the note-on, in milliseconds from the start of the file.
+    pub fn start_ms(&self) -> u64 {
+        self.start_ms
+    }
+
+    /// The time of the note-off, in milliseconds from the start of the file.
+    pub fn end_ms(&self) -> u64 {
+        self.end_ms
+    }
+}
+
+/// A MIDI message with its time converted from ticks to milliseconds, see
+/// [`MidiFile::scheduled`].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct ScheduledEvent {
+    time_ms: u64,
+    track: u32,
+    message: Message,
+}
+
+impl ScheduledEvent {
+    /// The time at which the message should be sent, in milliseconds from the start of the file.
+    pub fn time_ms(&self) -> u64 {
+        self.time_ms
+    }
+
+    /// The zero-based index of the track.
+    pub fn track(&self) -> u32 {
+        self.track
+    }
+
+    /// A getter for the `message` field.
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+}
+
+/// Converts absolute ticks to microseconds. Each point is `(tick, rate, scaled_start)` where `rate`
+/// is the number of scaled microseconds per tick from `tick` until the next point, and
+/// `scaled_start` is the scaled time at `tick`. Dividing a scaled time by `divisor` gives
+/// microseconds. Keeping the scaled times exact means that rounding happens only once, no matter
+/// how many tempo changes there are.
+struct Clock {
+    points: Vec<(u64, u128, u128)>,
+    divisor: u128,
+}
+
+impl Clock {
+    fn new(mfile: &MidiFile) -> Self {
+        let ppq = match mfile.header().division() {
+            Division::QuarterNote(ppq) => ppq.get(),
+            Division::Smpte(smpte) => {
+                // the tempo has no effect, every tick is a fixed fraction of a frame
+                let (frames, seconds) = match smpte.frame_rate() {
+                    FrameRate::N24 => (24, 1),
+                    FrameRate::N25 => (25, 1),
+                    FrameRate::N29 => (30000, 1001),
+                    FrameRate::N30 => (30, 1),
+                };
+                return Self {
+                    points: vec![(0, 1_000_000 * seconds, 0)],
+                    divisor: frames * u128::from(smpte.resolution()),
+                };
+            }
+        };
+        let default_tempo = u128::from(MicrosecondsPerQuarter::default().get());
+        let mut points = vec![(0u64, default_tempo, 0u128)];
+        for segment in mfile.conductor_summary().tempo_segments() {
+            let scaled = Self::scaled(&points, segment.start());
+            let tempo = u128::from(segment.tempo().get());
+            match points.last_mut() {
+                Some(last) if last.0 == segment.start() => *last = (segment.start(), tempo, scaled),
+                _ => points.push((segment.start(), tempo, scaled)),
+            }
+        }
+        Self {
+            points,
+            divisor: u128::from(ppq.max(1)),
+        }
+    }
+
+    fn scaled(points: &[(u64, u128, u128)], tick: u64) -> u128 {
+        let ix = points.partition_point(|(start, _, _)| *start <= tick);
+        let (start, rate, scaled_start) = points[ix.saturating_sub(1)];
+        scaled_start + u128::from(tick - start) * rate
+    }
+
+    fn millis(&self, tick: u64) -> u64 {
+        let millis = Self::scaled(&self.points, tick) / (self.divisor * 1000);
+        u64::try_from(millis).unwrap_or(u64::MAX)
+    }
+}
+
+pub(crate) fn note_events_ms(mfile: &MidiFile) -> Vec<NoteEventMs> {
+    let clock = Clock::new(mfile);
+    let mut notes: Vec<NoteEventMs> = all_notes_sorted(mfile)
+        .into_iter()
+        .map(|note| NoteEventMs {
+            track: note.track(),
+            channel: note.channel(),
+            note_number: note.note_number(),
+            velocity: note.velocity(),
+            start_ms: clock.millis(note.start_tick()),
+            end_ms: clock.millis(note.end_tick()),
+        })
+        .collect();
+    notes.sort_by_key(|note| (note.start_ms, note.track));
+    notes
+}
+
+pub(crate) fn scheduled(mfile: &MidiFile) -> Vec<ScheduledEvent> {
+    let clock = Clock::new(mfile);
+    // events are sorted by tick, and time never goes backwards as ticks increase
+    mfile
+        .iter_events_timesorted()
+        .into_iter()
+        .filter_map(|(track, tick, event)| match event {
+            Event::Midi(message) => Some(ScheduledEvent {
+                time_ms: clock.millis(tick),
+                track,
+                message: *message,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn clock_test() {
+    use crate::file::{Event, MetaEvent, QuarterNoteDivision, TrackBuilder};
+    use crate::Settings;
+    let conductor = TrackBuilder::new()
+        .event(
+            480,
+            Event::Meta(MetaEvent::SetTempo(MicrosecondsPerQuarter::new(250_000))),
+        )
+        .build()
+        .unwrap();
+    let settings = Settings::new().divisions(Division::QuarterNote(QuarterNoteDivision::new(480)));
+    let mut mfile = MidiFile::new_with_settings(settings);
+    mfile.push_track(conductor).unwrap();
+    let clock = Clock::new(&mfile);
+    assert_eq!(clock.millis(0), 0);
+    assert_eq!(clock.millis(240), 250);
+    assert_eq!(clock.millis(480), 500);
+    assert_eq!(clock.millis(960), 750);
+    assert_eq!(clock.millis(961), 750);
+}
@@ -1,18 +1,26 @@
-use crate::byte_iter::ByteIter;
+use crate::byte_iter::{ByteIter, ByteSource};
+use crate::core::vlq::{clamp_vlq, Vlq, MAX_VLQ_VALUE};
 use crate::core::{
-    Channel, Clocks, DurationName, GeneralMidi, Message, NoteMessage, NoteNumber, PitchBendMessage,
-    PitchBendValue, Program, ProgramChangeValue, Velocity,
+    Channel, Clocks, Control, ControlChangeValue, ControlValue, DurationName, GeneralMidi,
+    GmPercussion, Message, NoteMessage, NoteNumber, PitchBendMessage, PitchBendValue, Program,
+    ProgramChangeValue, Velocity,
 };
-use crate::error::LibResult;
+use crate::error::{self, LibResult};
 use crate::file::{
     Event, MetaEvent, MicrosecondsPerQuarter, QuartersPerMinute, TimeSignatureValue, TrackEvent,
 };
-use crate::scribe::{Scribe, ScribeSettings};
+use crate::scribe::Scribe;
 use crate::Text;
-use log::{debug, trace};
-use snafu::ResultExt;
+use log::{debug, trace, warn};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::convert::TryFrom;
-use std::io::{Read, Write};
+use std::io::Write;
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut};
+
+/// The zero-based channel that General MIDI uses for percussion, i.e. channel 10.
+const PERCUSSION_CHANNEL: u8 = 9;
 
 /// 2.3 - Track Chunks
 /// The track chunks (type MTrk) are where actual song data is stored. Each track chunk is simply a
@@ -30,47 +38,357 @@ pub struct Track {
 }
 
 impl Track {
+    /// Create a track from events at absolute ticks, which is the inverse of
+    /// [`crate::MidiFile::iter_events`]. The events are sorted by tick, keeping the given order of
+    /// events at the same tick, and their delta times are calculated. Any `EndOfTrack` events are
+    /// removed, and a single `EndOfTrack` is added at the tick of the last event, or at the tick of
+    /// the latest `EndOfTrack` that was removed if it is later. Returns an error if two consecutive
+    /// events are more than `0x0FFFFFFF` ticks apart, which is the largest delta time that can be
+    /// written to a file.
+    pub fn from_absolute_events<I>(events: I) -> crate::Result<Track>
+    where
+        I: IntoIterator<Item = (u64, Event)>,
+    {
+        Ok(Self::from_absolute_events_inner(events)?)
+    }
+
+    /// Create a track from events with delta times, adding an `EndOfTrack` event at the end if the
+    /// last event is not one. Together with [`Track::into_events`] this allows any editing that the
+    /// other methods do not provide, such as sorting or filtering the events. Delta times larger
+    /// than `0x0FFFFFFF` are clamped to `0x0FFFFFFF`.
+    pub fn from_events(mut events: Vec<TrackEvent>) -> Track {
+        for event in events.iter_mut() {
+            event.delta_time = clamp_vlq(u64::from(event.delta_time));
+        }
+        let mut track = Track { events };
+        if !track.events.last().is_some_and(TrackEvent::is_end) {
+            track
+                .events
+                .push(TrackEvent::new(0, Event::Meta(MetaEvent::EndOfTrack)));
+        }
+        track
+    }
+
+    /// Take the events out of the track, see [`Track::from_events`].
+    pub fn into_events(self) -> Vec<TrackEvent> {
+        self.events
+    }
+
+    fn from_absolute_events_inner<I>(events: I) -> LibResult<Track>
+    where
+        I: IntoIterator<Item = (u64, Event)>,
+    {
+        let mut end_tick = 0u64;
+        let mut events: Vec<(u64, Event)> = events
+            .into_iter()
+            .filter(|(tick, event)| {
+                end_tick = end_tick.max(*tick);
+                !matches!(event, Event::Meta(MetaEvent::EndOfTrack))
+            })
+            .collect();
+        events.sort_by_key(|(tick, _)| *tick);
+        events.push((end_tick, Event::Meta(MetaEvent::EndOfTrack)));
+        let mut track = Track::default();
+        let mut previous = 0u64;
+        for (tick, event) in events {
+            let delta_time = u32::try_from(tick - previous)
+                .context(error::TrackTooLongSnafu { site: site!() })?;
+            check_delta_time(delta_time)?;
+            track.events.push(TrackEvent::new(delta_time, event));
+            previous = tick;
+        }
+        Ok(track)
+    }
+
     /// Returns `true` if the track has no events.
     pub fn is_empty(&self) -> bool {
         self.events.is_empty()
     }
 
+    /// Returns `true` if the track looks like a conductor track, that is, it has at least one
+    /// tempo, time signature or key signature event, and no channel messages. By convention this is
+    /// the first track of a [`crate::file::Format::Multi`] file.
+    pub fn is_conductor(&self) -> bool {
+        let mut has_conductor_event = false;
+        for event in &self.events {
+            match &event.event {
+                Event::Midi(message) if message.channel().is_some() => return false,
+                Event::Meta(MetaEvent::SetTempo(_))
+                | Event::Meta(MetaEvent::TimeSignature(_))
+                | Event::Meta(MetaEvent::KeySignature(_)) => has_conductor_event = true,
+                _ => {}
+            }
+        }
+        has_conductor_event
+    }
+
     /// The number of events in the track.
     pub fn events_len(&self) -> usize {
         self.events.len()
     }
 
-    // TODO - maybe implement Iterator and IntoIterator on this type instead of doing this.
-    /// Iterator over the events in the track.
+    /// Iterator over the events in the track. You can also iterate with `for event in &track`.
     pub fn events(&self) -> impl Iterator<Item = &TrackEvent> {
         self.events.iter()
     }
 
-    /// Add an event to the end.
+    /// The length of the track in ticks, which is the sum of the delta times of all events up to
+    /// and including the first `EndOfTrack` event, or of all events if there is none.
+    pub fn duration_ticks(&self) -> u64 {
+        let mut ticks = 0u64;
+        for event in &self.events {
+            ticks += u64::from(event.delta_time);
+            if matches!(event.event, Event::Meta(MetaEvent::EndOfTrack)) {
+                break;
+            }
+        }
+        ticks
+    }
+
+    /// The notes that are sounding at `tick`, in the order that they started. A note that starts at
+    /// `tick` is sounding, and a note that ends at `tick` is not. A note-on with a velocity of zero
+    /// is treated as a note-off.
+    pub fn notes_sounding_at(&self, tick: u64) -> Vec<(Channel, NoteNumber)> {
+        let mut sounding: Vec<(Channel, NoteNumber)> = Vec::new();
+        let mut current = 0u64;
+        for event in &self.events {
+            current += u64::from(event.delta_time);
+            if current > tick {
+                break;
+            }
+            match &event.event {
+                Event::Midi(Message::NoteOn(m)) if m.velocity.get() > 0 => {
+                    sounding.push((m.channel, m.note_number))
+                }
+                Event::Midi(Message::NoteOn(m)) | Event::Midi(Message::NoteOff(m)) => {
+                    if let Some(ix) = sounding
+                        .iter()
+                        .position(|&note| note == (m.channel, m.note_number))
+                    {
+                        sounding.remove(ix);
+                    }
+                }
+                _ => {}
+            }
+        }
+        sounding
+    }
+
+    /// Split the track into the events before `tick` and the events at or after `tick`. Each half
+    /// ends with an `EndOfTrack`, the first at `tick`, and the second half starts at `tick`, so its
+    /// delta times are relative to the split. A note that is sounding across the split is cut: a
+    /// note-off is added at the end of the first half, and the note is struck again, with its
+    /// original velocity, at the start of the second half. A note-off at `tick` that ends a note
+    /// from the first half stays in the first half. Returns an error if a delta time overflows.
+    pub fn split_at_tick(&self, tick: u64) -> crate::Result<(Track, Track)> {
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        let mut sounding: Vec<NoteMessage> = Vec::new();
+        let mut current = 0u64;
+        for event in &self.events {
+            current += u64::from(event.delta_time);
+            if event.is_end() {
+                continue;
+            }
+            let note_off = match &event.event {
+                Event::Midi(Message::NoteOn(m)) if m.velocity.get() > 0 => None,
+                Event::Midi(Message::NoteOn(m)) | Event::Midi(Message::NoteOff(m)) => Some(m),
+                _ => None,
+            };
+            let ends_sounding_note = note_off.and_then(|m| {
+                sounding
+                    .iter()
+                    .position(|on| (on.channel, on.note_number) == (m.channel, m.note_number))
+            });
+            if current < tick || (current == tick && ends_sounding_note.is_some()) {
+                match (&event.event, ends_sounding_note) {
+                    (_, Some(ix)) => {
+                        sounding.remove(ix);
+                    }
+                    (Event::Midi(Message::NoteOn(m)), None) if m.velocity.get() > 0 => {
+                        sounding.push(*m)
+                    }
+                    _ => {}
+                }
+                before.push((current, event.event.clone()));
+            } else {
+                after.push((current - tick, event.event.clone()));
+            }
+        }
+        let end = current.max(tick);
+        let mut restruck = Vec::new();
+        for m in sounding {
+            let note_off = NoteMessage {
+                velocity: Velocity::new(64),
+                ..m
+            };
+            before.push((tick, Event::Midi(Message::NoteOff(note_off))));
+            restruck.push((0, Event::Midi(Message::NoteOn(m))));
+        }
+        before.push((tick, Event::Meta(MetaEvent::EndOfTrack)));
+        after.push((end - tick, Event::Meta(MetaEvent::EndOfTrack)));
+        Ok((
+            Track::from_absolute_events(before)?,
+            Track::from_absolute_events(restruck.into_iter().chain(after))?,
+        ))
+    }
+
+    /// Add an event to the end. Returns an error if `delta_time` is too large to be written, i.e.
+    /// larger than `0x0FFFFFFF`.
     pub fn push_event(&mut self, delta_time: u32, event: Event) -> crate::Result<()> {
-        // TODO check length is not bigger than u32
+        check_delta_time(delta_time)?;
         self.events.push(TrackEvent::new(delta_time, event));
         Ok(())
     }
 
-    /// Add event at `index` and shift everything after it.
+    /// Add event at `index` and shift everything after it. The delta times of the other events are
+    /// not changed, so every event after `index` is moved `delta_time` ticks later. Use
+    /// [`Track::insert_event_preserving_time`] to leave the later events where they are. Returns an
+    /// error if `index` is past the end of the track or if `delta_time` is larger than
+    /// `0x0FFFFFFF`.
     pub fn insert_event(&mut self, index: u32, delta_time: u32, event: Event) -> crate::Result<()> {
-        // TODO check length is not bigger than u32, index is in range, etc
+        check_delta_time(delta_time)?;
+        let index = index as usize;
+        ensure!(
+            index <= self.events.len(),
+            error::OtherSnafu { site: site!() }
+        );
         self.events
-            .insert(index as usize, TrackEvent::new(delta_time, event));
+            .insert(index, TrackEvent::new(delta_time, event));
         Ok(())
     }
 
-    /// Replace the event at `index`.
+    /// Add event at `index`, `delta_time` ticks after the event before it, and subtract `delta_time`
+    /// from the delta time of the event that was at `index` so that its absolute tick, and the
+    /// absolute tick of every event after it, is unchanged. Returns an error, and leaves the track
+    /// unchanged, if `index` is past the end of the track or if `delta_time` is larger than the
+    /// delta time of the event at `index`, which would put the events out of order.
+    pub fn insert_event_preserving_time(
+        &mut self,
+        index: u32,
+        delta_time: u32,
+        event: Event,
+    ) -> crate::Result<()> {
+        check_delta_time(delta_time)?;
+        let index = index as usize;
+        ensure!(
+            index <= self.events.len(),
+            error::OtherSnafu { site: site!() }
+        );
+        if let Some(next) = self.events.get_mut(index) {
+            next.delta_time = next
+                .delta_time
+                .checked_sub(delta_time)
+                .context(error::OtherSnafu { site: site!() })?;
+        }
+        self.events
+            .insert(index, TrackEvent::new(delta_time, event));
+        Ok(())
+    }
+
+    /// Add an event at an absolute tick. The delta time of the new event, and of the event that
+    /// follows it, are set so that the absolute tick of every other event is unchanged. If other
+    /// events occur at `absolute_tick`, the new event is inserted after them. An `EndOfTrack` event
+    /// stays last, and is moved later if necessary.
+    pub fn insert_event_at_tick(&mut self, absolute_tick: u64, event: Event) -> crate::Result<()> {
+        Ok(self.insert_event_at_tick_inner(absolute_tick, event)?)
+    }
+
+    fn insert_event_at_tick_inner(&mut self, absolute_tick: u64, event: Event) -> LibResult<()> {
+        let mut index = self.events.len();
+        let mut previous_tick = 0u64;
+        let mut tick = 0u64;
+        for (ix, track_event) in self.events.iter().enumerate() {
+            tick += u64::from(track_event.delta_time);
+            if tick > absolute_tick || track_event.is_end() {
+                index = ix;
+                break;
+            }
+            previous_tick = tick;
+        }
+        let delta_time = u32::try_from(absolute_tick - previous_tick)
+            .ok()
+            .context(error::OtherSnafu { site: site!() })?;
+        check_delta_time(delta_time)?;
+        if let Some(next) = self.events.get_mut(index) {
+            // saturates to zero when an EndOfTrack is pushed later by the new event
+            let next_tick = previous_tick + u64::from(next.delta_time);
+            next.delta_time = u32::try_from(next_tick.saturating_sub(absolute_tick)).unwrap_or(0);
+        }
+        self.events
+            .insert(index, TrackEvent::new(delta_time, event));
+        Ok(())
+    }
+
+    /// Move every event by `delta_ticks` by changing the delta time of the first event, so the
+    /// delta times of the other events are unchanged. A negative shift can only remove the silence
+    /// before the first event. Returns an error, and leaves the track unchanged, if the first event
+    /// would move before tick zero or its delta time would overflow. An empty track is unchanged.
+    pub fn shift(&mut self, delta_ticks: i64) -> crate::Result<()> {
+        Ok(self.shift_inner(delta_ticks)?)
+    }
+
+    fn shift_inner(&mut self, delta_ticks: i64) -> LibResult<()> {
+        let first = match self.events.first_mut() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+        let shifted = i64::from(first.delta_time) + delta_ticks;
+        let delta_time = u32::try_from(shifted)
+            .ok()
+            .context(error::OtherSnafu { site: site!() })?;
+        check_delta_time(delta_time)?;
+        first.delta_time = delta_time;
+        Ok(())
+    }
+
+    /// Remove `ticks` of time from the start of the track by reducing delta times, starting with
+    /// the first event. Events that occur before `ticks` are moved to tick zero in their original
+    /// order, and later events move earlier by `ticks`.
+    pub(crate) fn trim_start(&mut self, ticks: u64) {
+        let mut remaining = ticks;
+        for event in self.events.iter_mut() {
+            if remaining == 0 {
+                break;
+            }
+            // the smaller of the two fits in a u32
+            let removed = remaining.min(u64::from(event.delta_time)) as u32;
+            event.delta_time -= removed;
+            remaining -= u64::from(removed);
+        }
+    }
+
+    /// Move the `EndOfTrack` event to `tick`, or to the last of the other events if that is later.
+    /// An `EndOfTrack` is added if the track does not end with one. The `EndOfTrack` is placed at
+    /// most `0x0FFFFFFF` ticks after the last of the other events.
+    pub(crate) fn set_end_tick(&mut self, tick: u64) {
+        if !matches!(self.events.last(), Some(event) if event.is_end()) {
+            self.events
+                .push(TrackEvent::new(0, Event::Meta(MetaEvent::EndOfTrack)));
+        }
+        let last_ix = self.events.len() - 1;
+        let last_event_tick: u64 = self.events[..last_ix]
+            .iter()
+            .map(|event| u64::from(event.delta_time))
+            .sum();
+        let delta_time = tick.saturating_sub(last_event_tick);
+        self.events[last_ix].delta_time = clamp_vlq(delta_time);
+    }
+
+    /// Replace the event at `index`. Returns an error if there is no event at `index` or if
+    /// `delta_time` is larger than `0x0FFFFFFF`.
     pub fn replace_event(
         &mut self,
         index: u32,
         delta_time: u32,
         event: Event,
     ) -> crate::Result<()> {
-        // TODO check length is not bigger than u32, index is in range, etc
-        // std::mem::replace(&mut , TrackEvent{delta_time, event})
-        self.events[index as usize] = TrackEvent::new(delta_time, event);
+        check_delta_time(delta_time)?;
+        let track_event = self
+            .events
+            .get_mut(index as usize)
+            .context(error::OtherSnafu { site: site!() })?;
+        *track_event = TrackEvent::new(delta_time, event);
         Ok(())
     }
 
@@ -120,9 +438,15 @@ impl Track {
 
     /// Add, or replace, the general midi program at the beginning of a track.
     pub fn set_general_midi(&mut self, channel: Channel, value: GeneralMidi) -> crate::Result<()> {
+        self.set_program(channel, Program::new(value.into()))
+    }
+
+    /// Add, or replace, the program change at the beginning of a track. A program change among the
+    /// events at tick zero is replaced, otherwise one is inserted as the first event.
+    pub fn set_program(&mut self, channel: Channel, program: Program) -> crate::Result<()> {
         let program_change = Event::Midi(Message::ProgramChange(ProgramChangeValue {
             channel,
-            program: Program::new(value.into()),
+            program,
         }));
         if self.is_empty() {
             self.push_event(0, program_change)?;
@@ -134,9 +458,9 @@ impl Track {
             }
             if let Event::Midi(Message::ProgramChange(prog)) = event.event() {
                 debug!(
-                    "changing program from '{}' to '{:?}'",
+                    "changing program from '{}' to '{}'",
                     prog.program.get(),
-                    value
+                    program.get()
                 );
                 self.replace_event(ix as u32, 0, program_change)?;
                 return Ok(());
@@ -159,18 +483,31 @@ impl Track {
         self.push_event(delta_time, event)
     }
 
+    /// Add a time signature with the `denominator` given as it is written, e.g. `8` for 6/8. Returns
+    /// an error if `denominator` is not a power of two.
+    pub fn push_time_signature_fraction(
+        &mut self,
+        delta_time: u32,
+        numerator: u8,
+        denominator: u8,
+        click: Clocks,
+    ) -> crate::Result<()> {
+        ensure!(
+            denominator.is_power_of_two(),
+            error::OtherSnafu { site: site!() }
+        );
+        let denominator = DurationName::from_u8(denominator.trailing_zeros() as u8)?;
+        self.push_time_signature(delta_time, numerator, denominator, click)
+    }
+
     /// Add a tempo message.
     pub fn push_tempo(
         &mut self,
         delta_time: u32,
         quarters_per_minute: QuartersPerMinute,
     ) -> crate::Result<()> {
-        // convert to microseconds per quarter note
-        let minutes_per_quarter = 1f64 / f64::from(quarters_per_minute.get());
-        let seconds_per_quarter = minutes_per_quarter * 60f64;
-        let microseconds_per_quarter = seconds_per_quarter * 1000000f64;
-        let value = microseconds_per_quarter as u32;
-        let event = Event::Meta(MetaEvent::SetTempo(MicrosecondsPerQuarter::new(value)));
+        let value = MicrosecondsPerQuarter::from(quarters_per_minute);
+        let event = Event::Meta(MetaEvent::SetTempo(value));
         self.push_event(delta_time, event)
     }
 
@@ -207,6 +544,70 @@ impl Track {
         self.push_event(delta_time, note_off)
     }
 
+    /// Add a chord: a note-on for each of `notes`, the first `delta_time` after the previous event
+    /// and the rest at the same tick, followed `duration` ticks later by a note-off for each of
+    /// them. The note-offs use the same `velocity` as the note-ons. Nothing is added if `notes` is
+    /// empty.
+    pub fn push_chord(
+        &mut self,
+        delta_time: u32,
+        duration: u32,
+        channel: Channel,
+        notes: &[NoteNumber],
+        velocity: Velocity,
+    ) -> crate::Result<()> {
+        if notes.is_empty() {
+            return Ok(());
+        }
+        // check both delta times first so that an error does not leave half of a chord
+        check_delta_time(delta_time)?;
+        check_delta_time(duration)?;
+        for (i, &note_number) in notes.iter().enumerate() {
+            let delta_time = if i == 0 { delta_time } else { 0 };
+            self.push_note_on(delta_time, channel, note_number, velocity)?;
+        }
+        for (i, &note_number) in notes.iter().enumerate() {
+            let delta_time = if i == 0 { duration } else { 0 };
+            self.push_note_off(delta_time, channel, note_number, velocity)?;
+        }
+        Ok(())
+    }
+
+    /// Add a polyphonic key pressure (aftertouch) message. The `pressure` is stored in the
+    /// `velocity` field of the [`NoteMessage`].
+    pub fn push_poly_pressure(
+        &mut self,
+        delta_time: u32,
+        channel: Channel,
+        note_number: NoteNumber,
+        pressure: Velocity,
+    ) -> crate::Result<()> {
+        let poly_pressure = Event::Midi(Message::PolyPressure(NoteMessage {
+            channel,
+            note_number,
+            velocity: pressure,
+        }));
+        self.push_event(delta_time, poly_pressure)
+    }
+
+    /// Add a drum hit on the General MIDI percussion channel (`Channel::new(9)`): a note-on with
+    /// `delta_time`, followed by a note-off `duration` ticks later.
+    pub fn push_drum(
+        &mut self,
+        delta_time: u32,
+        duration: u32,
+        drum: GmPercussion,
+        velocity: Velocity,
+    ) -> crate::Result<()> {
+        // check both delta times first so that an error does not leave a stuck note
+        check_delta_time(delta_time)?;
+        check_delta_time(duration)?;
+        let channel = Channel::new(PERCUSSION_CHANNEL);
+        self.push_note_on(delta_time, channel, drum.note_number(), velocity)?;
+        self.push_note_off(duration, channel, drum.note_number(), velocity)?;
+        Ok(())
+    }
+
     /// Add a lyric.
     pub fn push_lyric<S: Into<String>>(&mut self, delta_time: u32, lyric: S) -> crate::Result<()> {
         let lyric = Event::Meta(MetaEvent::Lyric(Text::new(lyric)));
@@ -228,46 +629,685 @@ impl Track {
         Ok(())
     }
 
-    pub(crate) fn parse<R: Read>(iter: &mut ByteIter<R>) -> LibResult<Self> {
-        iter.expect_tag("MTrk").context(io!())?;
-        let chunk_length = iter.read_u32().context(io!())?;
-        iter.set_size_limit(chunk_length as u64);
+    /// Set a Registered Parameter Number (RPN) by adding four control change messages: the
+    /// parameter MSB and LSB (`101` and `100`) followed by the value MSB and LSB (`6` and `38`).
+    /// Only the first message has `delta_time`, the others are at delta time zero. `parameter` and
+    /// `value` are 14-bit numbers, higher bits are ignored. For example, a pitch bend range of two
+    /// semitones is parameter `0` with a value of `2 << 7`.
+    pub fn push_rpn(
+        &mut self,
+        delta_time: u32,
+        channel: Channel,
+        parameter: u16,
+        value: u16,
+    ) -> crate::Result<()> {
+        self.push_parameter(
+            delta_time,
+            channel,
+            (
+                Control::RegisteredParameterNumberMsb,
+                Control::RegisteredParameterNumberLsb,
+            ),
+            parameter,
+            value,
+        )
+    }
+
+    /// Set a Non-Registered Parameter Number (NRPN). This is the same as [`Track::push_rpn`] except
+    /// that the parameter is sent with controls `99` and `98`.
+    pub fn push_nrpn(
+        &mut self,
+        delta_time: u32,
+        channel: Channel,
+        parameter: u16,
+        value: u16,
+    ) -> crate::Result<()> {
+        self.push_parameter(
+            delta_time,
+            channel,
+            (
+                Control::NonRegisteredParameterNumberMsb,
+                Control::NonRegisteredParameterNumberLsb,
+            ),
+            parameter,
+            value,
+        )
+    }
+
+    fn push_parameter(
+        &mut self,
+        delta_time: u32,
+        channel: Channel,
+        (parameter_msb, parameter_lsb): (Control, Control),
+        parameter: u16,
+        value: u16,
+    ) -> crate::Result<()> {
+        let messages = [
+            (parameter_msb, parameter >> 7),
+            (parameter_lsb, parameter),
+            (Control::DataEntryMsb, value >> 7),
+            (Control::DataEntryMsbLsb, value),
+        ];
+        for (ix, (control, value)) in messages.iter().enumerate() {
+            let event = Event::Midi(Message::Control(ControlChangeValue {
+                channel,
+                control: *control,
+                value: ControlValue::new((value & 0x7f) as u8),
+            }));
+            let delta_time = if ix == 0 { delta_time } else { 0 };
+            self.push_event(delta_time, event)?;
+        }
+        Ok(())
+    }
+
+    /// Removes every event for which `f` returns `false`. The delta time of each removed event is
+    /// added to the event that follows it, so the timing of the remaining events is unchanged. If
+    /// that would make a delta time larger than `0x0FFFFFFF`, the last removed event is kept
+    /// instead.
+    pub(crate) fn retain_events<F: FnMut(&Event) -> bool>(&mut self, mut f: F) {
+        let mut carried_delta = 0u64;
+        let mut last_removed: Option<TrackEvent> = None;
+        let mut retained = Vec::with_capacity(self.events.len());
+        for mut event in self.events.drain(..) {
+            if carried_delta + u64::from(event.delta_time) > u64::from(MAX_VLQ_VALUE) {
+                // carried_delta is only non-zero while there is a removed event to hold it
+                if let Some(mut removed) = last_removed.take() {
+                    removed.delta_time = clamp_vlq(carried_delta);
+                    retained.push(removed);
+                    carried_delta = 0;
+                }
+            }
+            if f(&event.event) {
+                event.delta_time = clamp_vlq(carried_delta + u64::from(event.delta_time));
+                carried_delta = 0;
+                last_removed = None;
+                retained.push(event);
+            } else {
+                carried_delta += u64::from(event.delta_time);
+                last_removed = Some(event);
+            }
+        }
+        self.events = retained;
+    }
+
+    /// Returns the index and absolute tick of every event for which `f` returns `true`.
+    pub fn find_events<F: Fn(&Event) -> bool>(&self, f: F) -> Vec<(usize, u64)> {
+        let mut tick = 0u64;
+        let mut found = Vec::new();
+        for (ix, event) in self.events.iter().enumerate() {
+            tick += u64::from(event.delta_time);
+            if f(&event.event) {
+                found.push((ix, tick));
+            }
+        }
+        found
+    }
+
+    /// Iterate over the distinct absolute ticks of the track, each with all of the events that occur
+    /// at that tick, in order.
+    pub fn grouped_by_tick(&self) -> impl Iterator<Item = (u64, Vec<&TrackEvent>)> + '_ {
+        let mut groups: Vec<(u64, Vec<&TrackEvent>)> = Vec::new();
+        let mut tick = 0u64;
+        for event in &self.events {
+            tick += u64::from(event.delta_time);
+            match groups.last_mut() {
+                Some((group_tick, group)) if *group_tick == tick => group.push(event),
+                _ => groups.push((tick, vec![event])),
+            }
+        }
+        groups.into_iter()
+    }
+
+    /// The distinct channels of all channel voice and channel mode messages in the track.
+    pub fn channels_used(&self) -> BTreeSet<Channel> {
+        self.events
+            .iter()
+            .filter_map(|event| match &event.event {
+                Event::Midi(message) => message.channel(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The channel that a `MidiChannelPrefix` associates with the event at `index`, such as an
+    /// instrument name or sysex event in a format 0 file. A prefix is effective until the next
+    /// channel message or the next prefix, so the events before `index` are searched backward for
+    /// one of these. Returns `None` if a channel message is found first, if there is no prefix, or
+    /// if `index` is out of range.
+    pub fn effective_channel_at(&self, index: usize) -> Option<Channel> {
+        if index >= self.events.len() {
+            return None;
+        }
+        self.events[..index]
+            .iter()
+            .rev()
+            .find_map(|event| match &event.event {
+                Event::Meta(MetaEvent::MidiChannelPrefix(channel)) => Some(Some(*channel)),
+                Event::Midi(message) if message.channel().is_some() => Some(None),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    /// Iterate over the values of one controller on one channel, along with the absolute tick of
+    /// each control change event.
+    pub fn controller_values(
+        &self,
+        channel: Channel,
+        control: Control,
+    ) -> impl Iterator<Item = (u64, ControlValue)> + '_ {
+        self.events
+            .iter()
+            .scan(0u64, |tick, event| {
+                *tick += u64::from(event.delta_time);
+                Some((*tick, &event.event))
+            })
+            .filter_map(move |(tick, event)| match event {
+                Event::Midi(Message::Control(value))
+                    if value.channel == channel && value.control == control =>
+                {
+                    Some((tick, value.value))
+                }
+                _ => None,
+            })
+    }
+
+    /// Combine the MSB and LSB control changes of the 14-bit controllers (`Control` values `0` to
+    /// `31`, paired with `32` to `63`) into `(absolute_tick, msb_control, value)` entries.
+    ///
+    /// - An MSB sets the high seven bits and resets the low seven bits to zero, so an MSB that is
+    ///   not followed by an LSB produces a value of `msb * 128`.
+    /// - An LSB that is the next control change on the same channel after its MSB is combined into
+    ///   the MSB's entry.
+    /// - Any other LSB produces a new entry at its own tick, combined with the most recent MSB of
+    ///   that controller on that channel (or zero if there was none).
+    pub fn resolve_14bit_controllers(&self) -> Vec<(u64, Control, u16)> {
+        let mut resolved: Vec<(u64, Control, u16)> = Vec::new();
+        let mut msb_values: HashMap<(Channel, Control), u8> = HashMap::new();
+        let mut pending_msb: HashMap<Channel, usize> = HashMap::new();
+        let mut tick = 0u64;
+        for event in &self.events {
+            tick += u64::from(event.delta_time);
+            let cc = match &event.event {
+                Event::Midi(Message::Control(cc)) => cc,
+                _ => continue,
+            };
+            let pending = pending_msb.remove(&cc.channel);
+            let number = cc.control as u8;
+            let value = cc.value.get();
+            if number < 32 {
+                msb_values.insert((cc.channel, cc.control), value);
+                pending_msb.insert(cc.channel, resolved.len());
+                resolved.push((tick, cc.control, u16::from(value) << 7));
+            } else if number < 64 {
+                let msb = match Control::try_from_u8(number - 32) {
+                    Ok(msb) => msb,
+                    Err(_) => continue,
+                };
+                match pending.and_then(|ix| resolved.get_mut(ix)) {
+                    Some(entry) if entry.1 == msb => entry.2 |= u16::from(value),
+                    _ => {
+                        let high = msb_values.get(&(cc.channel, msb)).copied().unwrap_or(0);
+                        resolved.push((tick, msb, u16::from(high) << 7 | u16::from(value)));
+                    }
+                }
+            }
+        }
+        resolved
+    }
+
+    /// Decode the Registered Parameter Number (RPN) changes in the track into
+    /// `(absolute_tick, parameter, value)` entries. A change is produced by each data entry MSB
+    /// (control `6`) that follows the selection of a parameter with controls `101` and `100`. A
+    /// data entry LSB (control `38`) that directly follows it is combined into the same entry. The
+    /// null parameter (`127`, `127`), and parameters selected with the NRPN controls, are ignored.
+    pub fn rpn_changes(&self) -> Vec<(u64, u16, u16)> {
+        self.parameter_changes(true)
+    }
+
+    /// Decode the Non-Registered Parameter Number (NRPN) changes in the track. This is the same as
+    /// [`Track::rpn_changes`] except that parameters are selected with controls `99` and `98`.
+    pub fn nrpn_changes(&self) -> Vec<(u64, u16, u16)> {
+        self.parameter_changes(false)
+    }
+
+    fn parameter_changes(&self, registered: bool) -> Vec<(u64, u16, u16)> {
+        // For each channel: whether the selected parameter is registered, and its MSB and LSB.
+        let mut selected: HashMap<Channel, (bool, u8, u8)> = HashMap::new();
+        let mut data_msb: HashMap<Channel, u8> = HashMap::new();
+        let mut pending: HashMap<Channel, usize> = HashMap::new();
+        let mut changes: Vec<(u64, u16, u16)> = Vec::new();
+        let mut tick = 0u64;
+        for event in &self.events {
+            tick += u64::from(event.delta_time);
+            let cc = match &event.event {
+                Event::Midi(Message::Control(cc)) => cc,
+                _ => continue,
+            };
+            let pending_ix = pending.remove(&cc.channel);
+            let value = cc.value.get();
+            let (is_registered, is_msb) = match cc.control {
+                Control::RegisteredParameterNumberMsb => (true, true),
+                Control::RegisteredParameterNumberLsb => (true, false),
+                Control::NonRegisteredParameterNumberMsb => (false, true),
+                Control::NonRegisteredParameterNumberLsb => (false, false),
+                Control::DataEntryMsb | Control::DataEntryMsbLsb => {
+                    let parameter = match selected.get(&cc.channel) {
+                        Some((r, 127, 127)) if *r == registered => continue,
+                        Some((r, msb, lsb)) if *r == registered => {
+                            u16::from(*msb) << 7 | u16::from(*lsb)
+                        }
+                        _ => continue,
+                    };
+                    if cc.control == Control::DataEntryMsb {
+                        data_msb.insert(cc.channel, value);
+                        pending.insert(cc.channel, changes.len());
+                        changes.push((tick, parameter, u16::from(value) << 7));
+                    } else {
+                        match pending_ix.and_then(|ix| changes.get_mut(ix)) {
+                            Some(entry) if entry.1 == parameter => entry.2 |= u16::from(value),
+                            _ => {
+                                let high = data_msb.get(&cc.channel).copied().unwrap_or(0);
+                                changes.push((
+                                    tick,
+                                    parameter,
+                                    u16::from(high) << 7 | u16::from(value),
+                                ));
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let entry = selected.entry(cc.channel).or_insert((is_registered, 0, 0));
+            if entry.0 != is_registered {
+                *entry = (is_registered, 0, 0);
+            }
+            if is_msb {
+                entry.1 = value;
+            } else {
+                entry.2 = value;
+            }
+        }
+        changes
+    }
+
+    /// Rewrite note-offs in one of the two forms allowed by the spec. When `to_explicit` is `true`,
+    /// every note-on with a velocity of zero becomes a note-off with a velocity of zero. When it is
+    /// `false`, every note-off becomes a note-on with a velocity of zero, which allows running
+    /// status to be used for more of the messages, but the note-off velocity is lost.
+    pub fn normalize_note_offs(&mut self, to_explicit: bool) {
+        for track_event in self.events.iter_mut() {
+            track_event.event = match &track_event.event {
+                Event::Midi(Message::NoteOn(m)) if to_explicit && m.velocity.get() == 0 => {
+                    Event::Midi(Message::NoteOff(*m))
+                }
+                Event::Midi(Message::NoteOff(m)) if !to_explicit => {
+                    Event::Midi(Message::NoteOn(NoteMessage {
+                        velocity: Velocity::new(0),
+                        ..*m
+                    }))
+                }
+                _ => continue,
+            };
+        }
+    }
+
+    /// Replace the velocity of every note-on with the result of `curve`, which is given the current
+    /// velocity, see [`crate::core::VelocityCurve`] for some common curves. The result is clamped
+    /// to `1..=127`, so that a note-on cannot become a note-off. Note-ons with a velocity of zero,
+    /// which are note-offs, are not changed.
+    pub fn remap_velocity_curve<F: Fn(u8) -> u8>(&mut self, curve: F) {
+        for track_event in self.events.iter_mut() {
+            if let Event::Midi(Message::NoteOn(m)) = &mut track_event.event {
+                if m.velocity.get() > 0 {
+                    m.velocity = Velocity::new(curve(m.velocity.get()).max(1));
+                }
+            }
+        }
+    }
+
+    /// Add a note-off, with a velocity of `64`, immediately before each note-on that re-triggers a
+    /// note that is already sounding on the same channel, so that every note is turned off before it
+    /// is struck again. The note-off takes the delta time of the note-on, which then gets a delta
+    /// time of zero, so the absolute tick of every other event is unchanged. The existing note-offs
+    /// are not changed, so the one that ended the first note will now end the second.
+    pub fn fix_retriggers(&mut self) {
+        let mut sounding: HashMap<(u8, u8), usize> = HashMap::new();
+        let mut events = Vec::with_capacity(self.events.len());
+        for mut track_event in std::mem::take(&mut self.events) {
+            match &track_event.event {
+                Event::Midi(Message::NoteOn(m)) if m.velocity.get() > 0 => {
+                    let count = sounding
+                        .entry((m.channel.get(), m.note_number.get()))
+                        .or_default();
+                    if *count > 0 {
+                        let note_off =
+                            NoteMessage::new(m.channel, m.note_number, Velocity::new(64));
+                        events.push(TrackEvent::new(
+                            track_event.delta_time,
+                            Event::Midi(Message::NoteOff(note_off)),
+                        ));
+                        track_event.delta_time = 0;
+                    } else {
+                        *count = 1;
+                    }
+                }
+                Event::Midi(Message::NoteOn(m)) | Event::Midi(Message::NoteOff(m)) => {
+                    if let Some(count) = sounding.get_mut(&(m.channel.get(), m.note_number.get())) {
+                        *count = count.saturating_sub(1);
+                    }
+                }
+                _ => {}
+            }
+            events.push(track_event);
+        }
+        self.events = events;
+    }
+
+    /// Insert a MIDI panic at `at_tick`, i.e. `AllSoundsOff`, `AllNotesOff` and
+    /// `ResetAllControllers`, in that order, on every channel used by the track, see
+    /// [`Track::channels_used`]. This is what a sequencer sends when playback stops, to silence any
+    /// stuck notes. The messages are inserted with [`Track::insert_event_at_tick`], so the absolute
+    /// tick of every other event is unchanged. Nothing is inserted if the track has no channel
+    /// messages.
+    pub fn panic(&mut self, at_tick: u64) -> crate::Result<()> {
+        for channel in self.channels_used() {
+            for message in [
+                Message::AllSoundsOff(channel),
+                Message::AllNotesOff(channel),
+                Message::ResetAllControllers(channel),
+            ] {
+                self.insert_event_at_tick(at_tick, Event::Midi(message))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the delta time and event of every event with the result of `f`, which is given the
+    /// current delta time and event. For example, this can transpose the notes and change the
+    /// timing of a track in one pass. `f` is called for an `EndOfTrack` event too, so that its
+    /// delta time can be changed, but the event that it returns is ignored so that the track stays
+    /// terminated. Delta times larger than `0x0FFFFFFF` are clamped to `0x0FFFFFFF`.
+    pub fn map_events<F: FnMut(u32, &Event) -> (u32, Event)>(&mut self, mut f: F) {
+        for track_event in self.events.iter_mut() {
+            let (delta_time, event) = f(track_event.delta_time, &track_event.event);
+            track_event.delta_time = clamp_vlq(u64::from(delta_time));
+            if !track_event.is_end() {
+                track_event.event = event;
+            }
+        }
+    }
+
+    /// Reorder the events that share an absolute tick into a canonical order. Events at different
+    /// ticks keep their order, and within a tick, events of the same priority keep their order.
+    /// The priorities are:
+    ///
+    /// 1. Meta events (other than `EndOfTrack`) and sysex events.
+    /// 2. Other channel and system messages, such as program, control and pitch bend changes.
+    /// 3. Note-offs, including note-ons with a velocity of zero.
+    /// 4. Note-ons.
+    /// 5. Polyphonic and channel pressure, which apply to the notes that are sounding.
+    /// 6. `EndOfTrack`.
+    pub fn stable_sort_simultaneous(&mut self) {
+        fn priority(event: &Event) -> u8 {
+            match event {
+                Event::Meta(MetaEvent::EndOfTrack) => 5,
+                Event::Meta(_) | Event::Sysex(_) => 0,
+                Event::Midi(Message::NoteOn(m)) if m.velocity.get() > 0 => 3,
+                Event::Midi(Message::NoteOn(_)) | Event::Midi(Message::NoteOff(_)) => 2,
+                Event::Midi(Message::PolyPressure(_))
+                | Event::Midi(Message::ChannelPressure(_)) => 4,
+                Event::Midi(_) => 1,
+            }
+        }
+        let mut tick = 0u64;
+        let mut timed: Vec<(u64, TrackEvent)> = self
+            .events
+            .drain(..)
+            .map(|event| {
+                tick += u64::from(event.delta_time);
+                (tick, event)
+            })
+            .collect();
+        timed.sort_by_key(|(tick, event)| (*tick, priority(&event.event)));
+        let mut previous = 0u64;
+        for (tick, mut event) in timed {
+            // ticks only grow, and each gap is the delta time of an original event
+            event.delta_time = (tick - previous) as u32;
+            previous = tick;
+            self.events.push(event);
+        }
+    }
+
+    /// For each note that has both a note-on and a matching note-off, swap the two velocities so
+    /// that the attack velocity becomes the release velocity and vice versa. A note-on cannot have
+    /// a velocity of zero (that would be a note-off), so a release velocity of zero becomes an
+    /// attack velocity of one. Note-offs that were written as a velocity-zero note-on are rewritten
+    /// as true note-off messages so that they can carry the new velocity.
+    pub fn swap_note_velocities(&mut self) {
+        for (on_ix, off_ix) in self.note_pairs() {
+            let off_ix = match off_ix {
+                Some(ix) => ix,
+                None => continue,
+            };
+            let (on, off) = match (
+                note_message(&self.events[on_ix].event),
+                note_message(&self.events[off_ix].event),
+            ) {
+                (Some(on), Some(off)) => (on, off),
+                _ => continue,
+            };
+            let attack = on.velocity;
+            let release = off.velocity.get().max(1);
+            self.events[on_ix].event = Event::Midi(Message::NoteOn(NoteMessage {
+                velocity: Velocity::new(release),
+                ..on
+            }));
+            self.events[off_ix].event = Event::Midi(Message::NoteOff(NoteMessage {
+                velocity: attack,
+                ..off
+            }));
+        }
+    }
+
+    /// Add an event at the time of the `EndOfTrack` event, just before it. If the track does not
+    /// end with an `EndOfTrack` event, the event is added to the end with a delta time of zero.
+    pub(crate) fn push_before_end_of_track(&mut self, event: Event) {
+        match self.events.last_mut() {
+            Some(last) if last.is_end() => {
+                let delta_time = std::mem::replace(&mut last.delta_time, 0);
+                let index = self.events.len() - 1;
+                self.events
+                    .insert(index, TrackEvent::new(delta_time, event));
+            }
+            _ => self.events.push(TrackEvent::new(0, event)),
+        }
+    }
+
+    /// Pairs each note-on with the note-off that ends it. Notes are matched first-in-first-out by
+    /// channel and note number, and a note-on with a velocity of zero is treated as a note-off.
+    /// Returns `(note_on_index, note_off_index)` tuples ordered by the note-on index. The note-off
+    /// index is `None` if the note is never turned off.
+    pub(crate) fn note_pairs(&self) -> Vec<(usize, Option<usize>)> {
+        let mut sounding: HashMap<(u8, u8), VecDeque<usize>> = HashMap::new();
+        let mut pairs = Vec::new();
+        for (ix, track_event) in self.events.iter().enumerate() {
+            match &track_event.event {
+                Event::Midi(Message::NoteOn(m)) if m.velocity.get() > 0 => {
+                    sounding
+                        .entry((m.channel.get(), m.note_number.get()))
+                        .or_default()
+                        .push_back(pairs.len());
+                    pairs.push((ix, None));
+                }
+                Event::Midi(Message::NoteOn(m)) | Event::Midi(Message::NoteOff(m)) => {
+                    if let Some(pair_ix) = sounding
+                        .get_mut(&(m.channel.get(), m.note_number.get()))
+                        .and_then(|queue| queue.pop_front())
+                    {
+                        pairs[pair_ix].1 = Some(ix);
+                    }
+                }
+                _ => {}
+            }
+        }
+        pairs
+    }
+
+    pub(crate) fn parse<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Self> {
+        if Self::parse_chunk_header(iter)? {
+            return Ok(Self {
+                events: vec![TrackEvent::new(0, Event::Meta(MetaEvent::EndOfTrack))],
+            });
+        }
         let mut events = Vec::new();
         loop {
-            if iter.is_end() {
-                invalid_file!("end of track bytes reached before EndOfTrack event.");
-            }
-            let event = TrackEvent::parse(iter)?;
-            trace!("parsed {:?}", event);
+            let event = Self::parse_event(iter)?;
             let is_track_end = event.is_end();
             events.push(event);
             if is_track_end {
-                debug!("end of track event");
-                if !iter.is_end() {
-                    invalid_file!("EndOfTrack event before end of track bytes.");
-                }
                 break;
             }
         }
-        iter.clear_size_limit();
         Ok(Self { events })
     }
 
+    /// Reads chunk tags until an `MTrk` tag is found. Chunks with any other tag, made of ASCII
+    /// letters and digits, are skipped using their length, since the spec says that readers must
+    /// ignore chunk types that they do not recognize.
+    pub(crate) fn parse_chunk_tag<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<()> {
+        loop {
+            let tag = iter.read4().context(io!())?;
+            if &tag == b"MTrk" {
+                return Ok(());
+            }
+            if !tag.iter().all(u8::is_ascii_alphanumeric) {
+                invalid_file!(
+                    "expected a track chunk but found the bytes {:02X?}, near byte {}",
+                    tag,
+                    iter.position().unwrap_or(0)
+                );
+            }
+            let length = iter.read_u32().context(io!())?;
+            debug!(
+                "skipping unknown chunk '{}' of {} bytes",
+                String::from_utf8_lossy(&tag),
+                length
+            );
+            iter.skip(u64::from(length)).context(io!())?;
+        }
+    }
+
+    /// Reads the `MTrk` tag, skipping any unknown chunks before it, and the chunk length, then
+    /// limits the `iter` to the length of the chunk.
+    /// A chunk with a length of zero is an error unless the iterator is set to allow empty tracks,
+    /// in which case `true` is returned and the caller should use an `EndOfTrack` in place of the
+    /// missing events. A length that is greater than the bytes left in the data is an error unless
+    /// the iterator is set to clamp it.
+    pub(crate) fn parse_chunk_header<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<bool> {
+        Self::parse_chunk_tag(iter)?;
+        let mut chunk_length = iter.read_u32().context(io!())?;
+        if let Some(available) = iter.available() {
+            if u64::from(chunk_length) > available {
+                ensure!(
+                    iter.settings().clamp_track_lengths,
+                    error::TruncatedTrackChunkSnafu {
+                        site: site!(),
+                        declared: chunk_length,
+                        available,
+                        position: iter.position().unwrap_or(0) + available,
+                    }
+                );
+                warn!(
+                    "the track chunk declares a length of {} bytes, clamping to the {} available",
+                    chunk_length, available
+                );
+                // available is less than a u32 value here
+                chunk_length = available as u32;
+            }
+        }
+        if chunk_length == 0 {
+            if !iter.settings().allow_empty_tracks {
+                invalid_file!(
+                    "empty track chunk, expected at least an EndOfTrack event, near byte {}",
+                    iter.position().unwrap_or(0)
+                );
+            }
+            warn!("empty track chunk, adding an EndOfTrack event");
+            return Ok(true);
+        }
+        iter.set_size_limit(chunk_length as u64);
+        Ok(false)
+    }
+
+    /// Reads the next event of a track chunk. When the event is an [`MetaEvent::EndOfTrack`], the
+    /// size limit set by [`Track::parse_chunk_header`] is cleared. If the chunk ends without an
+    /// `EndOfTrack` and the iterator is set to repair this, an `EndOfTrack` is returned. When the
+    /// length of the data is not known up front, running out of data before the end of the chunk
+    /// is detected here.
+    pub(crate) fn parse_event<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<TrackEvent> {
+        if !iter.is_end() && iter.peek().is_none() {
+            check_truncated_chunk(iter)?;
+            iter.clear_size_limit();
+            return Ok(TrackEvent::new(0, Event::Meta(MetaEvent::EndOfTrack)));
+        }
+        if iter.is_end() {
+            let settings = iter.settings();
+            if settings.repair_missing_end_of_track || settings.clamp_track_lengths {
+                warn!("end of track bytes reached before EndOfTrack event, adding one");
+                iter.clear_size_limit();
+                return Ok(TrackEvent::new(0, Event::Meta(MetaEvent::EndOfTrack)));
+            }
+            invalid_file!("end of track bytes reached before EndOfTrack event.");
+        }
+        let event = TrackEvent::parse(iter)?;
+        trace!("parsed {:?}", event);
+        if event.is_end() {
+            debug!("end of track event");
+            if !iter.is_end() {
+                if iter.peek().is_some() {
+                    invalid_file!("EndOfTrack event before end of track bytes.");
+                }
+                check_truncated_chunk(iter)?;
+            }
+            iter.clear_size_limit();
+        }
+        Ok(event)
+    }
+
     pub(crate) fn write<W: Write>(&self, w: &mut Scribe<W>) -> LibResult<()> {
         // write the track chunk header
         w.write_all(b"MTrk").context(wr!())?;
 
         // we need to write out all of the data first so we know its length
         let mut track_data: Vec<u8> = Vec::new();
-        let mut track_scribe = Scribe::new(
-            &mut track_data,
-            ScribeSettings {
-                running_status: w.use_running_status(),
-            },
-        );
+        let mut track_scribe = Scribe::new(&mut track_data, w.settings());
+        // the track may have been mutated, so make sure that there is exactly one EndOfTrack and
+        // that it is last. the delta time of a misplaced EndOfTrack is carried to the next event.
+        let mut carried_delta = 0u32;
         for event in self.events() {
-            event.write(&mut track_scribe)?;
+            if event.is_end() {
+                carried_delta = carried_delta.saturating_add(event.delta_time());
+                continue;
+            }
+            if carried_delta == 0 {
+                event.write(&mut track_scribe)?;
+            } else {
+                let delta_time = carried_delta.saturating_add(event.delta_time());
+                TrackEvent::new(delta_time, event.event().clone()).write(&mut track_scribe)?;
+                carried_delta = 0;
+            }
+        }
+        let mut end_of_track = Vec::new();
+        TrackEvent::new(carried_delta, Event::Meta(MetaEvent::EndOfTrack))
+            .write(&mut Scribe::new(&mut end_of_track, w.settings()))?;
+        let unpadded_length = track_data.len() + end_of_track.len();
+        if let Some(padding) = padding_event(unpadded_length, w.track_alignment()) {
+            padding.write(&mut Scribe::new(&mut track_data, w.settings()))?;
         }
+        track_data.extend_from_slice(&end_of_track);
 
         // write the length of the track
         let track_length = u32::try_from(track_data.len())
@@ -280,8 +1320,128 @@ impl Track {
     }
 }
 
+/// Called when the data has ended before the end of the current track chunk. This is an error unless
+/// the iterator is set to clamp track lengths.
+fn check_truncated_chunk<S: ByteSource>(iter: &ByteIter<S>) -> LibResult<()> {
+    let declared = iter.size_limit().unwrap_or(0);
+    ensure!(
+        iter.settings().clamp_track_lengths,
+        error::TruncatedTrackChunkSnafu {
+            site: site!(),
+            declared,
+            available: declared - iter.remaining().unwrap_or(0),
+            position: iter.position().unwrap_or(0),
+        }
+    );
+    warn!("the data ended before the declared end of the track chunk, clamping");
+    Ok(())
+}
+
+/// Delta times are written as variable length quantities, which the spec limits to four bytes.
+fn check_delta_time(delta_time: u32) -> LibResult<()> {
+    ensure!(
+        delta_time <= MAX_VLQ_VALUE,
+        error::DeltaTimeTooBigSnafu {
+            site: site!(),
+            delta_time
+        }
+    );
+    Ok(())
+}
+
+/// The `OtherText` event that pads the data of a track chunk, including its `EndOfTrack`, from
+/// `length` bytes to a multiple of `alignment` bytes, or `None` if no padding is needed. The
+/// smallest text event is four bytes (`00 FF 01 00`), and each space added to the text adds a byte.
+fn padding_event(length: usize, alignment: u32) -> Option<TrackEvent> {
+    let alignment = alignment as usize;
+    if alignment <= 1 || length.is_multiple_of(alignment) {
+        return None;
+    }
+    let event = |spaces: usize| {
+        let text = Text::new(" ".repeat(spaces));
+        TrackEvent::new(0, Event::Meta(MetaEvent::OtherText(text)))
+    };
+    // the length of the text is written as a variable length quantity, so count its bytes too
+    let size = |spaces: usize| 3 + Vlq::new(spaces as u32).to_bytes().len() + spaces;
+    (0..)
+        .find(|spaces| (length + size(*spaces)).is_multiple_of(alignment))
+        .map(event)
+}
+
+impl Index<usize> for Track {
+    type Output = TrackEvent;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.events[index]
+    }
+}
+
+impl IndexMut<usize> for Track {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.events[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a Track {
+    type Item = &'a TrackEvent;
+    type IntoIter = std::slice::Iter<'a, TrackEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.iter()
+    }
+}
+
+impl IntoIterator for Track {
+    type Item = TrackEvent;
+    type IntoIter = std::vec::IntoIter<TrackEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.into_iter()
+    }
+}
+
+/// Collects events into a `Track`. No [`MetaEvent::EndOfTrack`] is added here, that happens when
+/// the track is added to a file with [`crate::MidiFile::push_track`].
+impl FromIterator<TrackEvent> for Track {
+    fn from_iter<I: IntoIterator<Item = TrackEvent>>(iter: I) -> Self {
+        Self {
+            events: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<TrackEvent> for Track {
+    fn extend<I: IntoIterator<Item = TrackEvent>>(&mut self, iter: I) {
+        self.events.extend(iter)
+    }
+}
+
+/// Returns the `NoteMessage` if the event is a note-on or note-off.
+fn note_message(event: &Event) -> Option<NoteMessage> {
+    match event {
+        Event::Midi(Message::NoteOn(m)) | Event::Midi(Message::NoteOff(m)) => Some(*m),
+        _ => None,
+    }
+}
+
+/// If the track has no tempo event at tick zero, then add one to the front.
+pub(crate) fn ensure_tempo(mut track: Track, tempo: QuartersPerMinute) -> LibResult<Track> {
+    let has_tempo = track
+        .events
+        .iter()
+        .take_while(|event| event.delta_time() == 0)
+        .any(|event| matches!(event.event(), Event::Meta(MetaEvent::SetTempo(_))));
+    if !has_tempo {
+        let value = MicrosecondsPerQuarter::from(tempo);
+        track.insert_event(0, 0, Event::Meta(MetaEvent::SetTempo(value)))?;
+    }
+    Ok(track)
+}
+
 /// If the last item of the track is *not* an end-of-track event, then add it to the back. If
-/// the track already has an end-of-track event as its last event, then nothing happens.
+/// the track already has an end-of-track event as its last event, then nothing happens. An
+/// end-of-track event that is not the last event is left where it is rather than moved, so the
+/// track then has two of them.
 pub(crate) fn ensure_end_of_track(mut track: Track) -> LibResult<Track> {
     if let Some(last_event) = track.events.last() {
         if !matches!(last_event.event(), Event::Meta(MetaEvent::EndOfTrack)) {
@@ -292,3 +1452,848 @@ pub(crate) fn ensure_end_of_track(mut track: Track) -> LibResult<Track> {
     }
     Ok(track)
 }
+
+#[test]
+fn into_iter_test() {
+    let mut track = Track::default();
+    track.push_lyric(0, "a").unwrap();
+    track
+        .push_note_on(0, Channel::new(0), NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    track
+        .push_note_off(100, Channel::new(0), NoteNumber::new(60), Velocity::new(0))
+        .unwrap();
+
+    let mut deltas = Vec::new();
+    for event in &track {
+        deltas.push(event.delta_time());
+    }
+    assert_eq!(vec![0, 0, 100], deltas);
+
+    let owned: Vec<TrackEvent> = track.clone().into_iter().collect();
+    assert_eq!(3, owned.len());
+    assert!(matches!(
+        owned.last().unwrap().event(),
+        Event::Midi(Message::NoteOff(_))
+    ));
+}
+
+#[test]
+fn swap_note_velocities_test() {
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    track
+        .push_note_on(0, ch, NoteNumber::new(60), Velocity::new(100))
+        .unwrap();
+    track
+        .push_note_on(0, ch, NoteNumber::new(64), Velocity::new(90))
+        .unwrap();
+    track
+        .push_note_off(10, ch, NoteNumber::new(60), Velocity::new(30))
+        .unwrap();
+    // a note-on with velocity zero is a note-off
+    track
+        .push_note_on(10, ch, NoteNumber::new(64), Velocity::new(0))
+        .unwrap();
+    // this note is never turned off and should be left alone
+    track
+        .push_note_on(0, ch, NoteNumber::new(67), Velocity::new(80))
+        .unwrap();
+    track.swap_note_velocities();
+
+    let velocities: Vec<(bool, u8)> = track
+        .events()
+        .map(|e| match e.event() {
+            Event::Midi(Message::NoteOn(m)) => (true, m.velocity().get()),
+            Event::Midi(Message::NoteOff(m)) => (false, m.velocity().get()),
+            _ => panic!("unexpected event {:?}", e),
+        })
+        .collect();
+    assert_eq!(
+        vec![(true, 30), (true, 1), (false, 100), (false, 90), (true, 80)],
+        velocities
+    );
+}
+
+#[test]
+fn from_iter_and_extend_test() {
+    let events = vec![
+        TrackEvent::new(0, Event::Meta(MetaEvent::TrackName(Text::new("x")))),
+        TrackEvent::new(0, Event::Meta(MetaEvent::Lyric(Text::new("y")))),
+    ];
+    let mut track: Track = events.into_iter().collect();
+    assert_eq!(2, track.events_len());
+    track.extend(vec![TrackEvent::new(5, Event::Meta(MetaEvent::EndOfTrack))]);
+    assert_eq!(3, track.events_len());
+    let mut mfile = crate::MidiFile::new();
+    mfile.push_track(track).unwrap();
+    // the track already ends with an EndOfTrack so push_track does not add another
+    assert_eq!(3, mfile.track(0).unwrap().events_len());
+}
+
+#[test]
+fn find_events_test() {
+    // the first measures of examples/main.rs
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    track.set_name("Singer").unwrap();
+    for (lyric, note, duration) in [("Row", 72, 1536), ("row", 72, 1536), ("row", 72, 1024)] {
+        track.push_lyric(0, lyric).unwrap();
+        track
+            .push_note_on(0, ch, NoteNumber::new(note), Velocity::new(64))
+            .unwrap();
+        track
+            .push_note_off(duration, ch, NoteNumber::new(note), Velocity::new(64))
+            .unwrap();
+    }
+    let lyrics = track.find_events(|e| matches!(e, Event::Meta(MetaEvent::Lyric(_))));
+    assert_eq!(lyrics, vec![(1, 0), (4, 1536), (7, 3072)]);
+    assert!(track
+        .find_events(|e| matches!(e, Event::Meta(MetaEvent::Marker(_))))
+        .is_empty());
+}
+
+#[test]
+fn insert_event_at_tick_test() {
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    track.push_lyric(0, "a").unwrap();
+    track
+        .push_note_on(100, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    track
+        .push_note_off(100, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    let track = ensure_end_of_track(track).unwrap();
+    let ticks = |track: &Track| -> Vec<u64> {
+        let mut tick = 0;
+        track
+            .events()
+            .map(|e| {
+                tick += u64::from(e.delta_time());
+                tick
+            })
+            .collect()
+    };
+
+    let mut between = track.clone();
+    let note_on = Event::Midi(Message::NoteOn(NoteMessage::new(
+        ch,
+        NoteNumber::new(64),
+        Velocity::new(64),
+    )));
+    between.insert_event_at_tick(150, note_on.clone()).unwrap();
+    assert_eq!(ticks(&between), vec![0, 100, 150, 200, 200]);
+    assert_eq!(between[2].event(), &note_on);
+
+    // stable: after the existing event at the same tick
+    let mut same = track.clone();
+    same.insert_event_at_tick(100, note_on.clone()).unwrap();
+    assert_eq!(ticks(&same), vec![0, 100, 100, 200, 200]);
+    assert_eq!(same[2].event(), &note_on);
+
+    // past the end: EndOfTrack stays last and moves later
+    let mut after = track;
+    after.insert_event_at_tick(300, note_on.clone()).unwrap();
+    assert_eq!(ticks(&after), vec![0, 100, 200, 300, 300]);
+    assert_eq!(after[3].event(), &note_on);
+    assert!(after[4].is_end());
+}
+
+#[test]
+fn resolve_14bit_controllers_test() {
+    let cc = |channel: u8, control: Control, value: u8| {
+        Event::Midi(Message::Control(ControlChangeValue {
+            channel: Channel::new(channel),
+            control,
+            value: ControlValue::new(value),
+        }))
+    };
+    let mut track = Track::default();
+    track.push_event(0, cc(0, Control::BankSelect, 1)).unwrap();
+    track
+        .push_event(0, cc(0, Control::BankSelectLsb, 32))
+        .unwrap();
+    track.push_event(10, cc(1, Control::ModWheel, 2)).unwrap();
+    track
+        .push_event(0, cc(1, Control::ChannelVolume, 100))
+        .unwrap();
+    track.push_event(5, cc(1, Control::ModWheelLsb, 3)).unwrap();
+    let resolved = track.resolve_14bit_controllers();
+    assert_eq!(
+        resolved,
+        vec![
+            (0, Control::BankSelect, 128 + 32),
+            (10, Control::ModWheel, 2 * 128),
+            (10, Control::ChannelVolume, 100 * 128),
+            (15, Control::ModWheel, 2 * 128 + 3),
+        ]
+    );
+}
+
+#[test]
+fn rpn_test() {
+    let ch = Channel::new(2);
+    let mut track = Track::default();
+    // pitch bend range of two semitones
+    track.push_rpn(10, ch, 0, 2 << 7).unwrap();
+    track.push_nrpn(5, ch, 300, 1000).unwrap();
+    assert_eq!(track.events_len(), 8);
+    assert_eq!(track.events().nth(1).unwrap().delta_time(), 0);
+    assert_eq!(track.rpn_changes(), vec![(10, 0, 2 << 7)]);
+    assert_eq!(track.nrpn_changes(), vec![(15, 300, 1000)]);
+}
+
+#[test]
+fn stable_sort_simultaneous_test() {
+    let ch = Channel::new(0);
+    let note = |on: bool, number: u8, velocity: u8| {
+        let m = NoteMessage::new(ch, NoteNumber::new(number), Velocity::new(velocity));
+        Event::Midi(if on {
+            Message::NoteOn(m)
+        } else {
+            Message::NoteOff(m)
+        })
+    };
+    let program = Event::Midi(Message::ProgramChange(ProgramChangeValue {
+        channel: ch,
+        program: Program::new(1),
+    }));
+    let marker = Event::Meta(MetaEvent::Marker(Text::new("B")));
+    let mut track = Track::default();
+    track.push_event(0, note(true, 60, 64)).unwrap();
+    track.push_event(0, program.clone()).unwrap();
+    track.push_event(96, note(true, 62, 64)).unwrap();
+    track
+        .push_event(0, Event::Meta(MetaEvent::EndOfTrack))
+        .unwrap();
+    track.push_event(0, note(true, 60, 0)).unwrap();
+    track.push_event(0, marker.clone()).unwrap();
+    track.push_event(0, note(false, 61, 0)).unwrap();
+    track.push_event(10, note(false, 62, 0)).unwrap();
+    track.stable_sort_simultaneous();
+    let sorted: Vec<_> = track
+        .events()
+        .map(|e| (e.delta_time(), e.event().clone()))
+        .collect();
+    assert_eq!(
+        sorted,
+        vec![
+            (0, program),
+            (0, note(true, 60, 64)),
+            (96, marker),
+            (0, note(true, 60, 0)),
+            (0, note(false, 61, 0)),
+            (0, note(true, 62, 64)),
+            (0, Event::Meta(MetaEvent::EndOfTrack)),
+            (10, note(false, 62, 0)),
+        ]
+    );
+}
+
+#[test]
+fn notes_sounding_at_test() {
+    // the first measure of examples/main.rs, with a harmony note added under the first note
+    let ch = Channel::new(0);
+    let c5 = NoteNumber::new(72);
+    let f4 = NoteNumber::new(65);
+    let mut track = Track::default();
+    track.push_lyric(0, "Row").unwrap();
+    track.push_note_on(0, ch, c5, Velocity::new(64)).unwrap();
+    track.push_note_on(0, ch, f4, Velocity::new(64)).unwrap();
+    track.push_note_off(768, ch, f4, Velocity::new(0)).unwrap();
+    track.push_note_off(768, ch, c5, Velocity::new(64)).unwrap();
+    track.push_lyric(0, "row").unwrap();
+    track.push_note_on(0, ch, c5, Velocity::new(64)).unwrap();
+    track.push_note_on(1536, ch, c5, Velocity::new(0)).unwrap();
+    let track = ensure_end_of_track(track).unwrap();
+    assert_eq!(track.duration_ticks(), 3072);
+    assert_eq!(track.notes_sounding_at(0), vec![(ch, c5), (ch, f4)]);
+    assert_eq!(track.notes_sounding_at(767).len(), 2);
+    assert_eq!(track.notes_sounding_at(768), vec![(ch, c5)]);
+    assert_eq!(track.notes_sounding_at(1536), vec![(ch, c5)]);
+    assert!(track.notes_sounding_at(3072).is_empty());
+}
+
+#[test]
+fn shift_test() {
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    track
+        .push_note_on(96, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    track
+        .push_note_off(480, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    let deltas = |track: &Track| -> Vec<u32> { track.events().map(|e| e.delta_time()).collect() };
+    track.shift(480).unwrap();
+    assert_eq!(deltas(&track), vec![576, 480]);
+    track.shift(-576).unwrap();
+    assert_eq!(deltas(&track), vec![0, 480]);
+    assert!(track.shift(-1).is_err());
+    assert_eq!(deltas(&track), vec![0, 480]);
+    assert!(track.shift(i64::from(u32::MAX) + 1).is_err());
+    assert!(Track::default().shift(-10).is_ok());
+}
+
+#[test]
+fn retain_events_delta_limit_test() {
+    let lyric = || Event::Meta(MetaEvent::Lyric(Text::new("la")));
+    let is_lyric = |event: &Event| matches!(event, Event::Meta(MetaEvent::Lyric(_)));
+    let mut track = Track::default();
+    track.push_lyric(MAX_VLQ_VALUE, "la").unwrap();
+    track
+        .push_tempo(MAX_VLQ_VALUE, QuartersPerMinute::new(120))
+        .unwrap();
+    track.push_event(10, lyric()).unwrap();
+    track.push_event(20, lyric()).unwrap();
+    track
+        .push_event(0, Event::Meta(MetaEvent::EndOfTrack))
+        .unwrap();
+    let ticks = track.duration_ticks();
+
+    // the first lyric must stay, otherwise the tempo would be 0x1FFFFFFE ticks after the start
+    track.retain_events(|event| !is_lyric(event));
+    assert_eq!(track.duration_ticks(), ticks);
+    assert!(track.events().all(|e| e.delta_time() <= MAX_VLQ_VALUE));
+    let deltas: Vec<u32> = track.events().map(TrackEvent::delta_time).collect();
+    assert_eq!(deltas, vec![MAX_VLQ_VALUE, MAX_VLQ_VALUE, 30]);
+    assert!(is_lyric(track[0].event()));
+    assert!(track[2].is_end());
+}
+
+#[test]
+fn push_drum_test() {
+    let mut track = Track::default();
+    track
+        .push_drum(0, 120, GmPercussion::AcousticSnare, Velocity::new(100))
+        .unwrap();
+    assert_eq!(track.events_len(), 2);
+    let on = note_message(track.events[0].event()).unwrap();
+    assert!(matches!(
+        track.events[0].event(),
+        Event::Midi(Message::NoteOn(_))
+    ));
+    assert_eq!(on.channel().get(), 9);
+    assert_eq!(on.note_number().get(), 38);
+    assert!(matches!(
+        track.events[1].event(),
+        Event::Midi(Message::NoteOff(_))
+    ));
+    assert_eq!(track.events[1].delta_time(), 120);
+
+    // a failed drum hit adds nothing
+    assert!(track
+        .push_drum(
+            0,
+            0x1000_0000,
+            GmPercussion::AcousticSnare,
+            Velocity::new(100)
+        )
+        .is_err());
+    assert_eq!(track.events_len(), 2);
+}
+
+#[test]
+fn from_absolute_events_test() {
+    let ch = Channel::new(0);
+    let note = |on: bool, number: u8| {
+        let m = NoteMessage::new(ch, NoteNumber::new(number), Velocity::new(64));
+        Event::Midi(if on {
+            Message::NoteOn(m)
+        } else {
+            Message::NoteOff(m)
+        })
+    };
+    // a C major scale of quarter notes, given with the note-offs first
+    let scale = [60u8, 62, 64, 65, 67, 69, 71, 72];
+    let offs = scale
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| ((i as u64 + 1) * 96, note(false, n)));
+    let ons = scale
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| (i as u64 * 96, note(true, n)));
+    let track = Track::from_absolute_events(offs.chain(ons)).unwrap();
+    assert_eq!(track.events_len(), 17);
+    assert!(track.events[16].is_end());
+    assert_eq!(track.events[16].delta_time(), 0);
+    assert_eq!(track.events[0].event(), &note(true, 60));
+    for pair in track.events[1..15].chunks(2) {
+        // each note-off from the previous note is followed by the next note-on
+        assert_eq!(pair[0].delta_time(), 96);
+        assert!(matches!(pair[0].event(), Event::Midi(Message::NoteOff(_))));
+        assert_eq!(pair[1].delta_time(), 0);
+    }
+    assert_eq!(track.events[15].event(), &note(false, 72));
+    assert_eq!(track.events[15].delta_time(), 96);
+    assert_eq!(track.duration_ticks(), 768);
+
+    let end_later = vec![
+        (0, note(true, 60)),
+        (500, Event::Meta(MetaEvent::EndOfTrack)),
+    ];
+    let track = Track::from_absolute_events(end_later).unwrap();
+    assert_eq!(track.events_len(), 2);
+    assert_eq!(track.duration_ticks(), 500);
+    assert!(Track::from_absolute_events(vec![(u64::from(u32::MAX) + 1, note(true, 60))]).is_err());
+}
+
+#[test]
+fn grouped_by_tick_test() {
+    // the first measure of examples/main.rs
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    track.set_name("Singer").unwrap();
+    for lyric in ["Row", "row"] {
+        track.push_lyric(0, lyric).unwrap();
+        track
+            .push_note_on(0, ch, NoteNumber::new(72), Velocity::new(64))
+            .unwrap();
+        track
+            .push_note_off(1536, ch, NoteNumber::new(72), Velocity::new(64))
+            .unwrap();
+    }
+    let groups: Vec<_> = track.grouped_by_tick().collect();
+    let ticks: Vec<u64> = groups.iter().map(|(tick, _)| *tick).collect();
+    assert_eq!(ticks, vec![0, 1536, 3072]);
+    let first: Vec<&Event> = groups[0].1.iter().map(|e| e.event()).collect();
+    assert_eq!(first.len(), 3);
+    assert!(matches!(first[1], Event::Meta(MetaEvent::Lyric(lyric)) if lyric.as_str() == "Row"));
+    assert!(matches!(first[2], Event::Midi(Message::NoteOn(_))));
+    assert_eq!(groups[1].1.len(), 3);
+    assert_eq!(groups[2].1.len(), 1);
+}
+
+#[test]
+fn map_events_test() {
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    track
+        .push_note_on(0, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    track
+        .push_note_off(100, ch, NoteNumber::new(60), Velocity::new(0))
+        .unwrap();
+    track
+        .push_event(10, Event::Meta(MetaEvent::EndOfTrack))
+        .unwrap();
+    track.map_events(|delta_time, event| {
+        let event = match event {
+            Event::Midi(Message::NoteOn(m)) => Event::Midi(Message::NoteOn(NoteMessage {
+                note_number: NoteNumber::new(m.note_number.get() + 12),
+                ..*m
+            })),
+            Event::Midi(Message::NoteOff(m)) => Event::Midi(Message::NoteOff(NoteMessage {
+                note_number: NoteNumber::new(m.note_number.get() + 12),
+                ..*m
+            })),
+            // try to turn the end of track into something else
+            _ => Event::Meta(MetaEvent::Lyric(Text::new("x"))),
+        };
+        (delta_time * 2, event)
+    });
+    let events: Vec<_> = track.events().collect();
+    assert_eq!(events[0].delta_time(), 0);
+    assert_eq!(events[1].delta_time(), 200);
+    assert_eq!(events[2].delta_time(), 20);
+    for event in &events[..2] {
+        match event.event() {
+            Event::Midi(Message::NoteOn(m)) | Event::Midi(Message::NoteOff(m)) => {
+                assert_eq!(m.note_number().get(), 72)
+            }
+            other => panic!("expected a note, got {:?}", other),
+        }
+    }
+    assert!(events[2].is_end());
+}
+
+#[test]
+fn effective_channel_at_test() {
+    let instrument = |name: &str| Event::Meta(MetaEvent::InstrumentName(Text::new(name)));
+    let track: Track = vec![
+        TrackEvent::new(0, instrument("none")),
+        TrackEvent::new(
+            0,
+            Event::Meta(MetaEvent::MidiChannelPrefix(Channel::new(3))),
+        ),
+        TrackEvent::new(0, instrument("flute")),
+        TrackEvent::new(0, Event::Meta(MetaEvent::Lyric(Text::new("la")))),
+        TrackEvent::new(
+            0,
+            Event::Meta(MetaEvent::MidiChannelPrefix(Channel::new(5))),
+        ),
+        TrackEvent::new(0, instrument("oboe")),
+        TrackEvent::new(
+            0,
+            Event::Midi(Message::NoteOn(NoteMessage::new(
+                Channel::new(0),
+                NoteNumber::new(60),
+                Velocity::new(64),
+            ))),
+        ),
+        TrackEvent::new(0, instrument("reset")),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(track.effective_channel_at(0), None);
+    assert_eq!(track.effective_channel_at(2), Some(Channel::new(3)));
+    assert_eq!(track.effective_channel_at(3), Some(Channel::new(3)));
+    assert_eq!(track.effective_channel_at(5), Some(Channel::new(5)));
+    assert_eq!(track.effective_channel_at(7), None);
+    assert_eq!(track.effective_channel_at(8), None);
+}
+
+#[test]
+fn split_at_tick_test() {
+    let ch = Channel::new(0);
+    let note = |n: u8| NoteNumber::new(n);
+    let vel = Velocity::new(80);
+    let mut track = Track::default();
+    track
+        .push_time_signature(0, 4, DurationName::Quarter, Clocks::Quarter)
+        .unwrap();
+    // two measures of quarter notes at 1024 ticks per quarter
+    for n in [60u8, 62, 64, 65, 67, 69, 71, 72] {
+        track.push_note_on(0, ch, note(n), vel).unwrap();
+        track.push_note_off(1024, ch, note(n), vel).unwrap();
+    }
+    // a half note that starts on the last beat of the first measure
+    track
+        .insert_event_at_tick(
+            3072,
+            Event::Midi(Message::NoteOn(NoteMessage::new(ch, note(48), vel))),
+        )
+        .unwrap();
+    track
+        .insert_event_at_tick(
+            5120,
+            Event::Midi(Message::NoteOff(NoteMessage::new(ch, note(48), vel))),
+        )
+        .unwrap();
+    track
+        .push_event(0, Event::Meta(MetaEvent::EndOfTrack))
+        .unwrap();
+
+    let (first, second) = track.split_at_tick(4096).unwrap();
+    assert_eq!(first.duration_ticks(), 4096);
+    assert_eq!(second.duration_ticks(), 4096);
+    assert!(first.events().last().unwrap().is_end());
+    assert!(second.events().last().unwrap().is_end());
+    // the time signature, four quarter notes and the cut half note
+    assert_eq!(first.events_len(), 1 + 8 + 2 + 1);
+    assert_eq!(first.note_pairs().len(), 5);
+    assert!(first.note_pairs().iter().all(|(_, off)| off.is_some()));
+    assert!(first.notes_sounding_at(4095).contains(&(ch, note(48))));
+    // the half note is struck again at the start of the second measure
+    assert_eq!(second.events_len(), 2 + 8 + 1);
+    assert_eq!(
+        *second[0].event(),
+        Event::Midi(Message::NoteOn(NoteMessage::new(ch, note(48), vel)))
+    );
+    assert_eq!(second[0].delta_time(), 0);
+    assert_eq!(
+        second.notes_sounding_at(0),
+        vec![(ch, note(48)), (ch, note(67))]
+    );
+    assert!(second.note_pairs().iter().all(|(_, off)| off.is_some()));
+}
+
+#[test]
+fn delta_time_limit_test() {
+    let event = || Event::Meta(MetaEvent::Lyric(Text::new("la")));
+    let mut track = Track::default();
+    track.push_event(MAX_VLQ_VALUE, event()).unwrap();
+    let error = track.push_event(0x1000_0000, event()).unwrap_err();
+    assert_eq!(error.kind(), crate::ErrorKind::Overflow);
+    assert!(track.insert_event(0, 0x1000_0000, event()).is_err());
+    assert!(Track::from_absolute_events(vec![(0x1000_0000, event())]).is_err());
+    assert!(Track::from_absolute_events(vec![(MAX_VLQ_VALUE.into(), event())]).is_ok());
+    assert!(track.replace_event(0, 0x1000_0000, event()).is_err());
+    assert!(track.shift(1).is_err());
+    assert_eq!(track.events_len(), 1);
+    assert_eq!(track[0].delta_time(), MAX_VLQ_VALUE);
+
+    // out of range indexes are errors instead of panics
+    assert!(track.insert_event(2, 0, event()).is_err());
+    assert!(track.replace_event(1, 0, event()).is_err());
+    track.insert_event(1, 0, event()).unwrap();
+    assert_eq!(track.events_len(), 2);
+
+    // the paths that cannot return an error clamp instead
+    let mut track_event = TrackEvent::new(u32::MAX, event());
+    assert_eq!(track_event.delta_time(), MAX_VLQ_VALUE);
+    track_event.set_delta_time(0x1000_0000);
+    assert_eq!(track_event.delta_time(), MAX_VLQ_VALUE);
+    track_event.delta_time = u32::MAX;
+    let mut track = Track::from_events(vec![track_event]);
+    assert_eq!(track[0].delta_time(), MAX_VLQ_VALUE);
+    track.map_events(|_, event| (u32::MAX, event.clone()));
+    assert!(track.events().all(|e| e.delta_time() == MAX_VLQ_VALUE));
+    track.set_end_tick(u64::MAX);
+    assert_eq!(track[1].delta_time(), MAX_VLQ_VALUE);
+}
+
+#[test]
+fn insert_event_preserving_time_test() {
+    let ch = Channel::new(0);
+    let lyric = || Event::Meta(MetaEvent::Lyric(Text::new("la")));
+    let mut track = Track::default();
+    track.push_lyric(0, "a").unwrap();
+    track
+        .push_note_on(100, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    track
+        .push_note_off(100, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    let track = ensure_end_of_track(track).unwrap();
+    let ticks = |track: &Track| -> Vec<u64> {
+        let mut tick = 0;
+        track
+            .events()
+            .map(|e| {
+                tick += u64::from(e.delta_time());
+                tick
+            })
+            .collect()
+    };
+    assert_eq!(ticks(&track), vec![0, 100, 200, 200]);
+
+    let mut preserved = track.clone();
+    preserved
+        .insert_event_preserving_time(1, 40, lyric())
+        .unwrap();
+    assert_eq!(ticks(&preserved), vec![0, 40, 100, 200, 200]);
+    assert_eq!(preserved[2].event(), track[1].event());
+
+    // a raw insert moves everything after the index
+    let mut raw = track.clone();
+    raw.insert_event(1, 40, lyric()).unwrap();
+    assert_eq!(ticks(&raw), vec![0, 40, 140, 240, 240]);
+
+    // the new event cannot come after the event that it is inserted before
+    let mut too_late = track.clone();
+    assert!(too_late
+        .insert_event_preserving_time(1, 101, lyric())
+        .is_err());
+    assert_eq!(too_late, track);
+
+    // at the end there is no next event to adjust
+    let mut end = track.clone();
+    end.insert_event_preserving_time(4, 7, lyric()).unwrap();
+    assert_eq!(ticks(&end), vec![0, 100, 200, 200, 207]);
+    assert!(end.insert_event_preserving_time(6, 0, lyric()).is_err());
+}
+
+#[test]
+fn into_events_from_events_test() {
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    track.push_lyric(0, "a").unwrap();
+    track
+        .push_note_on(10, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    track
+        .push_note_off(100, ch, NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    let track = ensure_end_of_track(track).unwrap();
+
+    let events = track.clone().into_events();
+    assert_eq!(events.len(), 4);
+    assert_eq!(Track::from_events(events), track);
+
+    // an EndOfTrack is added when it is missing
+    let mut events = track.clone().into_events();
+    events.pop();
+    assert_eq!(Track::from_events(events), track);
+    assert_eq!(Track::from_events(Vec::new()).events_len(), 1);
+}
+
+#[test]
+fn push_chord_test() {
+    let ch = Channel::new(2);
+    let triad = [
+        NoteNumber::new(60),
+        NoteNumber::new(64),
+        NoteNumber::new(67),
+    ];
+    let mut track = Track::default();
+    track.push_lyric(0, "a").unwrap();
+    track
+        .push_chord(96, 480, ch, &triad, Velocity::new(72))
+        .unwrap();
+    assert_eq!(track.events_len(), 7);
+    let mut tick = 0u64;
+    let mut ons = Vec::new();
+    let mut offs = Vec::new();
+    for event in track.events() {
+        tick += u64::from(event.delta_time());
+        match event.event() {
+            Event::Midi(Message::NoteOn(m)) => ons.push((tick, m.note_number())),
+            Event::Midi(Message::NoteOff(m)) => offs.push((tick, m.note_number())),
+            _ => {}
+        }
+    }
+    assert_eq!(ons, triad.iter().map(|&n| (96, n)).collect::<Vec<_>>());
+    assert_eq!(offs, triad.iter().map(|&n| (576, n)).collect::<Vec<_>>());
+
+    track
+        .push_chord(10, 10, ch, &[], Velocity::new(72))
+        .unwrap();
+    assert_eq!(track.events_len(), 7);
+    assert!(track
+        .push_chord(0, 0x1000_0000, ch, &triad, Velocity::new(72))
+        .is_err());
+    assert_eq!(track.events_len(), 7);
+}
+
+#[test]
+fn fix_retriggers_test() {
+    let ch = Channel::new(0);
+    let other = Channel::new(1);
+    let c4 = NoteNumber::new(60);
+    let v = Velocity::new(90);
+    let mut track = Track::default();
+    track.push_note_on(0, ch, c4, v).unwrap();
+    // the same note on another channel is not a re-trigger
+    track.push_note_on(0, other, c4, v).unwrap();
+    track.push_note_on(100, ch, c4, v).unwrap();
+    track.push_note_off(100, ch, c4, v).unwrap();
+    track.push_note_off(0, other, c4, v).unwrap();
+    track.push_note_off(50, ch, c4, v).unwrap();
+    // not a re-trigger, the note has been turned off
+    track.push_note_on(10, ch, c4, v).unwrap();
+    track.push_note_off(10, ch, c4, v).unwrap();
+    let mut track = ensure_end_of_track(track).unwrap();
+    let ticks = |track: &Track| -> Vec<(u64, bool)> {
+        let mut tick = 0;
+        track
+            .events()
+            .map(|e| {
+                tick += u64::from(e.delta_time());
+                (tick, matches!(e.event(), Event::Midi(Message::NoteOff(_))))
+            })
+            .collect()
+    };
+    let before = ticks(&track);
+
+    track.fix_retriggers();
+    assert_eq!(track.events_len(), before.len() + 1);
+    let after = ticks(&track);
+    assert_eq!(after[2], (100, true));
+    assert_eq!(after[3], (100, false));
+    assert!(matches!(
+        track[2].event(),
+        Event::Midi(Message::NoteOff(m)) if m.channel() == ch && m.note_number() == c4
+    ));
+    // every other event is at the same tick
+    let mut without_inserted = after.clone();
+    without_inserted.remove(2);
+    assert_eq!(without_inserted, before);
+
+    // running it again changes nothing
+    let fixed = track.clone();
+    track.fix_retriggers();
+    assert_eq!(track, fixed);
+}
+
+#[test]
+fn push_time_signature_fraction_test() {
+    let mut track = Track::default();
+    track
+        .push_time_signature_fraction(0, 6, 8, Clocks::DottedQuarter)
+        .unwrap();
+    let mut expected = Track::default();
+    expected
+        .push_time_signature(0, 6, DurationName::Eighth, Clocks::DottedQuarter)
+        .unwrap();
+    assert_eq!(track, expected);
+    track
+        .push_time_signature_fraction(0, 3, 1, Clocks::Whole)
+        .unwrap();
+    assert!(matches!(
+        track[1].event(),
+        Event::Meta(MetaEvent::TimeSignature(ts)) if ts.denominator() == DurationName::Whole
+    ));
+
+    assert!(track
+        .push_time_signature_fraction(0, 6, 6, Clocks::DottedQuarter)
+        .is_err());
+    assert!(track
+        .push_time_signature_fraction(0, 6, 0, Clocks::DottedQuarter)
+        .is_err());
+    assert_eq!(track.events_len(), 2);
+}
+
+#[test]
+fn remap_velocity_curve_test() {
+    use crate::core::VelocityCurve;
+    let ch = Channel::new(0);
+    let mut track = Track::default();
+    for (i, velocity) in [1u8, 40, 100, 127].iter().enumerate() {
+        let note_number = NoteNumber::new(60 + i as u8);
+        track
+            .push_note_on(0, ch, note_number, Velocity::new(*velocity))
+            .unwrap();
+        track
+            .push_note_on(10, ch, note_number, Velocity::new(0))
+            .unwrap();
+    }
+    let velocities = |track: &Track| -> Vec<u8> {
+        track
+            .events()
+            .filter_map(|e| match e.event() {
+                Event::Midi(Message::NoteOn(m)) => Some(m.velocity().get()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let original = track.clone();
+    let identity = VelocityCurve::linear(1.0);
+    track.remap_velocity_curve(|v| identity.apply(v));
+    assert_eq!(track, original);
+
+    // the note-offs stay at zero, and the loudest note is inverted to 1 rather than a note-off
+    track.remap_velocity_curve(|v| 127 - v);
+    assert_eq!(velocities(&track), vec![126, 0, 87, 0, 27, 0, 1, 0]);
+
+    // out of range results are clamped
+    track.remap_velocity_curve(|_| 200);
+    assert_eq!(velocities(&track), vec![127, 0, 127, 0, 127, 0, 127, 0]);
+}
+
+#[test]
+fn panic_test() {
+    let c4 = NoteNumber::new(60);
+    let v = Velocity::new(90);
+    let mut track = Track::default();
+    track.push_note_on(0, Channel::new(0), c4, v).unwrap();
+    track.push_note_on(0, Channel::new(9), c4, v).unwrap();
+    track.push_note_off(480, Channel::new(0), c4, v).unwrap();
+    track.push_note_off(480, Channel::new(9), c4, v).unwrap();
+    let mut track = ensure_end_of_track(track).unwrap();
+    track.panic(600).unwrap();
+    let events: Vec<(u64, Event)> = track
+        .events()
+        .scan(0u64, |tick, e| {
+            *tick += u64::from(e.delta_time());
+            Some((*tick, e.event().clone()))
+        })
+        .collect();
+    assert_eq!(events.len(), 11);
+    assert_eq!(events[2].0, 480);
+    assert_eq!(events[9].0, 960);
+    let mut expected = Vec::new();
+    for channel in [Channel::new(0), Channel::new(9)] {
+        expected.push((600, Event::Midi(Message::AllSoundsOff(channel))));
+        expected.push((600, Event::Midi(Message::AllNotesOff(channel))));
+        expected.push((600, Event::Midi(Message::ResetAllControllers(channel))));
+    }
+    assert_eq!(&events[3..9], expected.as_slice());
+
+    let mut empty = Track::default();
+    empty.panic(0).unwrap();
+    assert!(empty.is_empty());
+}
@@ -0,0 +1,182 @@
+use crate::core::{Channel, Message, NoteNumber};
+use crate::file::{Event, Format, MetaEvent};
+use crate::MidiFile;
+use std::fmt::{Display, Formatter};
+
+/// The kind of spec violation found by [`MidiFile::validate`].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub enum ValidationWarningKind {
+    /// A [`Format::Single`] file has more than one track. The warning points at the first extra
+    /// track.
+    MultipleTracksInSingleFormat,
+    /// An `EndOfTrack` event is not the last event of its track, or a track does not end with one.
+    EndOfTrackNotLast,
+    /// A `SequenceNumber` event occurs after time zero.
+    SequenceNumberNotAtTimeZero,
+    /// A `TrackName` event occurs after time zero.
+    TrackNameNotAtTimeZero,
+    /// A `Copyright` event is not the first event of the first track.
+    CopyrightNotFirst,
+    /// A note-on event has no matching note-off.
+    NoteWithoutOff,
+}
+
+impl Display for ValidationWarningKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ValidationWarningKind::MultipleTracksInSingleFormat => {
+                "a format 0 file has more than one track"
+            }
+            ValidationWarningKind::EndOfTrackNotLast => "EndOfTrack is not the last event",
+            ValidationWarningKind::SequenceNumberNotAtTimeZero => {
+                "SequenceNumber is not at time zero"
+            }
+            ValidationWarningKind::TrackNameNotAtTimeZero => "TrackName is not at time zero",
+            ValidationWarningKind::CopyrightNotFirst => {
+                "Copyright is not the first event of the first track"
+            }
+            ValidationWarningKind::NoteWithoutOff => "note-on has no matching note-off",
+        };
+        f.write_str(s)
+    }
+}
+
+/// A spec violation found by [`MidiFile::validate`], with the location of the offending event.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct ValidationWarning {
+    track: usize,
+    event: usize,
+    kind: ValidationWarningKind,
+}
+
+impl ValidationWarning {
+    /// The zero-based index of the track.
+    pub fn track(&self) -> usize {
+        self.track
+    }
+
+    /// The zero-based index of the event within the track.
+    pub fn event(&self) -> usize {
+        self.event
+    }
+
+    /// A getter for the `kind` field.
+    pub fn kind(&self) -> ValidationWarningKind {
+        self.kind
+    }
+}
+
+impl Display for ValidationWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "track {}, event {}: {}",
+            self.track, self.event, self.kind
+        )
+    }
+}
+
+pub(crate) fn validate(mfile: &MidiFile) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |track: usize, event: usize, kind: ValidationWarningKind| {
+        warnings.push(ValidationWarning { track, event, kind })
+    };
+    if *mfile.header().format() == Format::Single && mfile.tracks_len() > 1 {
+        warn(1, 0, ValidationWarningKind::MultipleTracksInSingleFormat);
+    }
+    for (track_ix, track) in mfile.tracks().enumerate() {
+        let last_ix = track.events_len().saturating_sub(1);
+        let mut tick = 0u64;
+        for (event_ix, event) in track.events().enumerate() {
+            tick += u64::from(event.delta_time());
+            match event.event() {
+                Event::Meta(MetaEvent::EndOfTrack) if event_ix != last_ix => {
+                    warn(track_ix, event_ix, ValidationWarningKind::EndOfTrackNotLast)
+                }
+                Event::Meta(MetaEvent::SequenceNumber) if tick != 0 => warn(
+                    track_ix,
+                    event_ix,
+                    ValidationWarningKind::SequenceNumberNotAtTimeZero,
+                ),
+                Event::Meta(MetaEvent::TrackName(_)) if tick != 0 => warn(
+                    track_ix,
+                    event_ix,
+                    ValidationWarningKind::TrackNameNotAtTimeZero,
+                ),
+                Event::Meta(MetaEvent::Copyright(_)) if track_ix != 0 || event_ix != 0 => {
+                    warn(track_ix, event_ix, ValidationWarningKind::CopyrightNotFirst)
+                }
+                _ => {}
+            }
+        }
+        match track.events().last().map(|e| e.event()) {
+            Some(Event::Meta(MetaEvent::EndOfTrack)) => {}
+            _ => warn(track_ix, last_ix, ValidationWarningKind::EndOfTrackNotLast),
+        }
+        for (on_ix, off_ix) in track.note_pairs() {
+            if off_ix.is_none() {
+                warn(track_ix, on_ix, ValidationWarningKind::NoteWithoutOff);
+            }
+        }
+    }
+    warnings
+}
+
+/// A note-on that is never turned off, found by [`MidiFile::stuck_notes`].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct StuckNote {
+    track: u32,
+    channel: Channel,
+    note_number: NoteNumber,
+    on_tick: u64,
+}
+
+impl StuckNote {
+    /// The zero-based index of the track.
+    pub fn track(&self) -> u32 {
+        self.track
+    }
+
+    /// A getter for the `channel` field.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// A getter for the `note_number` field.
+    pub fn note_number(&self) -> NoteNumber {
+        self.note_number
+    }
+
+    /// The absolute tick of the note-on.
+    pub fn on_tick(&self) -> u64 {
+        self.on_tick
+    }
+}
+
+pub(crate) fn stuck_notes(mfile: &MidiFile) -> Vec<StuckNote> {
+    let mut stuck = Vec::new();
+    for (track_ix, track) in mfile.tracks().enumerate() {
+        let ticks: Vec<u64> = track
+            .events()
+            .scan(0u64, |tick, event| {
+                *tick += u64::from(event.delta_time());
+                Some(*tick)
+            })
+            .collect();
+        for (on_ix, off_ix) in track.note_pairs() {
+            if off_ix.is_some() {
+                continue;
+            }
+            if let Event::Midi(Message::NoteOn(m)) = track[on_ix].event() {
+                stuck.push(StuckNote {
+                    // the number of tracks is limited to u32 by MidiFile
+                    track: track_ix as u32,
+                    channel: m.channel(),
+                    note_number: m.note_number(),
+                    on_tick: ticks[on_ix],
+                });
+            }
+        }
+    }
+    stuck
+}
@@ -22,24 +22,36 @@ mod error;
 #[macro_use]
 mod macros;
 
-use crate::byte_iter::ByteIter;
+use crate::byte_iter::{ByteIter, ByteSource, ReadSettings};
 use std::convert::TryFrom;
 use std::io::{BufWriter, Read, Write};
 use std::path::Path;
 
 mod byte_iter;
 pub mod core;
+mod csv;
 pub mod file;
+mod rmid;
 mod scribe;
 mod text;
 
+use crate::core::{
+    Channel, Control, ControlValue, Message, NoteMessage, NoteNumber, Program, Velocity,
+};
 use crate::error::LibResult;
-use crate::file::{ensure_end_of_track, Division, Format, Header, Track};
+use crate::file::{
+    ensure_end_of_track, ensure_tempo, ConductorSummary, Difference, Division, Event,
+    EventHistogram, EventStream, Format, Header, KeySignatureValue, MetaEvent,
+    MicrosecondsPerQuarter, Note, NoteEventMs, QuartersPerMinute, ScheduledEvent, StuckNote,
+    TimeSignatureValue, Track, ValidationWarning,
+};
 use crate::scribe::{Scribe, ScribeSettings};
-pub use crate::text::Text;
-pub use error::{Error, Result};
-use log::trace;
+pub use crate::text::{Text, TextEncoding, MAX_TEXT_LEN};
+pub use error::{Error, ErrorKind, Result, TrackError};
+use log::{debug, trace};
 use snafu::{ensure, ResultExt};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 
 /// Optionally provide settings to the [`MidiFile`]. This is a 'builder' struct.
@@ -63,6 +75,24 @@ pub struct Settings {
     division: Division,
     /// Whether or not we should omit redundant status bytes.
     running_status: bool,
+    /// Whether a missing `EndOfTrack` should be added instead of failing when reading.
+    repair_missing_end_of_track: bool,
+    /// Whether each channel message should record if it was read with its own status byte.
+    preserve_status_bytes: bool,
+    /// Whether a track chunk with no bytes should be read as an empty track instead of failing.
+    allow_empty_tracks: bool,
+    /// Whether a track chunk that is longer than the remaining data should be cut short.
+    clamp_track_lengths: bool,
+    /// Whether bytes after the last track chunk should be ignored instead of failing when reading.
+    ignore_trailing_data: bool,
+    /// The encoding of text meta events.
+    text_encoding: TextEncoding,
+    /// A tempo to add to the first track if it does not have one.
+    default_tempo: Option<QuartersPerMinute>,
+    /// Whether note-offs with a velocity of zero are written as note-ons.
+    compress_note_offs: bool,
+    /// The multiple of bytes that the data of each track chunk is padded to when writing.
+    track_alignment: u32,
 }
 
 impl Settings {
@@ -72,6 +102,15 @@ impl Settings {
             format: Format::default(),
             division: Division::default(),
             running_status: false,
+            repair_missing_end_of_track: false,
+            preserve_status_bytes: false,
+            allow_empty_tracks: false,
+            clamp_track_lengths: false,
+            ignore_trailing_data: true,
+            text_encoding: TextEncoding::Utf8,
+            default_tempo: None,
+            compress_note_offs: false,
+            track_alignment: 0,
         }
     }
 
@@ -93,6 +132,111 @@ impl Settings {
         self.division = value;
         self
     }
+
+    /// Set the `repair_missing_end_of_track` setting. When this is `true`, reading a track chunk
+    /// that ends without an `EndOfTrack` event will add one instead of returning an error. Defaults
+    /// to `false`.
+    pub fn repair_missing_end_of_track(mut self, value: bool) -> Self {
+        self.repair_missing_end_of_track = value;
+        self
+    }
+
+    /// Set the `preserve_status_bytes` setting. When this is `true`, reading records, for each
+    /// channel message, whether it had its own status byte or relied on running status, see
+    /// [`TrackEvent::explicit_status`]. When the file is written, each of these messages is written
+    /// the same way, so a file that mixes the two can be reproduced byte-for-byte, which the
+    /// `running_status` setting alone cannot do. The recorded layout is ignored by comparisons, so
+    /// events that were read this way equal the same events read without it. Defaults to `false`.
+    pub fn preserve_status_bytes(mut self, value: bool) -> Self {
+        self.preserve_status_bytes = value;
+        self
+    }
+
+    /// Set the `allow_empty_tracks` setting. When this is `true`, reading a track chunk with a
+    /// length of zero will produce a track with only an `EndOfTrack` event instead of returning an
+    /// error. Defaults to `false`.
+    pub fn allow_empty_tracks(mut self, value: bool) -> Self {
+        self.allow_empty_tracks = value;
+        self
+    }
+
+    /// Set the `clamp_track_lengths` setting. When this is `true`, a track chunk that declares a
+    /// length greater than the number of bytes left in the data is read up to the end of the data,
+    /// instead of returning an error. Because such a chunk has usually lost its `EndOfTrack`, this
+    /// also adds a missing one, as [`Settings::repair_missing_end_of_track`] does. An event that is
+    /// cut off by the end of the data is still an error. Defaults to `false`.
+    pub fn clamp_track_lengths(mut self, value: bool) -> Self {
+        self.clamp_track_lengths = value;
+        self
+    }
+
+    /// Set the `ignore_trailing_data` setting. When this is `true`, any bytes that follow the last
+    /// track chunk declared in the header, such as padding or unknown chunks, are ignored when
+    /// reading. Set it to `false` to return an error instead. Defaults to `true`. Unknown chunks
+    /// that come before a track chunk are always skipped, as the spec requires.
+    pub fn ignore_trailing_data(mut self, value: bool) -> Self {
+        self.ignore_trailing_data = value;
+        self
+    }
+
+    /// Set the `text_encoding` setting, which is used to decode text meta events when reading, and
+    /// to encode them when writing. Defaults to [`TextEncoding::Utf8`].
+    pub fn text_encoding(mut self, value: TextEncoding) -> Self {
+        self.text_encoding = value;
+        self
+    }
+
+    /// Set the `default_tempo` setting. When this is set, and the first track is added to the file
+    /// with [`MidiFile::push_track`], a `SetTempo` event is added at tick zero of that track unless
+    /// it already has one. In a `Single` or `Multi` format file, the first track is the one that
+    /// holds the tempo map. In a `Sequential` file, each track is an independent sequence, so the
+    /// tempo is only added to the first of them.
+    pub fn default_tempo(mut self, value: QuartersPerMinute) -> Self {
+        self.default_tempo = Some(value);
+        self
+    }
+
+    /// Set the `compress_note_offs` setting. When this is `true`, a note-off with a velocity of
+    /// zero is written as a note-on with a velocity of zero, which means the same thing. Together
+    /// with `running_status` this lets runs of notes on a channel share a single status byte. A
+    /// note-off with any other velocity is written unchanged so that its velocity is not lost. The
+    /// file will read back with note-ons in place of these note-offs, see
+    /// [`MidiFile::normalize_note_offs`]. Defaults to `false`.
+    pub fn compress_note_offs(mut self, value: bool) -> Self {
+        self.compress_note_offs = value;
+        self
+    }
+
+    /// Set the `pad_track_to` setting. This is a workaround for legacy players that expect the
+    /// length of each track chunk to be a multiple of `align` bytes. When writing, an empty or
+    /// space-filled `OtherText` event with a delta time of zero is added before the `EndOfTrack` of
+    /// each track that needs it, so the bytes change but the timing and the other events do not.
+    /// The padding event is read back like any other text event. A value of `0` or `1` means no
+    /// padding, which is the default.
+    pub fn pad_track_to(mut self, align: u32) -> Self {
+        self.track_alignment = align;
+        self
+    }
+
+    fn scribe_settings(&self) -> ScribeSettings {
+        ScribeSettings {
+            running_status: self.running_status,
+            text_encoding: self.text_encoding,
+            compress_note_offs: self.compress_note_offs,
+            track_alignment: self.track_alignment,
+        }
+    }
+
+    fn read_settings(&self) -> ReadSettings {
+        ReadSettings {
+            repair_missing_end_of_track: self.repair_missing_end_of_track,
+            preserve_status_bytes: self.preserve_status_bytes,
+            allow_empty_tracks: self.allow_empty_tracks,
+            clamp_track_lengths: self.clamp_track_lengths,
+            ignore_trailing_data: self.ignore_trailing_data,
+            text_encoding: self.text_encoding,
+        }
+    }
 }
 
 impl Default for Settings {
@@ -108,6 +252,10 @@ pub struct MidiFile {
     header: Header,
     tracks: Vec<Track>,
     running_status: bool,
+    text_encoding: TextEncoding,
+    default_tempo: Option<QuartersPerMinute>,
+    compress_note_offs: bool,
+    track_alignment: u32,
 }
 
 impl Default for MidiFile {
@@ -116,6 +264,35 @@ impl Default for MidiFile {
     }
 }
 
+/// Read a `MidiFile` from a slice of bytes, see [`MidiFile::from_bytes`].
+///
+/// # Example
+/// ```
+/// use midi_file::MidiFile;
+/// use std::convert::TryFrom;
+///
+/// let mut bytes = Vec::new();
+/// MidiFile::new().write(&mut bytes).unwrap();
+/// let midi_file = MidiFile::try_from(bytes.as_slice()).unwrap();
+/// assert_eq!(midi_file.tracks_len(), 0);
+/// ```
+impl TryFrom<&[u8]> for MidiFile {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+/// Read a `MidiFile` from a vector of bytes, see [`MidiFile::from_bytes`].
+impl TryFrom<Vec<u8>> for MidiFile {
+    type Error = Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_bytes(&bytes)
+    }
+}
+
 impl MidiFile {
     /// Create a new `MidiFile` with reasonable default [`Settings`].
     pub fn new() -> Self {
@@ -127,47 +304,202 @@ impl MidiFile {
         &self.header
     }
 
+    /// Change the format in the header without changing the tracks. Returns an error if `format`
+    /// is [`Format::Single`] and the file has more than one track.
+    pub fn set_format(&mut self, format: Format) -> Result<()> {
+        if format == Format::Single {
+            ensure!(self.tracks_len() <= 1, error::OtherSnafu { site: site!() });
+        }
+        self.header = Header::new(format, *self.header.division());
+        Ok(())
+    }
+
+    /// The number of ticks per quarter note, or `None` if the division is SMPTE based. See
+    /// [`Header::ppq`].
+    pub fn ppq(&self) -> Option<u16> {
+        self.header.ppq()
+    }
+
     /// A getter for the `running_status` field.
     pub fn running_status(&self) -> bool {
         self.running_status
     }
 
+    /// The encoding that text meta events are written with, see [`Settings::text_encoding`].
+    pub fn text_encoding(&self) -> TextEncoding {
+        self.text_encoding
+    }
+
     /// Create a new `MidiFile` with customizable [`Settings`].
     pub fn new_with_settings(settings: Settings) -> Self {
         Self {
             header: Header::new(settings.format, settings.division),
             tracks: Vec::new(),
             running_status: settings.running_status,
+            text_encoding: settings.text_encoding,
+            default_tempo: settings.default_tempo,
+            compress_note_offs: settings.compress_note_offs,
+            track_alignment: settings.track_alignment,
         }
     }
 
-    /// Read a `MidiFile` from bytes.
+    /// Create a [`Format::Single`] file with one track that has only an `EndOfTrack` event. This is
+    /// the smallest valid file and can be written as is, unlike [`MidiFile::new`], which has no
+    /// tracks and is rejected by some players.
+    pub fn empty_single_track() -> Self {
+        let mut mfile = Self::new_with_settings(Settings::new().format(Format::Single));
+        mfile.tracks.push(Track::from_events(Vec::new()));
+        mfile
+    }
+
+    /// Read a `MidiFile` from bytes. The reader is not buffered, so wrap it in a `BufReader` if
+    /// small reads from it are slow, e.g. if it is a `File`. Reading stops at most three bytes after
+    /// the end of the MIDI data, so a file that is embedded in a larger stream can be read without
+    /// losing much of what follows it.
     pub fn read<R: Read>(r: R) -> Result<Self> {
-        let bytes = r.bytes();
-        let iter = ByteIter::new(bytes).context(io!())?;
+        let iter = ByteIter::new(r).context(io!())?;
+        Ok(Self::read_inner(iter)?)
+    }
+
+    /// Read a `MidiFile` from bytes, using the reading options in [`Settings`]. The `format`,
+    /// `division` and `running_status` settings are ignored since these come from the data.
+    pub fn read_with_settings<R: Read>(r: R, settings: Settings) -> Result<Self> {
+        let mut iter = ByteIter::new(r).context(io!())?;
+        iter.set_settings(settings.read_settings());
+        Ok(Self::read_inner(iter)?)
+    }
+
+    /// Read a `MidiFile` from an in-memory slice of bytes. This is faster than [`MidiFile::read`]
+    /// since the bytes are indexed directly instead of being read through `Read`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let iter = ByteIter::from_slice(bytes).context(io!())?;
         Ok(Self::read_inner(iter)?)
     }
 
+    /// Read a `MidiFile` from an RMID (`.rmi`) file, which is a RIFF container with a standard MIDI
+    /// file in its `data` chunk. Other chunks, such as `LIST` and `DISP`, are ignored.
+    pub fn read_rmid<R: Read>(r: R) -> Result<Self> {
+        let mut iter = ByteIter::new(r).context(io!())?;
+        let data = rmid::read_data_chunk(&mut iter)?;
+        Self::from_bytes(&data)
+    }
+
+    /// Read the header of a `MidiFile`, then return an iterator that parses and yields one event at
+    /// a time as `(track_index, event)`, instead of loading all tracks into memory. Iteration stops
+    /// after the first error.
+    pub fn stream_events<R: Read>(r: R) -> Result<EventStream<R>> {
+        let iter = ByteIter::new(r).context(io!())?;
+        Ok(EventStream::new(iter)?)
+    }
+
     /// Load a `MidiFile` from a file path.
     pub fn load<P: AsRef<Path>>(file: P) -> Result<Self> {
         Ok(Self::read_inner(ByteIter::new_file(file).context(io!())?)?)
     }
 
-    /// Write a `MidiFile` to bytes.
+    /// Load a `MidiFile` from a file path, using the reading options in [`Settings`], see
+    /// [`MidiFile::read_with_settings`].
+    pub fn load_with_settings<P: AsRef<Path>>(file: P, settings: Settings) -> Result<Self> {
+        let mut iter = ByteIter::new_file(file).context(io!())?;
+        iter.set_settings(settings.read_settings());
+        Ok(Self::read_inner(iter)?)
+    }
+
+    /// Load only the track at `track_index` from a file path. The track chunks before it are
+    /// skipped using their length fields without parsing their events, and the chunks after it are
+    /// not read at all, so this is faster than [`MidiFile::load`] when only one track is needed.
+    /// Returns an error if the file has fewer than `track_index + 1` tracks.
+    pub fn load_track<P: AsRef<Path>>(path: P, track_index: u32) -> Result<Track> {
+        Ok(Self::load_track_inner(
+            ByteIter::new_file(path).context(io!())?,
+            track_index,
+        )?)
+    }
+
+    /// Load a `MidiFile` from a file path, skipping any track that cannot be read instead of failing.
+    /// Each track chunk is parsed on its own, and when one fails, reading continues with the next
+    /// chunk, which is found using the length of the failed one. The tracks that were read are
+    /// returned in order along with an error for each track that was not. If the length of a failed
+    /// chunk cannot be trusted, i.e. its header is damaged or it runs past the end of the file, the
+    /// remaining tracks cannot be found and are reported as errors too. Returns an error if the file
+    /// cannot be read or its header chunk is invalid.
+    pub fn load_lossy<P: AsRef<Path>>(path: P) -> Result<(Self, Vec<TrackError>)> {
+        let bytes = byte_iter::read_file(path).context(io!())?;
+        Ok(Self::read_lossy_inner(&bytes)?)
+    }
+
+    /// Load a `MidiFile` from a file path by memory-mapping the file and parsing the mapped bytes
+    /// directly, instead of copying them through a buffer. This can be faster for very large
+    /// files. The result is the same as [`MidiFile::load`].
+    ///
+    /// The file is mapped with the `memmap2` crate. A memory map reflects changes that are made to
+    /// the file while it is mapped, so if another process modifies the file during the call, the
+    /// parser may see inconsistent bytes, and if it truncates the file, the process may be killed
+    /// by a `SIGBUS` signal. Only use this for files that will not be modified while loading.
+    #[cfg(feature = "mmap")]
+    pub fn load_mmap<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let map = byte_iter::map_file(path).context(io!())?;
+        let iter = ByteIter::from_slice(&map).context(io!())?;
+        Ok(Self::read_inner(iter)?)
+    }
+
+    /// Read a `MidiFile` from an in-memory slice of bytes, parsing the track chunks in parallel.
+    /// The result is the same as [`MidiFile::from_bytes`].
+    #[cfg(feature = "rayon")]
+    pub fn read_parallel(bytes: &[u8]) -> Result<Self> {
+        Ok(Self::read_parallel_inner(bytes)?)
+    }
+
+    /// Load a `MidiFile` from a file path, parsing the track chunks in parallel.
+    #[cfg(feature = "rayon")]
+    pub fn load_parallel<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let bytes = byte_iter::read_file(file).context(io!())?;
+        Self::read_parallel(&bytes)
+    }
+
+    /// Write a `MidiFile` to bytes. The bytes are written after anything that is already in `w`,
+    /// and `w` is flushed when the file has been written.
     pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
-        let ntracks = u16::try_from(self.tracks.len())
-            .context(error::TooManyTracksSnafu { site: site!() })?;
-        let mut scribe = Scribe::new(
-            w,
-            ScribeSettings {
-                running_status: self.running_status,
-            },
-        );
-        self.header.write(&mut scribe, ntracks)?;
-        for track in self.tracks() {
-            track.write(&mut scribe)?;
-        }
-        Ok(())
+        Ok(self.write_inner(w, self.scribe_settings())?)
+    }
+
+    /// Write a `MidiFile` wrapped in an RMID (`.rmi`) container, that is, a RIFF file of type `RMID`
+    /// with the MIDI file as its only chunk, `data`.
+    pub fn write_rmid<W: Write>(&self, w: &mut W) -> Result<()> {
+        let bytes = self.write_to_vec()?;
+        Ok(rmid::write_rmid(&bytes, w)?)
+    }
+
+    /// Write a `MidiFile` to a new `Vec` of bytes, see [`MidiFile::write`]. This is useful for
+    /// placing the file inside a container format, such as RMID, see [`MidiFile::write_rmid`].
+    pub fn write_to_vec(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Write a `MidiFile` to bytes, using the `running_status` and `text_encoding` of [`Settings`]
+    /// instead of those of the `MidiFile`. For example, a file that was read with running status
+    /// can be written with explicit status bytes for every message by passing
+    /// `Settings::new().running_status(false)`.
+    pub fn write_with_settings<W: Write>(&self, w: &mut W, settings: Settings) -> Result<()> {
+        Ok(self.write_inner(w, settings.scribe_settings())?)
+    }
+
+    /// Write the `MidiFile` as text in the format produced by the
+    /// [`midicsv`](https://www.fourmilab.ch/webtools/midicsv/) program. This is useful for
+    /// comparing a file with the output of other tools.
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> Result<()> {
+        Ok(csv::write_csv(self, w)?)
+    }
+
+    /// Write the file in the `midicsv` text format, see [`MidiFile::write_csv`], and return it as a
+    /// `String`.
+    pub fn to_csv_string(&self) -> Result<String> {
+        let mut bytes = Vec::new();
+        self.write_csv(&mut bytes)?;
+        // the csv writer escapes every byte that is not printable ascii, so nothing is lost
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
     }
 
     /// Save a `MidiFile` to a file path.
@@ -177,14 +509,8 @@ impl MidiFile {
             site: site!(),
             path,
         })?;
-        let w = BufWriter::new(file);
-        let mut scribe = Scribe::new(
-            w,
-            ScribeSettings {
-                running_status: self.running_status,
-            },
-        );
-        self.write(&mut scribe)
+        let mut w = BufWriter::new(file);
+        self.write(&mut w)
     }
 
     /// The number of tracks, i.e. the length of the vector of tracks.
@@ -197,6 +523,463 @@ impl MidiFile {
         self.tracks.iter()
     }
 
+    /// An iterator over the tracks of a [`Format::Sequential`] file, which are called patterns. Each
+    /// pattern is an independent sequence with its own timing that starts at tick zero, so tempo
+    /// and other meta events only apply to the pattern they are in. For this reason the helpers
+    /// that treat tracks as simultaneous, such as [`MidiFile::iter_events_timesorted`] and
+    /// [`MidiFile::conductor_summary`], are not meaningful for these files. This is the same as
+    /// [`MidiFile::tracks`].
+    pub fn patterns(&self) -> impl Iterator<Item = &Track> {
+        self.tracks()
+    }
+
+    /// A mutable iterator over the tracks in the file. Edits may leave a track with a misplaced or
+    /// missing `EndOfTrack` event. This is corrected when the file is written.
+    pub fn tracks_mut(&mut self) -> impl Iterator<Item = &mut Track> {
+        self.tracks.iter_mut()
+    }
+
+    /// An iterator over every event in the file as `(track_index, absolute_tick, event)`. The
+    /// absolute tick is the sum of the delta times that precede the event in its own track. Events
+    /// are yielded in track order, i.e. all events of the first track, then all events of the
+    /// second track, and so on. They are *not* sorted by time across tracks, see
+    /// [`MidiFile::iter_events_timesorted`] for that.
+    pub fn iter_events(&self) -> impl Iterator<Item = (u32, u64, &Event)> {
+        self.tracks.iter().enumerate().flat_map(|(ix, track)| {
+            let track_index = u32::try_from(ix).unwrap_or(u32::MAX);
+            let mut tick = 0u64;
+            track.events().map(move |track_event| {
+                tick += u64::from(track_event.delta_time());
+                (track_index, tick, track_event.event())
+            })
+        })
+    }
+
+    /// The same events as [`MidiFile::iter_events`], but merged across tracks and sorted by
+    /// absolute tick. Events that occur at the same tick are ordered by track index, and events at
+    /// the same tick in the same track keep their original order.
+    pub fn iter_events_timesorted(&self) -> Vec<(u32, u64, &Event)> {
+        let mut events: Vec<_> = self.iter_events().collect();
+        events.sort_by_key(|(track_index, tick, _)| (*tick, *track_index));
+        events
+    }
+
+    /// The distinct channels of all channel voice and channel mode messages in every track.
+    pub fn channels_used(&self) -> BTreeSet<Channel> {
+        self.tracks
+            .iter()
+            .flat_map(|track| track.channels_used())
+            .collect()
+    }
+
+    /// The program that is active on `channel` at `tick`, i.e. the program of the last
+    /// `ProgramChange` on that channel, in any track, at or before `tick`. Returns `None` if there is
+    /// no such program change.
+    pub fn program_at(&self, channel: Channel, tick: u64) -> Option<Program> {
+        self.iter_events_timesorted()
+            .into_iter()
+            .take_while(|(_, event_tick, _)| *event_tick <= tick)
+            .filter_map(|(_, _, event)| match event {
+                Event::Midi(Message::ProgramChange(p)) if *p.channel() == channel => {
+                    Some(*p.program())
+                }
+                _ => None,
+            })
+            .last()
+    }
+
+    /// The first program change of each track, with its channel, or `None` for a track that has no
+    /// program changes. This is usually the instrument that the track is played with, see
+    /// [`Track::set_program`].
+    pub fn track_programs(&self) -> Vec<Option<(Channel, Program)>> {
+        self.tracks()
+            .map(|track| {
+                track.events().find_map(|event| match event.event() {
+                    Event::Midi(Message::ProgramChange(p)) => Some((*p.channel(), *p.program())),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// The programs used on each channel, in the order that they are first used, without
+    /// duplicates.
+    pub fn programs_used(&self) -> BTreeMap<Channel, Vec<Program>> {
+        let mut used: BTreeMap<Channel, Vec<Program>> = BTreeMap::new();
+        for (_, _, event) in self.iter_events_timesorted() {
+            if let Event::Midi(Message::ProgramChange(p)) = event {
+                let programs = used.entry(*p.channel()).or_default();
+                if !programs.contains(p.program()) {
+                    programs.push(*p.program());
+                }
+            }
+        }
+        used
+    }
+
+    /// The greatest common divisor of every non-zero delta time in every track, i.e. the coarsest
+    /// grid of ticks that all of the events fall on. This can be much coarser than the declared
+    /// division, which is useful to know when re-quantizing. Returns `None` if every delta time is
+    /// zero.
+    pub fn effective_resolution(&self) -> Option<u32> {
+        self.tracks()
+            .flat_map(|track| track.events())
+            .map(|event| event.delta_time())
+            .filter(|&delta_time| delta_time != 0)
+            .fold(None, |resolution, delta_time| match resolution {
+                None => Some(delta_time),
+                Some(resolution) => Some(gcd(resolution, delta_time)),
+            })
+    }
+
+    /// Count the events of each type in every track, see [`EventHistogram`].
+    pub fn event_histogram(&self) -> EventHistogram {
+        EventHistogram::from_events(self.iter_events().map(|(_, _, event)| event))
+    }
+
+    /// Collect the tempo segments, time signatures, key signatures and SMPTE offset of the file,
+    /// from all tracks, into a single [`ConductorSummary`].
+    pub fn conductor_summary(&self) -> ConductorSummary {
+        ConductorSummary::from_sorted(
+            self.iter_events_timesorted()
+                .into_iter()
+                .map(|(_, tick, event)| (tick, event)),
+        )
+    }
+
+    /// The time signature changes from all tracks as `(absolute_tick, time_signature)`, sorted by
+    /// tick. If the file has no time signature at tick zero, then 4/4 is assumed until the first
+    /// one and is included at tick zero.
+    pub fn time_signature_map(&self) -> Vec<(u64, TimeSignatureValue)> {
+        let mut map = self.conductor_summary().time_signatures().to_vec();
+        if !matches!(map.first(), Some((0, _))) {
+            map.insert(0, (0, TimeSignatureValue::common_time()));
+        }
+        map
+    }
+
+    /// The key signature changes from all tracks as `(absolute_tick, key_signature)`, sorted by
+    /// tick. If the file has no key signature at tick zero, then C major is assumed until the first
+    /// one and is included at tick zero.
+    pub fn key_signature_map(&self) -> Vec<(u64, KeySignatureValue)> {
+        let mut map = self.conductor_summary().key_signatures().to_vec();
+        if !matches!(map.first(), Some((0, _))) {
+            map.insert(0, (0, KeySignatureValue::default()));
+        }
+        map
+    }
+
+    /// The text of the first `Copyright` event in the file, which should be the first event of the
+    /// first track.
+    pub fn copyright(&self) -> Option<Cow<'_, str>> {
+        self.iter_events().find_map(|(_, _, event)| match event {
+            Event::Meta(MetaEvent::Copyright(text)) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The `Marker` events from all tracks as `(absolute_tick, text)`, sorted by tick.
+    pub fn markers(&self) -> Vec<(u64, Cow<'_, str>)> {
+        self.texts_timesorted(|meta| match meta {
+            MetaEvent::Marker(text) => Some(text),
+            _ => None,
+        })
+    }
+
+    /// The `Lyric` events from all tracks as `(absolute_tick, text)`, sorted by tick, e.g. for
+    /// displaying the lyrics in time with the music.
+    pub fn lyrics(&self) -> Vec<(u64, Cow<'_, str>)> {
+        self.texts_timesorted(|meta| match meta {
+            MetaEvent::Lyric(text) => Some(text),
+            _ => None,
+        })
+    }
+
+    /// The length of the file in quarter notes, i.e. the length of its longest track in ticks divided
+    /// by the number of ticks per quarter note. Returns `None` if the division is SMPTE based.
+    pub fn beat_count(&self) -> Option<f64> {
+        let ppq = self.ppq()?;
+        Some(self.end_tick() as f64 / f64::from(ppq.max(1)))
+    }
+
+    /// The number of complete measures in the file, following the meters of
+    /// [`MidiFile::time_signature_map`] up to the end of its longest track. A measure that is cut
+    /// short by a meter change or by the end of the file is not counted. Returns `None` if the
+    /// division is SMPTE based.
+    pub fn measure_count(&self) -> Option<u32> {
+        let ppq = match *self.header.division() {
+            Division::QuarterNote(q) => q,
+            Division::Smpte(_) => return None,
+        };
+        let end = self.end_tick();
+        let map = self.time_signature_map();
+        let mut measures = 0u64;
+        for (ix, (start, time_signature)) in map.iter().enumerate() {
+            let stop = map.get(ix + 1).map_or(end, |(tick, _)| (*tick).min(end));
+            let beat = u64::from(time_signature.denominator().ticks(ppq));
+            let measure = (u64::from(time_signature.numerator()) * beat).max(1);
+            measures += stop.saturating_sub(*start) / measure;
+        }
+        Some(u32::try_from(measures).unwrap_or(u32::MAX))
+    }
+
+    /// Set the tempo at an absolute tick in the first track, which is the conductor track of a
+    /// [`Format::Multi`] file. A `SetTempo` event that is already in the first track at `tick` is
+    /// replaced, otherwise one is inserted without moving the other events, see
+    /// [`Track::insert_event_at_tick`]. If the file has no tracks, an empty one is added first.
+    pub fn set_tempo_at(
+        &mut self,
+        tick: u64,
+        quarters_per_minute: QuartersPerMinute,
+    ) -> Result<()> {
+        if self.tracks.is_empty() {
+            self.push_track(Track::default())?;
+        }
+        let track = &mut self.tracks[0];
+        let event = Event::Meta(MetaEvent::SetTempo(MicrosecondsPerQuarter::from(
+            quarters_per_minute,
+        )));
+        let mut event_tick = 0u64;
+        let mut existing = None;
+        for (ix, track_event) in track.events().enumerate() {
+            event_tick += u64::from(track_event.delta_time());
+            if event_tick > tick {
+                break;
+            }
+            if event_tick == tick
+                && matches!(track_event.event(), Event::Meta(MetaEvent::SetTempo(_)))
+            {
+                existing = Some((ix, track_event.delta_time()));
+            }
+        }
+        match existing {
+            Some((ix, delta_time)) => {
+                let ix = u32::try_from(ix).context(error::TrackTooLongSnafu { site: site!() })?;
+                track.replace_event(ix, delta_time, event)
+            }
+            None => track.insert_event_at_tick(tick, event),
+        }
+    }
+
+    /// Add a track with a note on every beat, from the start of the file to the end of its longest
+    /// track, following the meters of [`MidiFile::time_signature_map`]. A beat is the denominator of
+    /// the time signature, and the count restarts at each meter change. The note of each downbeat
+    /// is accented by raising `velocity` by a quarter, and each note lasts half a beat. Tempo
+    /// changes do not move the beats, since these are measured in ticks. Returns an error if the
+    /// division is SMPTE based or if the track cannot be added to the file.
+    pub fn add_click_track(
+        &mut self,
+        channel: Channel,
+        note: NoteNumber,
+        velocity: Velocity,
+    ) -> Result<()> {
+        let ppq = match *self.header.division() {
+            Division::QuarterNote(q) => q,
+            Division::Smpte(_) => error::OtherSnafu { site: site!() }.fail()?,
+        };
+        let end = self.end_tick();
+        let accent = Velocity::new(velocity.get().saturating_add(velocity.get() / 4));
+        let map = self.time_signature_map();
+        let mut events = Vec::new();
+        for (ix, (start, time_signature)) in map.iter().enumerate() {
+            let stop = map.get(ix + 1).map_or(end, |(tick, _)| (*tick).min(end));
+            let beat = u64::from(time_signature.denominator().ticks(ppq)).max(1);
+            let length = (beat / 2).max(1);
+            let numerator = u64::from(time_signature.numerator()).max(1);
+            let beats = (*start..stop).step_by(beat as usize);
+            for (tick, count) in beats.zip((0..numerator).cycle()) {
+                let note_velocity = if count == 0 { accent } else { velocity };
+                let note_on = NoteMessage::new(channel, note, note_velocity);
+                let note_off = NoteMessage::new(channel, note, Velocity::new(64));
+                events.push((tick, Event::Midi(Message::NoteOn(note_on))));
+                events.push((tick + length, Event::Midi(Message::NoteOff(note_off))));
+            }
+        }
+        let mut track = Track::from_absolute_events(events)?;
+        track.set_name("Click")?;
+        self.push_track(track)
+    }
+
+    /// Every note in the file, from all tracks, as a [`Note`] tagged with the index of its track.
+    /// Note-ons are paired with note-offs as in [`MidiFile::stuck_notes`], and a note that is never
+    /// turned off ends at the end of its track. The notes are sorted by start tick, then by note
+    /// number, then by track.
+    pub fn all_notes_sorted(&self) -> Vec<Note> {
+        file::all_notes_sorted(self)
+    }
+
+    /// Every note in the file with its start and end converted to milliseconds, using the tempo
+    /// changes from all tracks, or 120 quarters per minute before the first one. A note that spans a
+    /// tempo change is converted through each tempo in turn. A note that is never turned off ends
+    /// at the end of its track. The notes are sorted by start time, then by track.
+    pub fn note_events_ms(&self) -> Vec<NoteEventMs> {
+        file::note_events_ms(self)
+    }
+
+    /// Every channel message in the file, from all tracks, with its time converted to milliseconds
+    /// in the same way as [`MidiFile::note_events_ms`]. The messages are sorted by time, then by
+    /// track. Meta and sysex events are left out, so this is what would be sent to a MIDI output
+    /// port to play the file.
+    pub fn scheduled(&self) -> impl Iterator<Item = ScheduledEvent> {
+        file::scheduled(self).into_iter()
+    }
+
+    /// Check the file for common violations of the MIDI file spec, such as a misplaced `EndOfTrack`
+    /// or notes that are never turned off. Problems are reported rather than treated as errors, so
+    /// an empty `Vec` means that none were found.
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        file::validate(self)
+    }
+
+    /// Find the note-ons, in every track, that are never turned off by a note-off (or a note-on
+    /// with a velocity of zero).
+    pub fn stuck_notes(&self) -> Vec<StuckNote> {
+        file::stuck_notes(self)
+    }
+
+    /// Turn off every note found by [`MidiFile::stuck_notes`] by adding a note-off, with a velocity
+    /// of `64`, at the end of its track just before the `EndOfTrack` event.
+    pub fn fix_stuck_notes(&mut self) {
+        for stuck in self.stuck_notes() {
+            let track = &mut self.tracks[stuck.track() as usize];
+            let note_off = Event::Midi(Message::NoteOff(NoteMessage::new(
+                stuck.channel(),
+                stuck.note_number(),
+                Velocity::new(64),
+            )));
+            track.push_before_end_of_track(note_off);
+        }
+    }
+
+    /// Returns `true` if both files have the same header and the same decoded events in every
+    /// track. Unlike `==`, this ignores settings that only affect how the bytes are encoded, such as
+    /// whether running status is used.
+    pub fn semantically_equal(&self, other: &MidiFile) -> bool {
+        self.header == other.header && self.tracks == other.tracks
+    }
+
+    /// Compare the events of every track with those of `other`, reporting each event whose delta
+    /// time, kind or fields differ, along with events and tracks that are only in one of the files.
+    /// The headers are not compared. An empty `Vec` means that the tracks are the same.
+    pub fn diff(&self, other: &MidiFile) -> Vec<Difference> {
+        file::diff(self, other)
+    }
+
+    /// Remove the text meta events, i.e. `OtherText`, `Copyright`, `TrackName`, `InstrumentName`,
+    /// `Lyric`, `Marker`, `CuePoint`, `ProgramName` and `DeviceName`, along with `SequenceNumber`,
+    /// `Sequencer` and `Unknown` meta events. All other meta events are kept. The delta times of
+    /// removed events are merged into the events that follow them, so timing is preserved.
+    pub fn strip_non_essential_meta(&mut self) {
+        for track in self.tracks.iter_mut() {
+            track.retain_events(|event| match event {
+                Event::Meta(meta) => !matches!(
+                    meta,
+                    MetaEvent::OtherText(_)
+                        | MetaEvent::Copyright(_)
+                        | MetaEvent::TrackName(_)
+                        | MetaEvent::InstrumentName(_)
+                        | MetaEvent::Lyric(_)
+                        | MetaEvent::Marker(_)
+                        | MetaEvent::CuePoint(_)
+                        | MetaEvent::ProgramName(_)
+                        | MetaEvent::DeviceName(_)
+                        | MetaEvent::SequenceNumber
+                        | MetaEvent::Sequencer
+                        | MetaEvent::Unknown { .. }
+                ),
+                _ => true,
+            });
+        }
+    }
+
+    /// Remove each control change that sets a controller to the value it already has, that is, the
+    /// same value as the previous control change for that controller and channel in the same
+    /// track. The delta times of removed events are merged into the events that follow them, so
+    /// timing is preserved.
+    ///
+    /// Data Entry, Data Increment and Data Decrement are never removed, because they act on the
+    /// selected RPN or NRPN rather than holding a value of their own. The values known for a
+    /// channel are forgotten when a `ResetAllControllers` is sent on it, and when the selected RPN
+    /// or NRPN changes.
+    pub fn dedup_controllers(&mut self) {
+        for track in self.tracks.iter_mut() {
+            let mut values: HashMap<(Channel, Control), ControlValue> = HashMap::new();
+            track.retain_events(|event| match event {
+                Event::Midi(Message::Control(cc)) => match cc.control() {
+                    Control::DataEntryMsb
+                    | Control::DataEntryMsbLsb
+                    | Control::DataIncrement
+                    | Control::DataDecrement => true,
+                    control if is_parameter_select(control) => {
+                        let key = (cc.channel(), control);
+                        if values.get(&key) == Some(&cc.value()) {
+                            return false;
+                        }
+                        values.retain(|(channel, control), _| {
+                            *channel != cc.channel() || is_parameter_select(*control)
+                        });
+                        values.insert(key, cc.value());
+                        true
+                    }
+                    control => {
+                        values.insert((cc.channel(), control), cc.value()) != Some(cc.value())
+                    }
+                },
+                Event::Midi(Message::ResetAllControllers(channel)) => {
+                    values.retain(|(c, _), _| c != channel);
+                    true
+                }
+                _ => true,
+            });
+        }
+    }
+
+    /// Rewrite the note-offs in every track, see [`Track::normalize_note_offs`].
+    pub fn normalize_note_offs(&mut self, to_explicit: bool) {
+        for track in self.tracks.iter_mut() {
+            track.normalize_note_offs(to_explicit);
+        }
+    }
+
+    /// Remove the silence at the start of the file by moving every track earlier, so that the first
+    /// note-on, in any track, is at tick zero. Events that occur before the first note-on, such as
+    /// tempo and time signature changes, are moved to tick zero, and all later events keep their
+    /// positions relative to the first note-on. A file without notes is unchanged.
+    pub fn trim_leading_silence(&mut self) {
+        let first_note = self
+            .iter_events()
+            .filter(|(_, _, event)| {
+                matches!(event, Event::Midi(Message::NoteOn(m)) if m.velocity().get() > 0)
+            })
+            .map(|(_, tick, _)| tick)
+            .min();
+        if let Some(first_note) = first_note {
+            for track in self.tracks.iter_mut() {
+                track.trim_start(first_note);
+            }
+        }
+    }
+
+    /// Remove the silence at the end of the file by moving the `EndOfTrack` of every track to the
+    /// tick of the last note message, normally a note-off, in any track. A track that has other
+    /// events after that tick ends with its last event instead.
+    pub fn trim_trailing_silence(&mut self) {
+        let last_note_off = self
+            .iter_events()
+            .filter(|(_, _, event)| {
+                matches!(
+                    event,
+                    Event::Midi(Message::NoteOff(_)) | Event::Midi(Message::NoteOn(_))
+                )
+            })
+            .map(|(_, tick, _)| tick)
+            .max()
+            .unwrap_or(0);
+        for track in self.tracks.iter_mut() {
+            track.set_end_tick(last_note_off);
+        }
+    }
+
     /// Get a reference to the track at `index` if it exists.
     pub fn track(&self, index: u32) -> Option<&Track> {
         let i = match usize::try_from(index) {
@@ -206,6 +989,25 @@ impl MidiFile {
         self.tracks.get(i)
     }
 
+    /// Get a mutable reference to the track at `index` if it exists. Edits may leave the track with
+    /// a misplaced or missing `EndOfTrack` event. This is corrected when the file is written.
+    pub fn track_mut(&mut self, index: u32) -> Option<&mut Track> {
+        let i = match usize::try_from(index) {
+            Ok(ok) => ok,
+            Err(_) => return None,
+        };
+        self.tracks.get_mut(i)
+    }
+
+    /// Combine the tracks of `files`, in order, into a new [`Format::Multi`] file. The division,
+    /// running status and text encoding are taken from the first file. Tracks are copied unchanged,
+    /// so their names and programs are preserved. Returns an error if the files do not all have the
+    /// same division, because their delta times would not mean the same thing, or if there would be
+    /// more tracks than the 65535 that a file can hold.
+    pub fn merge(files: &[MidiFile]) -> Result<MidiFile> {
+        Ok(Self::merge_inner(files)?)
+    }
+
     /// Add a track to the file.
     pub fn push_track(&mut self, track: Track) -> Result<()> {
         ensure!(
@@ -215,6 +1017,10 @@ impl MidiFile {
         if *self.header().format() == Format::Single {
             ensure!(self.tracks_len() <= 1, error::OtherSnafu { site: site!() });
         }
+        let track = match self.default_tempo {
+            Some(tempo) if self.tracks.is_empty() => ensure_tempo(track, tempo)?,
+            _ => track,
+        };
         self.tracks.push(ensure_end_of_track(track)?);
         Ok(())
     }
@@ -249,28 +1055,288 @@ impl MidiFile {
         Ok(self.tracks.remove(i))
     }
 
-    fn read_inner<R: Read>(mut iter: ByteIter<R>) -> LibResult<Self> {
-        trace!("parsing header chunk");
-        iter.expect_tag("MThd").context(io!())?;
-        let chunk_length = iter.read_u32().context(io!())?;
-        // header chunk length is always 6
-        if chunk_length != 6 {
-            return error::OtherSnafu { site: site!() }.fail();
+    /// The length of the longest track in ticks.
+    fn end_tick(&self) -> u64 {
+        self.tracks
+            .iter()
+            .map(Track::duration_ticks)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn texts_timesorted<F>(&self, text_of: F) -> Vec<(u64, Cow<'_, str>)>
+    where
+        F: Fn(&MetaEvent) -> Option<&Text>,
+    {
+        self.iter_events_timesorted()
+            .into_iter()
+            .filter_map(|(_, tick, event)| match event {
+                Event::Meta(meta) => text_of(meta).map(|text| (tick, text.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn merge_inner(files: &[MidiFile]) -> LibResult<MidiFile> {
+        let mut merged = match files.first() {
+            Some(first) => MidiFile {
+                header: Header::new(Format::Multi, *first.header().division()),
+                tracks: Vec::new(),
+                running_status: first.running_status,
+                text_encoding: first.text_encoding,
+                default_tempo: None,
+                compress_note_offs: first.compress_note_offs,
+                track_alignment: first.track_alignment,
+            },
+            None => MidiFile::new_with_settings(Settings::new().format(Format::Multi)),
+        };
+        for file in files {
+            ensure!(
+                file.header().division() == merged.header().division(),
+                error::OtherSnafu { site: site!() }
+            );
+            merged.tracks.extend(file.tracks.iter().cloned());
+        }
+        ensure!(
+            u16::try_from(merged.tracks.len()).is_ok(),
+            error::TrackCountSnafu {
+                site: site!(),
+                count: merged.tracks.len()
+            }
+        );
+        Ok(merged)
+    }
+
+    fn write_inner<W: Write>(&self, w: &mut W, settings: ScribeSettings) -> LibResult<()> {
+        let ntracks = u16::try_from(self.tracks.len())
+            .context(error::TooManyTracksSnafu { site: site!() })?;
+        let mut scribe = Scribe::new(w, settings);
+        self.header.write(&mut scribe, ntracks)?;
+        for track in self.tracks() {
+            track.write(&mut scribe)?;
+        }
+        scribe.flush().context(wr!())?;
+        Ok(())
+    }
+
+    fn scribe_settings(&self) -> ScribeSettings {
+        ScribeSettings {
+            running_status: self.running_status,
+            text_encoding: self.text_encoding,
+            compress_note_offs: self.compress_note_offs,
+            track_alignment: self.track_alignment,
         }
-        let format_word = iter.read_u16().context(io!())?;
-        let num_tracks = iter.read_u16().context(io!())?;
-        let division_data = iter.read_u16().context(io!())?;
-        let format = Format::from_u16(format_word)?;
-        let header = Header::new(format, Division::from_u16(division_data)?);
+    }
+
+    #[cfg(feature = "rayon")]
+    fn read_parallel_inner(bytes: &[u8]) -> LibResult<Self> {
+        use rayon::prelude::*;
+        let mut iter = ByteIter::from_slice(bytes).context(io!())?;
+        let (header, num_tracks) = Header::parse(&mut iter)?;
+        let chunks = track_chunk_ranges(bytes, num_tracks)?;
+        // each track gets its own iterator so running status cannot leak between tracks
+        let parsed = chunks
+            .into_par_iter()
+            .map(|range| {
+                let mut iter = ByteIter::from_slice(&bytes[range]).context(io!())?;
+                let track = Track::parse(&mut iter)?;
+                Ok((track, iter.is_running_status_detected()))
+            })
+            .collect::<LibResult<Vec<_>>>()?;
+        let running_status = parsed.iter().any(|(_, detected)| *detected);
+        Ok(Self {
+            running_status,
+            text_encoding: TextEncoding::default(),
+            default_tempo: None,
+            compress_note_offs: false,
+            track_alignment: 0,
+            header,
+            tracks: parsed.into_iter().map(|(track, _)| track).collect(),
+        })
+    }
+
+    fn load_track_inner<S: ByteSource>(
+        mut iter: ByteIter<S>,
+        track_index: u32,
+    ) -> LibResult<Track> {
+        let (_, num_tracks) = Header::parse(&mut iter)?;
+        ensure!(
+            track_index < u32::from(num_tracks),
+            error::OtherSnafu { site: site!() }
+        );
+        for i in 0..track_index {
+            trace!("skipping track chunk {} (zero-based)", i);
+            Track::parse_chunk_tag(&mut iter)?;
+            let chunk_length = iter.read_u32().context(io!())?;
+            iter.skip(u64::from(chunk_length)).context(io!())?;
+        }
+        Track::parse(&mut iter)
+    }
+
+    fn read_lossy_inner(bytes: &[u8]) -> LibResult<(Self, Vec<TrackError>)> {
+        let mut iter = ByteIter::from_slice(bytes).context(io!())?;
+        let (header, num_tracks) = Header::parse(&mut iter)?;
+        let mut tracks = Vec::new();
+        let mut errors = Vec::new();
+        let mut running_status = false;
+        // the index of the track chunk whose length could not be used to find the next one
+        let mut lost = None;
+        for i in 0..num_tracks {
+            if let Some(lost) = lost {
+                let error = invalid_file_e!(
+                    "track chunk {} could not be found because track chunk {} is damaged",
+                    i,
+                    lost
+                );
+                errors.push(TrackError::new(u32::from(i), error));
+                continue;
+            }
+            let start = iter.position().map_or(0, |position| position as usize + 1);
+            match Track::parse(&mut iter) {
+                Ok(track) => tracks.push(track),
+                Err(e) => {
+                    debug!("unable to read track chunk {}: {}", i, e);
+                    errors.push(TrackError::new(u32::from(i), e));
+                    running_status |= iter.is_running_status_detected();
+                    match track_chunk_end(bytes, start) {
+                        Some(end) => {
+                            iter = ByteIter::from_slice(bytes).context(io!())?;
+                            iter.skip(end as u64).context(io!())?;
+                        }
+                        None => lost = Some(i),
+                    }
+                }
+            }
+        }
+        Ok((
+            Self {
+                running_status: running_status || iter.is_running_status_detected(),
+                text_encoding: TextEncoding::default(),
+                default_tempo: None,
+                compress_note_offs: false,
+                track_alignment: 0,
+                header,
+                tracks,
+            },
+            errors,
+        ))
+    }
+
+    fn read_inner<S: ByteSource>(mut iter: ByteIter<S>) -> LibResult<Self> {
+        let (header, num_tracks) = Header::parse(&mut iter)?;
         let mut tracks = Vec::new();
         for i in 0..num_tracks {
             trace!("parsing track chunk {} (zero-based) of {}", i, num_tracks);
+            if iter.peek().is_none() {
+                invalid_file!(
+                    "the header declares {} tracks but only {} were found",
+                    num_tracks,
+                    i
+                );
+            }
             tracks.push(Track::parse(&mut iter)?)
         }
+        if iter.peek().is_some() {
+            if !iter.settings().ignore_trailing_data {
+                invalid_file!("unexpected data after the last track chunk");
+            }
+            debug!("ignoring data after the last track chunk");
+        }
         Ok(Self {
             running_status: iter.is_running_status_detected(),
+            text_encoding: iter.settings().text_encoding,
+            default_tempo: None,
+            compress_note_offs: false,
+            track_alignment: 0,
             header,
             tracks,
         })
     }
 }
+
+/// Whether `control` selects the RPN or NRPN that Data Entry acts on.
+fn is_parameter_select(control: Control) -> bool {
+    matches!(
+        control,
+        Control::NonRegisteredParameterNumberLsb
+            | Control::NonRegisteredParameterNumberMsb
+            | Control::RegisteredParameterNumberLsb
+            | Control::RegisteredParameterNumberMsb
+    )
+}
+
+/// The greatest common divisor of `a` and `b` by Euclid's algorithm.
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+/// The end of the track chunk that starts at `start`, according to its length field. Returns `None`
+/// if there is no track chunk header at `start` or if the chunk runs past the end of `bytes`.
+fn track_chunk_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let prefix = bytes.get(start..start.checked_add(8)?)?;
+    if &prefix[..4] != b"MTrk" {
+        return None;
+    }
+    let length = u32::from_be_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]) as usize;
+    let end = (start + 8).checked_add(length)?;
+    if end > bytes.len() {
+        return None;
+    }
+    Some(end)
+}
+
+/// Scan the track chunk headers that follow the 14-byte header chunk and return the byte range of
+/// each track chunk, including its `MTrk` tag and length. As in [`Track::parse_chunk_tag`], unknown
+/// chunks are skipped, and as with the default [`Settings`], any data after the last track chunk is
+/// ignored.
+#[cfg(feature = "rayon")]
+fn track_chunk_ranges(bytes: &[u8], num_tracks: u16) -> LibResult<Vec<std::ops::Range<usize>>> {
+    const HEADER_CHUNK_LEN: usize = 14;
+    const CHUNK_PREFIX_LEN: usize = 8;
+    let mut ranges = Vec::with_capacity(usize::from(num_tracks));
+    let mut start = HEADER_CHUNK_LEN;
+    for i in 0..num_tracks {
+        if start >= bytes.len() {
+            invalid_file!(
+                "the header declares {} tracks but only {} were found",
+                num_tracks,
+                i
+            );
+        }
+        let (length, end) = loop {
+            let prefix = match bytes.get(start..start + CHUNK_PREFIX_LEN) {
+                Some(prefix) => prefix,
+                None => invalid_file!("track chunk {} is truncated", i),
+            };
+            let length = u32::from_be_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]) as usize;
+            let end = start + CHUNK_PREFIX_LEN + length;
+            if &prefix[..4] == b"MTrk" {
+                break (length, end);
+            }
+            if !prefix[..4].iter().all(u8::is_ascii_alphanumeric) {
+                invalid_file!("expected MTrk at byte {}", start);
+            }
+            debug!(
+                "skipping unknown chunk of {} bytes at byte {}",
+                length, start
+            );
+            start = end;
+        };
+        if end > bytes.len() {
+            invalid_file!(
+                "track chunk {} declares a length of {} bytes but only {} bytes are available",
+                i,
+                length,
+                bytes.len() - start - CHUNK_PREFIX_LEN
+            );
+        }
+        ranges.push(start..end);
+        start = end;
+    }
+    Ok(ranges)
+}
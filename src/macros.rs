@@ -32,13 +32,24 @@ macro_rules! clamp {
         }
 
         impl $symbol {
-            /// Silently clamps the value if it is out of range. See [`Self::set`].
+            /// Silently clamps the value if it is out of range. See [`Self::set`] and
+            /// [`Self::try_new`].
             #[allow(dead_code)]
             $visibility const fn new(value: $inner_type) -> Self {
                 let (clamped, _) = Self::clamp(value);
                 Self(clamped)
             }
 
+            /// Returns `None` if the value is out of range instead of clamping it. See
+            /// [`Self::new`].
+            #[allow(dead_code)]
+            $visibility const fn try_new(value: $inner_type) -> Option<Self> {
+                match Self::clamp(value) {
+                    (clamped, true) => Some(Self(clamped)),
+                    (_, false) => None,
+                }
+            }
+
             /// Returns the inner value.
             #[allow(dead_code)]
             $visibility fn get(&self) -> $inner_type {
@@ -98,4 +109,7 @@ fn clamp_test() {
     assert_eq!(1, foo_val);
     let fmted = format!("{}", Foo::new(6));
     assert_eq!("6", fmted.as_str());
+    assert_eq!(Some(Foo::new(16)), Foo::try_new(16));
+    assert_eq!(None, Foo::try_new(17));
+    assert_eq!(None, Foo::try_new(0));
 }
@@ -0,0 +1,62 @@
+//! The `rmid` module reads and writes the RIFF MIDI (`.rmi`) container, which is a RIFF file of
+//! type `RMID` with a standard MIDI file as its `data` chunk. Other chunks, such as `LIST` (with
+//! `INFO`) and `DISP`, are skipped when reading and are not written.
+
+use crate::byte_iter::{ByteIter, ByteSource};
+use crate::error::{self, LibResult};
+use log::debug;
+use snafu::{OptionExt, ResultExt};
+use std::convert::TryFrom;
+use std::io::Write;
+
+/// Read the RIFF header and return the contents of the `data` chunk.
+pub(crate) fn read_data_chunk<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<Vec<u8>> {
+    iter.expect_tag("RIFF").context(io!())?;
+    let _riff_size = read_u32_le(iter)?;
+    iter.expect_tag("RMID").context(io!())?;
+    loop {
+        if iter.peek().is_none() {
+            invalid_file!("the RMID file does not have a data chunk");
+        }
+        let id = iter.read4().context(io!())?;
+        let size = read_u32_le(iter)?;
+        if &id == b"data" {
+            return iter.read_n(size as usize).context(io!());
+        }
+        debug!("skipping RIFF chunk '{}'", String::from_utf8_lossy(&id));
+        // chunks are padded to an even length
+        let padded_size = u64::from(size) + u64::from(size % 2);
+        for _ in 0..padded_size {
+            iter.read_or_die().context(io!())?;
+        }
+    }
+}
+
+/// Write the RIFF header followed by `data`, which should be a standard MIDI file, as the `data`
+/// chunk.
+pub(crate) fn write_rmid<W: Write>(data: &[u8], w: &mut W) -> LibResult<()> {
+    let data_size = u32::try_from(data.len())
+        .ok()
+        .context(error::OtherSnafu { site: site!() })?;
+    let padding = data_size % 2;
+    // the RIFF size counts the form type ('RMID') and the data chunk with its header
+    let riff_size = 4u32
+        .checked_add(8)
+        .and_then(|size| size.checked_add(data_size))
+        .and_then(|size| size.checked_add(padding))
+        .context(error::OtherSnafu { site: site!() })?;
+    w.write_all(b"RIFF").context(wr!())?;
+    w.write_all(&riff_size.to_le_bytes()).context(wr!())?;
+    w.write_all(b"RMIDdata").context(wr!())?;
+    w.write_all(&data_size.to_le_bytes()).context(wr!())?;
+    w.write_all(data).context(wr!())?;
+    if padding == 1 {
+        w.write_all(&[0]).context(wr!())?;
+    }
+    w.flush().context(wr!())?;
+    Ok(())
+}
+
+fn read_u32_le<S: ByteSource>(iter: &mut ByteIter<S>) -> LibResult<u32> {
+    Ok(u32::from_le_bytes(iter.read4().context(io!())?))
+}
@@ -1,10 +1,14 @@
 use crate::error::LibResult;
+use crate::text::TextEncoding;
 use snafu::ResultExt;
 use std::io::Write;
 
 #[derive(Copy, Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
 pub(crate) struct ScribeSettings {
     pub(crate) running_status: bool,
+    pub(crate) text_encoding: TextEncoding,
+    pub(crate) compress_note_offs: bool,
+    pub(crate) track_alignment: u32,
 }
 
 /// A wrapper for any `Write`, which provides a setting for running status, and allows for the
@@ -13,6 +17,7 @@ pub(crate) struct Scribe<W: Write> {
     w: W,
     settings: ScribeSettings,
     running_status_byte: Option<u8>,
+    explicit_status: Option<bool>,
 }
 
 impl<W: Write> Write for Scribe<W> {
@@ -32,13 +37,20 @@ impl<W: Write> Scribe<W> {
             w,
             settings,
             running_status_byte: None,
+            explicit_status: None,
         }
     }
 
     /// Write a status byte. If `running_status` is `true`, and the `status` byte is the same as
-    /// `previous_status`, then nothing happens.
+    /// `previous_status`, then nothing happens. A layout recorded with `set_explicit_status` takes
+    /// precedence over the `running_status` setting.
     pub(crate) fn write_status_byte(&mut self, status: u8) -> LibResult<()> {
-        match self.running_status() {
+        let previous_status = match self.explicit_status {
+            Some(true) => None,
+            Some(false) => self.running_status_byte,
+            None => self.running_status(),
+        };
+        match previous_status {
             Some(previous_status) if previous_status == status => Ok(()),
             _ => {
                 write_u8!(self.w, status)?;
@@ -58,12 +70,37 @@ impl<W: Write> Scribe<W> {
         }
     }
 
-    /// If the `running_status` setting is true, sets the `running_status_byte`, otherwise does
-    /// nothing.
+    /// Remember `value` as the most recent status byte. It is only used for running status when
+    /// the `running_status` setting is true, or when the next message was read with running status.
     pub(crate) fn set_running_status(&mut self, value: u8) {
-        if self.use_running_status() {
-            self.running_status_byte = Some(value)
-        }
+        self.running_status_byte = Some(value)
+    }
+
+    /// Set whether the next message was read with its own status byte, see
+    /// [`crate::file::TrackEvent::explicit_status`].
+    pub(crate) fn set_explicit_status(&mut self, value: Option<bool>) {
+        self.explicit_status = value;
+    }
+
+    /// A getter for the `settings` field.
+    pub(crate) fn settings(&self) -> ScribeSettings {
+        self.settings
+    }
+
+    /// Returns `true` if note-offs with a velocity of zero should be written as note-ons.
+    pub(crate) fn compress_note_offs(&self) -> bool {
+        self.settings.compress_note_offs
+    }
+
+    /// Returns the multiple of bytes that the data of each track chunk should be padded to, where
+    /// `0` and `1` mean no padding.
+    pub(crate) fn track_alignment(&self) -> u32 {
+        self.settings.track_alignment
+    }
+
+    /// Returns the encoding that text should be written with.
+    pub(crate) fn text_encoding(&self) -> TextEncoding {
+        self.settings.text_encoding
     }
 
     /// Returns true if the settings are set to use `running_status`.
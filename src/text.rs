@@ -3,10 +3,30 @@
 //! that holds a `UTF-8` `String` whenever possible, but reverts to holding raw bytes when the bytes
 //! are not valid `UTF-8`.
 
+use crate::core::vlq::MAX_VLQ_VALUE;
+use crate::error::{self, LibResult};
 use log::warn;
+use snafu::ensure;
 use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
 
+/// The maximum length, in bytes, of the text in a meta event. The length is written as a variable
+/// length quantity, which can be at most `0x0FFFFFFF`.
+pub const MAX_TEXT_LEN: usize = MAX_VLQ_VALUE as usize;
+
+/// The encoding used to decode and encode the bytes of text meta events, see
+/// [`crate::Settings::text_encoding`].
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub enum TextEncoding {
+    /// Text is decoded as UTF-8 when it is valid, otherwise the raw bytes are kept.
+    #[default]
+    Utf8,
+    /// Text is decoded as ISO-8859-1, where each byte is a single character. This is common in
+    /// older files. When writing, characters that cannot be represented are written as `?`.
+    Latin1,
+}
+
 /// The MIDI spec does not state what encoding should be used for strings. Since Rust strings are
 /// UTF-8 encoded, we try to parse text as a `String` and hope for the best. But if we get an error
 /// then we store the original bytes to facilitate lossless parsing.
@@ -73,6 +93,33 @@ impl Text {
         Text::Utf8(s.into())
     }
 
+    /// Create a new `Text` object, returning an error if it is longer than [`MAX_TEXT_LEN`] bytes
+    /// and thus cannot be written.
+    pub fn try_new<S: Into<String>>(s: S) -> crate::Result<Self> {
+        Ok(Self::try_new_inner(s.into())?)
+    }
+
+    fn try_new_inner(s: String) -> LibResult<Self> {
+        ensure!(
+            s.len() <= MAX_TEXT_LEN,
+            error::TextTooLongSnafu {
+                site: site!(),
+                len: s.len()
+            }
+        );
+        Ok(Text::Utf8(s))
+    }
+
+    /// The length of the text in bytes, not chars.
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    /// Returns `true` if the text has a length of zero.
+    pub fn is_empty(&self) -> bool {
+        self.as_bytes().is_empty()
+    }
+
     /// Get the exact bytes of the text.
     pub fn as_bytes(&self) -> &[u8] {
         match self {
@@ -81,6 +128,32 @@ impl Text {
         }
     }
 
+    /// Decode the `bytes` of a text meta event using `encoding`.
+    pub(crate) fn decode(bytes: Vec<u8>, encoding: TextEncoding) -> Self {
+        match encoding {
+            TextEncoding::Utf8 => bytes.into(),
+            TextEncoding::Latin1 => Text::Utf8(bytes.into_iter().map(char::from).collect()),
+        }
+    }
+
+    /// Get the bytes to write for the text using `encoding`. Raw bytes are always written as-is.
+    pub(crate) fn encode(&self, encoding: TextEncoding) -> Cow<'_, [u8]> {
+        match (self, encoding) {
+            (Text::Utf8(s), TextEncoding::Latin1) if !s.is_ascii() => Cow::Owned(
+                s.chars()
+                    .map(|c| match u8::try_from(u32::from(c)) {
+                        Ok(byte) => byte,
+                        Err(_) => {
+                            warn!("'{}' cannot be encoded as Latin-1", c);
+                            b'?'
+                        }
+                    })
+                    .collect(),
+            ),
+            _ => Cow::Borrowed(self.as_bytes()),
+        }
+    }
+
     /// Get a UTF-8 representation of the string (lossy if non UTF-8-encoded).
     pub fn as_str(&self) -> Cow<'_, str> {
         match self {
@@ -89,3 +162,20 @@ impl Text {
         }
     }
 }
+
+#[test]
+fn text_empty_test() {
+    let text = Text::default();
+    assert!(text.is_empty());
+    assert_eq!(text.len(), 0);
+    assert!(Text::try_new("").unwrap().is_empty());
+    assert_eq!(MAX_TEXT_LEN, 0x0FFF_FFFF);
+}
+
+#[test]
+fn text_len_test() {
+    let text = Text::try_new("Café").unwrap();
+    assert!(!text.is_empty());
+    assert_eq!(text.len(), 5);
+    assert_eq!(Text::Other(vec![0x43, 0x61, 0x66, 0xE9]).len(), 4);
+}
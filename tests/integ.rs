@@ -1,13 +1,20 @@
 mod utils;
 
 use crate::utils::{PITCH_BEND, PITCH_BEND_TWO_BYTES};
-use midi_file::core::{Clocks, Control, DurationName, Message};
-use midi_file::file::{Division, Event, Format, MetaEvent, QuarterNoteDivision};
-use midi_file::MidiFile;
+use midi_file::core::{
+    Channel, Clocks, Control, ControlValue, DurationName, GeneralMidi, Message, NoteMessage,
+    NoteNumber, PitchBendMessage, PitchBendValue, Program, ProgramChangeValue, Velocity,
+};
+use midi_file::file::{
+    Division, Event, Format, KeyMode, MetaEvent, QuarterNoteDivision, QuartersPerMinute, Track,
+    TrackBuilder, TrackEvent, ValidationWarningKind,
+};
+use midi_file::{ErrorKind, MidiFile, Settings, Text, TextEncoding};
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::Read;
 use tempfile::tempdir;
-use utils::{enable_logging, test_file, AVE_MARIS_STELLA};
+use utils::{enable_logging, test_file, ALS_DIE_ROEMER, AVE_MARIS_STELLA};
 
 #[test]
 fn ave_maris_stella_finale_export() {
@@ -263,3 +270,1817 @@ fn pitch_bend_two_byte() {
     assert_pitch_bend(track.events().nth(7).unwrap().event(), 0);
     assert_pitch_bend(track.events().nth(8).unwrap().event(), 1);
 }
+
+#[test]
+fn iter_events() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let events: Vec<_> = midi_file.iter_events().collect();
+    assert_eq!(29 + 230, events.len());
+
+    // events come in track order with a per-track running tick
+    assert_eq!(0, events[0].0);
+    assert_eq!(0, events[0].1);
+    assert_eq!(1, events[29].0);
+    assert_eq!(0, events[29].1);
+    for pair in events.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            assert!(pair[0].1 <= pair[1].1);
+        }
+    }
+    let last_tick_track_1: u64 = midi_file
+        .track(1)
+        .unwrap()
+        .events()
+        .map(|e| u64::from(e.delta_time()))
+        .sum();
+    assert_eq!(last_tick_track_1, events.last().unwrap().1);
+
+    let sorted = midi_file.iter_events_timesorted();
+    assert_eq!(events.len(), sorted.len());
+    for pair in sorted.windows(2) {
+        assert!((pair[0].1, pair[0].0) <= (pair[1].1, pair[1].0));
+    }
+}
+
+#[test]
+fn conductor_summary() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let summary = midi_file.conductor_summary();
+    assert!(summary.smpte_offset().is_some());
+
+    let time_signatures = summary.time_signatures();
+    assert_eq!(6, time_signatures.len());
+    assert_eq!(0, time_signatures[0].0);
+    assert_eq!(4, time_signatures[0].1.numerator());
+    assert_eq!(4096, time_signatures[1].0);
+    assert_eq!(DurationName::Eighth, time_signatures[1].1.denominator());
+
+    assert_eq!(1, summary.key_signatures().len());
+
+    // the file has two tempos at tick 0, the second replaces the first
+    let tempos = summary.tempo_segments();
+    assert_eq!(19, tempos.len());
+    assert_eq!(0, tempos[0].start());
+    assert_eq!(652233, tempos[0].tempo().get());
+    assert_eq!(Some(5376), tempos[0].end());
+    assert!(tempos
+        .iter()
+        .all(|segment| segment.end() != Some(segment.start())));
+    assert_eq!(13568, tempos.last().unwrap().start());
+    assert_eq!(None, tempos.last().unwrap().end());
+
+    let conductor = TrackBuilder::new()
+        .tempo(0, QuartersPerMinute::new(100))
+        .tempo(480, QuartersPerMinute::new(120))
+        .tempo(0, QuartersPerMinute::new(150))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(conductor).unwrap();
+    let tempos: Vec<(u64, Option<u64>, u32)> = midi_file
+        .conductor_summary()
+        .tempo_segments()
+        .iter()
+        .map(|segment| (segment.start(), segment.end(), segment.tempo().get()))
+        .collect();
+    assert_eq!(tempos, vec![(0, Some(480), 600_000), (480, None, 400_000)]);
+}
+
+#[test]
+fn write_csv() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let mut bytes = Vec::new();
+    midi_file.write_csv(&mut bytes).unwrap();
+    let csv = String::from_utf8(bytes).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!("0, 0, Header, 1, 2, 1024", lines[0]);
+    assert_eq!("1, 0, Start_track", lines[1]);
+    assert_eq!("1, 0, SMPTE_offset, 0, 0, 0, 0, 0", lines[2]);
+    assert_eq!("1, 0, Time_signature, 4, 2, 24, 8", lines[3]);
+    assert_eq!("1, 0, Key_signature, 0, \"major\"", lines[4]);
+    assert_eq!("1, 0, Tempo, 652176", lines[5]);
+    assert!(lines.contains(&"1, 4096, Time_signature, 5, 3, 12, 8"));
+    assert!(lines.contains(&"1, 34305, End_track"));
+    assert!(lines.contains(&"2, 0, Title_t, \"[Staff 1]\""));
+    assert!(lines.contains(&"2, 0, Program_c, 0, 0"));
+    assert_eq!("0, 0, End_of_file", *lines.last().unwrap());
+    // one line per event, plus header, end of file, and a start line per track
+    assert_eq!(29 + 230 + 2 + 2, lines.len());
+}
+
+#[test]
+fn to_csv_string() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let mut bytes = Vec::new();
+    midi_file.write_csv(&mut bytes).unwrap();
+    let csv = midi_file.to_csv_string().unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), csv);
+    assert!(csv.starts_with("0, 0, Header, 1, 2, 1024\n"));
+}
+
+#[test]
+fn stream_events() {
+    enable_logging();
+    for filename in [AVE_MARIS_STELLA, PITCH_BEND_TWO_BYTES] {
+        let midi_file = MidiFile::load(test_file(filename)).unwrap();
+        let stream = MidiFile::stream_events(File::open(test_file(filename)).unwrap()).unwrap();
+        assert_eq!(midi_file.header(), stream.header());
+        let streamed: Vec<(u32, TrackEvent)> = stream.map(|result| result.unwrap()).collect();
+        let eager: Vec<(u32, TrackEvent)> = midi_file
+            .tracks()
+            .enumerate()
+            .flat_map(|(ix, track)| track.events().map(move |e| (ix as u32, e.clone())))
+            .collect();
+        assert_eq!(eager.len(), streamed.len());
+        assert_eq!(eager, streamed);
+    }
+}
+
+#[test]
+fn from_bytes() {
+    enable_logging();
+    let bytes = std::fs::read(test_file(AVE_MARIS_STELLA)).unwrap();
+    let from_bytes = MidiFile::from_bytes(&bytes).unwrap();
+    let from_read = MidiFile::read(bytes.as_slice()).unwrap();
+    let loaded = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    assert_eq!(from_read, from_bytes);
+    assert_eq!(loaded, from_bytes);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn read_parallel() {
+    enable_logging();
+    for filename in [
+        AVE_MARIS_STELLA,
+        utils::ADESTE_FIDELES,
+        PITCH_BEND_TWO_BYTES,
+    ] {
+        let sequential = MidiFile::load(test_file(filename)).unwrap();
+        let parallel = MidiFile::load_parallel(test_file(filename)).unwrap();
+        assert_eq!(sequential, parallel);
+        let bytes = std::fs::read(test_file(filename)).unwrap();
+        let truncated = MidiFile::read_parallel(&bytes[..bytes.len() - 1]);
+        assert!(truncated.is_err());
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn load_mmap() {
+    enable_logging();
+    let loaded = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let mapped = MidiFile::load_mmap(test_file(AVE_MARIS_STELLA)).unwrap();
+    assert_eq!(loaded, mapped);
+    let dir = tempdir().unwrap();
+    let error = MidiFile::load_mmap(dir.path().join("missing.mid")).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Io);
+}
+
+#[test]
+fn unknown_meta_event_round_trip() {
+    enable_logging();
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 0, 1 track, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x60,
+        // track chunk, 11 bytes
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x0B,
+        // a made-up meta event type 0x60 with 3 bytes of data
+        0x00, 0xFF, 0x60, 0x03, 0x01, 0x02, 0x03,
+        // end of track
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let midi_file = MidiFile::from_bytes(&bytes).unwrap();
+    let event = midi_file.tracks().next().unwrap().events().next().unwrap();
+    assert_eq!(
+        *event.event(),
+        Event::Meta(MetaEvent::Unknown {
+            meta_type: 0x60,
+            data: vec![0x01, 0x02, 0x03],
+        })
+    );
+    let mut written = Vec::new();
+    midi_file.write(&mut written).unwrap();
+    assert_eq!(bytes, written);
+}
+
+#[test]
+fn error_kind_and_position() {
+    enable_logging();
+    let bytes = std::fs::read(test_file(AVE_MARIS_STELLA)).unwrap();
+    let truncated = &bytes[..1234];
+    let error = MidiFile::from_bytes(truncated).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidFile);
+    let position = error.byte_position().unwrap();
+    assert!(position > 1200 && position <= 1234, "position {}", position);
+
+    let dir = tempdir().unwrap();
+    let error = MidiFile::load(dir.path().join("missing.mid"))
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), ErrorKind::Io);
+    assert!(error.byte_position().is_none());
+}
+
+#[test]
+fn repair_missing_end_of_track() {
+    enable_logging();
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 0, 1 track, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x60,
+        // track chunk, 8 bytes, without an end of track event
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x08,
+        // note on, then note off after a quarter
+        0x00, 0x90, 0x3C, 0x40,
+        0x60, 0x80, 0x3C, 0x00,
+    ];
+    assert!(MidiFile::read(bytes.as_slice()).is_err());
+    let settings = Settings::new().repair_missing_end_of_track(true);
+    let midi_file = MidiFile::read_with_settings(bytes.as_slice(), settings).unwrap();
+    let track = midi_file.tracks().next().unwrap();
+    assert_eq!(track.events_len(), 3);
+    let last = track.events().last().unwrap();
+    assert_eq!(*last.event(), Event::Meta(MetaEvent::EndOfTrack));
+    assert_eq!(last.delta_time(), 0);
+}
+
+#[test]
+fn empty_track_chunk() {
+    enable_logging();
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 1, 2 tracks, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x02, 0x00, 0x60,
+        // track chunk with a length of zero
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x00,
+        // track chunk with only an end of track event
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x04, 0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let error = MidiFile::read(bytes.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidFile);
+    assert!(error.to_string().contains("empty track chunk"));
+    let settings = Settings::new().allow_empty_tracks(true);
+    let midi_file = MidiFile::read_with_settings(bytes.as_slice(), settings).unwrap();
+    assert_eq!(midi_file.tracks_len(), 2);
+    for track in midi_file.tracks() {
+        assert_eq!(track.events_len(), 1);
+        assert_eq!(
+            *track.events().next().unwrap().event(),
+            Event::Meta(MetaEvent::EndOfTrack)
+        );
+    }
+}
+
+#[test]
+fn zero_tracks() {
+    enable_logging();
+    // header chunk: format 1, 0 tracks, 96 ticks per quarter
+    let bytes = [
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x60,
+    ];
+    let midi_file = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(midi_file.tracks_len(), 0);
+    assert_eq!(midi_file.ppq(), Some(96));
+    let mut written = Vec::new();
+    midi_file.write(&mut written).unwrap();
+    assert_eq!(written, bytes);
+    assert_eq!(MidiFile::stream_events(&bytes[..]).unwrap().count(), 0);
+}
+
+#[test]
+fn trailing_data_and_missing_tracks() {
+    enable_logging();
+    let mut bytes = std::fs::read(test_file(AVE_MARIS_STELLA)).unwrap();
+    let expected = MidiFile::from_bytes(&bytes).unwrap();
+
+    // the header claims one more track than the file contains
+    let mut too_few = bytes.clone();
+    too_few[11] += 1;
+    let message = MidiFile::from_bytes(&too_few).err().unwrap().to_string();
+    assert!(message.contains("declares 3 tracks but only 2 were found"));
+
+    bytes.extend_from_slice(&[0x00; 16]);
+    let padded = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(expected, padded);
+    let padded = MidiFile::read(bytes.as_slice()).unwrap();
+    assert_eq!(expected, padded);
+    let settings = Settings::new().ignore_trailing_data(false);
+    assert!(MidiFile::read_with_settings(bytes.as_slice(), settings).is_err());
+}
+
+#[test]
+fn read_embedded() {
+    enable_logging();
+    let bytes = std::fs::read(test_file(AVE_MARIS_STELLA)).unwrap();
+    let mut stream = bytes.clone();
+    stream.extend_from_slice(&[0xAB; 1024]);
+    let mut cursor = std::io::Cursor::new(stream);
+    let midi_file = MidiFile::read(&mut cursor).unwrap();
+    assert_eq!(midi_file, MidiFile::from_bytes(&bytes).unwrap());
+    // the reader is not buffered, so only a few bytes after the file are consumed
+    let position = cursor.position() as usize;
+    assert!(position >= bytes.len() && position <= bytes.len() + 3);
+}
+
+#[test]
+fn unknown_chunks() {
+    enable_logging();
+    let bytes = std::fs::read(test_file(AVE_MARIS_STELLA)).unwrap();
+    let expected = MidiFile::from_bytes(&bytes).unwrap();
+    // an unknown chunk between the header and the first track, and another at the end
+    let alien = [
+        b'X', b'F', b'I', b'H', 0x00, 0x00, 0x00, 0x03, 0x01, 0x02, 0x03,
+    ];
+    let mut with_chunks = bytes[..14].to_vec();
+    with_chunks.extend_from_slice(&alien);
+    with_chunks.extend_from_slice(&bytes[14..]);
+    with_chunks.extend_from_slice(&alien);
+    assert_eq!(MidiFile::from_bytes(&with_chunks).unwrap(), expected);
+    assert_eq!(MidiFile::read(with_chunks.as_slice()).unwrap(), expected);
+    let tempdir = tempdir().unwrap();
+    let path = tempdir.path().join("file.mid");
+    std::fs::write(&path, &with_chunks).unwrap();
+    assert_eq!(
+        MidiFile::load_track(&path, 1).unwrap(),
+        *expected.track(1).unwrap()
+    );
+    #[cfg(feature = "rayon")]
+    assert_eq!(MidiFile::read_parallel(&with_chunks).unwrap(), expected);
+    let settings = Settings::new().ignore_trailing_data(false);
+    assert!(MidiFile::read_with_settings(with_chunks.as_slice(), settings).is_err());
+}
+
+#[test]
+fn validate() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    assert!(midi_file.validate().is_empty());
+
+    let mut track = Track::default();
+    track.set_name("name").unwrap();
+    track
+        .push_note_on(0, Channel::new(0), NoteNumber::new(60), Velocity::new(64))
+        .unwrap();
+    track
+        .push_event(0, Event::Meta(MetaEvent::EndOfTrack))
+        .unwrap();
+    track
+        .push_note_off(96, Channel::new(0), NoteNumber::new(60), Velocity::new(0))
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    let warnings = midi_file.validate();
+    assert_eq!(warnings.len(), 1);
+    let warning = warnings.first().unwrap();
+    assert_eq!(warning.kind(), ValidationWarningKind::EndOfTrackNotLast);
+    assert_eq!(warning.track(), 0);
+    assert_eq!(warning.event(), 2);
+}
+
+#[test]
+fn latin1_text_encoding() {
+    enable_logging();
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 0, 1 track, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x60,
+        // track chunk, 12 bytes
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x0C,
+        // track name "Café" in Latin-1
+        0x00, 0xFF, 0x03, 0x04, 0x43, 0x61, 0x66, 0xE9,
+        // end of track
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let name = |midi_file: &MidiFile| match midi_file.tracks().next().unwrap().events().next() {
+        Some(event) => match event.event() {
+            Event::Meta(MetaEvent::TrackName(text)) => text.clone(),
+            _ => panic!("expected a track name"),
+        },
+        None => panic!("expected an event"),
+    };
+
+    let utf8 = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(name(&utf8), Text::Other(vec![0x43, 0x61, 0x66, 0xE9]));
+
+    let settings = Settings::new().text_encoding(TextEncoding::Latin1);
+    let latin1 = MidiFile::read_with_settings(bytes.as_slice(), settings).unwrap();
+    assert_eq!(latin1.text_encoding(), TextEncoding::Latin1);
+    assert_eq!(name(&latin1), Text::new("Café"));
+    let mut written = Vec::new();
+    latin1.write(&mut written).unwrap();
+    assert_eq!(bytes, written);
+}
+
+#[test]
+fn track_builder() {
+    enable_logging();
+    const QUARTER: u32 = 1024;
+    const EIGHTH: u32 = QUARTER / 2;
+    const DOTTED_QUARTER: u32 = QUARTER + EIGHTH;
+    const C4: NoteNumber = NoteNumber::new(72);
+    const D4: NoteNumber = NoteNumber::new(74);
+    const E4: NoteNumber = NoteNumber::new(76);
+    const V: Velocity = Velocity::new(64);
+    const CH: Channel = Channel::new(0);
+
+    // the first track of examples/main.rs
+    let mut track = Track::default();
+    track.set_name("Singer").unwrap();
+    track.set_instrument_name("Alto").unwrap();
+    track.set_general_midi(CH, GeneralMidi::SynthVoice).unwrap();
+    track
+        .push_time_signature(0, 6, DurationName::Eighth, Clocks::DottedQuarter)
+        .unwrap();
+    track.push_tempo(0, QuartersPerMinute::new(116)).unwrap();
+    track.push_lyric(0, "Row").unwrap();
+    track.push_note_on(0, CH, C4, V).unwrap();
+    track
+        .push_note_off(DOTTED_QUARTER, CH, C4, Velocity::default())
+        .unwrap();
+    track.push_lyric(0, "row").unwrap();
+    track.push_note_on(0, CH, C4, V).unwrap();
+    track.push_note_off(DOTTED_QUARTER, CH, C4, V).unwrap();
+    track.push_lyric(0, "row").unwrap();
+    track.push_note_on(0, CH, C4, V).unwrap();
+    track.push_note_off(QUARTER, CH, C4, V).unwrap();
+    track.push_lyric(0, "your").unwrap();
+    track.push_note_on(0, CH, D4, V).unwrap();
+    track.push_note_off(EIGHTH, CH, D4, V).unwrap();
+    track.push_lyric(0, "boat").unwrap();
+    track.push_note_on(0, CH, E4, V).unwrap();
+    track.push_note_off(DOTTED_QUARTER, CH, E4, V).unwrap();
+    let mut expected = MidiFile::new();
+    expected.push_track(track).unwrap();
+
+    let track = TrackBuilder::new()
+        .name("Singer")
+        .instrument_name("Alto")
+        .general_midi(CH, GeneralMidi::SynthVoice)
+        .time_signature(0, 6, DurationName::Eighth, Clocks::DottedQuarter)
+        .tempo(0, QuartersPerMinute::new(116))
+        .lyric(0, "Row")
+        .note_on(0, CH, C4, V)
+        .note_off(DOTTED_QUARTER, CH, C4, Velocity::default())
+        .lyric(0, "row")
+        .note(0, CH, C4, V, DOTTED_QUARTER)
+        .lyric(0, "row")
+        .note(0, CH, C4, V, QUARTER)
+        .lyric(0, "your")
+        .note(0, CH, D4, V, EIGHTH)
+        .lyric(0, "boat")
+        .note(0, CH, E4, V, DOTTED_QUARTER)
+        .build()
+        .unwrap();
+    let mut actual = MidiFile::new();
+    actual.push_track(track).unwrap();
+
+    let mut expected_bytes = Vec::new();
+    expected.write(&mut expected_bytes).unwrap();
+    let mut actual_bytes = Vec::new();
+    actual.write(&mut actual_bytes).unwrap();
+    assert_eq!(expected_bytes.len(), 144);
+    assert_eq!(expected_bytes, actual_bytes);
+
+    // errors are held until build
+    let result = TrackBuilder::new()
+        .time_signature(0, 0, DurationName::Quarter, Clocks::Quarter)
+        .lyric(0, "a")
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn index_track_events() {
+    enable_logging();
+    let ch = Channel::new(0);
+    let mut track = TrackBuilder::new()
+        .note(0, ch, NoteNumber::new(60), Velocity::new(64), 96)
+        .build()
+        .unwrap();
+    assert_eq!(track[1].delta_time(), 96);
+    track[1].set_delta_time(192);
+    let mut midi_file = MidiFile::new_with_settings(Settings::new().format(Format::Single));
+    midi_file.push_track(track).unwrap();
+    let mut bytes = Vec::new();
+    midi_file.write(&mut bytes).unwrap();
+    // the note off is now 192 ticks after the note on, i.e. the vlq bytes 0x81 0x40
+    assert_eq!(
+        &bytes[bytes.len() - 9..],
+        &[0x81, 0x40, 0x80, 0x3C, 0x40, 0x00, 0xFF, 0x2F, 0x00][..]
+    );
+}
+
+#[test]
+fn track_mut() {
+    enable_logging();
+    let mut midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let original = midi_file.clone();
+    let track = midi_file.track_mut(1).unwrap();
+    let transpose = |note: &NoteMessage| {
+        NoteMessage::new(
+            note.channel(),
+            NoteNumber::new(note.note_number().get() + 2),
+            note.velocity(),
+        )
+    };
+    for ix in 0..track.events_len() {
+        match track[ix].event_mut() {
+            Event::Midi(Message::NoteOn(note)) | Event::Midi(Message::NoteOff(note)) => {
+                *note = transpose(note)
+            }
+            _ => {}
+        }
+    }
+    // a trailing event after the EndOfTrack is moved before it when written
+    midi_file
+        .track_mut(1)
+        .unwrap()
+        .push_lyric(0, "amen")
+        .unwrap();
+    assert!(midi_file.track_mut(2).is_none());
+
+    let mut bytes = Vec::new();
+    midi_file.write(&mut bytes).unwrap();
+    let reloaded = MidiFile::from_bytes(&bytes).unwrap();
+    let before: Vec<_> = original.track(1).unwrap().events().collect();
+    let after: Vec<_> = reloaded.track(1).unwrap().events().collect();
+    assert_eq!(before.len() + 1, after.len());
+    for (a, b) in before.iter().zip(after.iter()).take(before.len() - 1) {
+        assert_eq!(a.delta_time(), b.delta_time());
+        match (a.event(), b.event()) {
+            (Event::Midi(Message::NoteOn(a)), Event::Midi(Message::NoteOn(b)))
+            | (Event::Midi(Message::NoteOff(a)), Event::Midi(Message::NoteOff(b))) => {
+                assert_eq!(a.note_number().get() + 2, b.note_number().get())
+            }
+            (a, b) => assert_eq!(a, b),
+        }
+    }
+    assert_eq!(
+        *after[after.len() - 2].event(),
+        Event::Meta(MetaEvent::Lyric(Text::new("amen")))
+    );
+}
+
+#[test]
+fn push_poly_pressure() {
+    enable_logging();
+    let mut track = Track::default();
+    track
+        .push_poly_pressure(0, Channel::new(3), NoteNumber::new(60), Velocity::new(100))
+        .unwrap();
+    match track[0].event() {
+        Event::Midi(Message::PolyPressure(note)) => assert_eq!(note.velocity().get(), 100),
+        other => panic!("expected poly pressure, got {:?}", other),
+    }
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    let mut bytes = Vec::new();
+    midi_file.write(&mut bytes).unwrap();
+    // header (14) + track chunk header (8) + delta time (1)
+    assert_eq!(&bytes[23..26], &[0xA3, 0x3C, 0x64]);
+}
+
+#[test]
+fn default_tempo() {
+    enable_logging();
+    let settings = Settings::new().default_tempo(QuartersPerMinute::new(140));
+    let mut midi_file = MidiFile::new_with_settings(settings);
+    midi_file
+        .push_track(TrackBuilder::new().name("conductor").build().unwrap())
+        .unwrap();
+    midi_file
+        .push_track(TrackBuilder::new().lyric(0, "la").build().unwrap())
+        .unwrap();
+    let mut bytes = Vec::new();
+    midi_file.write(&mut bytes).unwrap();
+    let reloaded = MidiFile::from_bytes(&bytes).unwrap();
+    let tempos: Vec<_> = reloaded
+        .iter_events()
+        .filter_map(|(track, tick, event)| match event {
+            Event::Meta(MetaEvent::SetTempo(value)) => Some((track, tick, value.get())),
+            _ => None,
+        })
+        .collect();
+    // 60,000,000 / 140 = 428,571.4 microseconds per quarter
+    assert_eq!(tempos, vec![(0, 0, 428571)]);
+
+    // an existing tempo is not replaced
+    let mut midi_file = MidiFile::new_with_settings(settings);
+    let track = TrackBuilder::new()
+        .tempo(0, QuartersPerMinute::new(60))
+        .build()
+        .unwrap();
+    midi_file.push_track(track).unwrap();
+    assert_eq!(midi_file.track(0).unwrap().events_len(), 2);
+}
+
+#[test]
+fn strip_non_essential_meta() {
+    enable_logging();
+    let ch = Channel::new(0);
+    let track = TrackBuilder::new()
+        .name("Singer")
+        .instrument_name("Alto")
+        .time_signature(0, 6, DurationName::Eighth, Clocks::DottedQuarter)
+        .tempo(0, QuartersPerMinute::new(116))
+        .event(
+            0,
+            Event::Meta(MetaEvent::Port(midi_file::core::PortValue::new(1))),
+        )
+        .event(
+            0,
+            Event::Meta(MetaEvent::Unknown {
+                meta_type: 0x60,
+                data: vec![1],
+            }),
+        )
+        .lyric(0, "Row")
+        .note(0, ch, NoteNumber::new(72), Velocity::new(64), 1536)
+        .lyric(0, "row")
+        .note(0, ch, NoteNumber::new(72), Velocity::new(64), 1536)
+        .event(512, Event::Meta(MetaEvent::Marker(Text::new("rest"))))
+        .lyric(0, "row")
+        .note(512, ch, NoteNumber::new(72), Velocity::new(64), 1024)
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    let duration = |m: &MidiFile| m.iter_events().map(|(_, tick, _)| tick).max();
+    let notes = |m: &MidiFile| -> Vec<(u64, Event)> {
+        m.iter_events()
+            .filter(|(_, _, e)| matches!(e, Event::Midi(_)))
+            .map(|(_, tick, e)| (tick, e.clone()))
+            .collect()
+    };
+    let original = midi_file.clone();
+    midi_file.strip_non_essential_meta();
+
+    assert_eq!(duration(&original), duration(&midi_file));
+    assert_eq!(duration(&midi_file), Some(1536 + 1536 + 512 + 512 + 1024));
+    assert_eq!(notes(&original), notes(&midi_file));
+    let metas: Vec<_> = midi_file
+        .iter_events()
+        .filter_map(|(_, _, e)| match e {
+            Event::Meta(meta) => Some(meta.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(metas.len(), 4);
+    assert!(matches!(metas[0], MetaEvent::TimeSignature(_)));
+    assert!(matches!(metas[1], MetaEvent::SetTempo(_)));
+    assert!(matches!(metas[2], MetaEvent::Port(_)));
+    assert_eq!(metas[3], MetaEvent::EndOfTrack);
+}
+
+#[test]
+fn controller_values() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let track = midi_file.track(1).unwrap();
+    let values: Vec<_> = track
+        .controller_values(Channel::new(0), Control::ChannelVolume)
+        .collect();
+    assert!(values.iter().any(|(_, value)| value.get() == 83));
+    assert!(values.windows(2).all(|w| w[0].0 <= w[1].0));
+    assert!(track
+        .controller_values(Channel::new(9), Control::ChannelVolume)
+        .next()
+        .is_none());
+}
+
+#[test]
+fn normalize_note_offs() {
+    enable_logging();
+    let count = |midi_file: &MidiFile| {
+        let mut zero_velocity_on = 0;
+        let mut off = 0;
+        for event in midi_file.tracks().flat_map(|t| t.events()) {
+            match event.event() {
+                Event::Midi(Message::NoteOn(m)) if m.velocity().get() == 0 => zero_velocity_on += 1,
+                Event::Midi(Message::NoteOff(_)) => off += 1,
+                _ => {}
+            }
+        }
+        (zero_velocity_on, off)
+    };
+    let mut midi_file = MidiFile::load(test_file(ALS_DIE_ROEMER)).unwrap();
+    let (zero_velocity_on, off) = count(&midi_file);
+    assert!(zero_velocity_on > 0);
+    assert_eq!(off, 0);
+
+    midi_file.normalize_note_offs(true);
+    assert_eq!(count(&midi_file), (0, zero_velocity_on));
+
+    midi_file.normalize_note_offs(false);
+    assert_eq!(count(&midi_file), (zero_velocity_on, 0));
+}
+
+#[test]
+fn merge() {
+    enable_logging();
+    let stem = |name: &str, program: GeneralMidi| {
+        let ch = Channel::new(0);
+        let mut midi_file = MidiFile::new_with_settings(Settings::new().format(Format::Single));
+        let track = TrackBuilder::new()
+            .name(name)
+            .general_midi(ch, program)
+            .note(0, ch, NoteNumber::new(60), Velocity::new(64), 1024)
+            .build()
+            .unwrap();
+        midi_file.push_track(track).unwrap();
+        midi_file
+    };
+    let stems = [
+        stem("Flute", GeneralMidi::Flute),
+        stem("Cello", GeneralMidi::Cello),
+    ];
+    let merged = MidiFile::merge(&stems).unwrap();
+    assert_eq!(*merged.header().format(), Format::Multi);
+    assert_eq!(merged.tracks_len(), 2);
+    assert_eq!(merged.track(0), stems[0].track(0));
+    assert_eq!(merged.track(1), stems[1].track(0));
+    let names = merged
+        .track(1)
+        .unwrap()
+        .find_events(|event| matches!(event, Event::Meta(MetaEvent::TrackName(name)) if name.as_str() == "Cello"));
+    assert_eq!(names.len(), 1);
+
+    let other_division = MidiFile::new_with_settings(
+        Settings::new().divisions(Division::QuarterNote(QuarterNoteDivision::new(480))),
+    );
+    let err = MidiFile::merge(&[stems[0].clone(), other_division]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+
+    let mut full = MidiFile::new_with_settings(Settings::new().format(Format::Multi));
+    for _ in 0..u16::MAX {
+        full.push_track(Track::default()).unwrap();
+    }
+    let err = MidiFile::merge(&[full.clone(), stems[0].clone()]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Overflow);
+    assert!(err.to_string().contains("65536 tracks"));
+    assert!(err.to_string().contains("65535"));
+    assert_eq!(
+        MidiFile::merge(&[full]).unwrap().tracks_len(),
+        u32::from(u16::MAX)
+    );
+}
+
+#[test]
+fn try_from_bytes() {
+    enable_logging();
+    let bytes = std::fs::read(test_file(AVE_MARIS_STELLA)).unwrap();
+    let expected = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(MidiFile::try_from(bytes.as_slice()).unwrap(), expected);
+    assert_eq!(MidiFile::try_from(bytes).unwrap(), expected);
+    let err = MidiFile::try_from(&b"MThd"[..]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidFile);
+}
+
+#[test]
+fn pitch_bend_message_new() {
+    enable_logging();
+    let message = PitchBendMessage::new(Channel::new(3), PitchBendValue::new(10000));
+    assert_eq!(message.channel().get(), 3);
+    assert_eq!(message.pitch_bend().get(), 10000);
+    let mut track = Track::default();
+    track
+        .push_event(0, Event::Midi(Message::PitchBend(message)))
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    let mut bytes = Vec::new();
+    midi_file.write(&mut bytes).unwrap();
+
+    // the message is written as a pitch bend status byte on channel 3 and a 14-bit value
+    let expected = [0x00, 0xE3, (10000 & 0x7f) as u8, (10000 >> 7) as u8];
+    assert!(bytes.windows(expected.len()).any(|w| w == expected));
+    let reloaded = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        reloaded.track(0).unwrap().events().next().unwrap().event(),
+        &Event::Midi(Message::PitchBend(message))
+    );
+}
+
+#[test]
+fn dedup_controllers() {
+    enable_logging();
+    let ch = Channel::new(0);
+    let volume = ControlValue::new(100);
+    let track = TrackBuilder::new()
+        .control_change(0, ch, Control::ChannelVolume, volume)
+        .control_change(10, ch, Control::ChannelVolume, volume)
+        .control_change(0, Channel::new(1), Control::ChannelVolume, volume)
+        .control_change(20, ch, Control::ChannelVolume, volume)
+        .note_on(5, ch, NoteNumber::new(60), Velocity::new(64))
+        .control_change(0, ch, Control::ChannelVolume, ControlValue::new(90))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    midi_file.dedup_controllers();
+    let track = midi_file.track(0).unwrap();
+    assert_eq!(track.events_len(), 5);
+    let ticks: Vec<u64> = track
+        .find_events(|_| true)
+        .into_iter()
+        .map(|(_, tick)| tick)
+        .collect();
+    assert_eq!(ticks, vec![0, 10, 35, 35, 35]);
+    assert!(matches!(
+        track.events().nth(2).unwrap().event(),
+        Event::Midi(Message::NoteOn(_))
+    ));
+}
+
+#[test]
+fn dedup_controllers_stateful() {
+    enable_logging();
+    let ch = Channel::new(0);
+    let value = |v: u8| ControlValue::new(v);
+    let controls = |midi_file: &MidiFile| -> Vec<(u8, u8)> {
+        midi_file
+            .track(0)
+            .unwrap()
+            .events()
+            .filter_map(|e| match e.event() {
+                Event::Midi(Message::Control(cc)) => Some((cc.control() as u8, cc.value().get())),
+                _ => None,
+            })
+            .collect()
+    };
+
+    // pitch bend range of 2 semitones, then fine tuning with the same data entry value
+    let track = TrackBuilder::new()
+        .control_change(0, ch, Control::RegisteredParameterNumberMsb, value(0))
+        .control_change(0, ch, Control::RegisteredParameterNumberLsb, value(0))
+        .control_change(0, ch, Control::DataEntryMsb, value(2))
+        .control_change(0, ch, Control::RegisteredParameterNumberMsb, value(0))
+        .control_change(0, ch, Control::RegisteredParameterNumberLsb, value(1))
+        .control_change(0, ch, Control::DataEntryMsb, value(2))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    midi_file.dedup_controllers();
+    // only the repeated selection of RPN MSB 0 is removed
+    assert_eq!(
+        controls(&midi_file),
+        vec![(101, 0), (100, 0), (6, 2), (100, 1), (6, 2)]
+    );
+
+    // data increment and decrement, and repeated data entry, are always kept
+    let track = TrackBuilder::new()
+        .control_change(0, ch, Control::NonRegisteredParameterNumberMsb, value(1))
+        .control_change(0, ch, Control::NonRegisteredParameterNumberLsb, value(8))
+        .control_change(0, ch, Control::DataEntryMsbLsb, value(5))
+        .control_change(0, ch, Control::DataEntryMsbLsb, value(5))
+        .control_change(0, ch, Control::DataIncrement, value(0))
+        .control_change(0, ch, Control::DataIncrement, value(0))
+        .control_change(0, ch, Control::DataDecrement, value(0))
+        .control_change(0, ch, Control::DataDecrement, value(0))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    midi_file.dedup_controllers();
+    assert_eq!(controls(&midi_file).len(), 8);
+
+    // a reset sets the controllers to their defaults, so the value after it is needed again
+    let track = TrackBuilder::new()
+        .control_change(0, ch, Control::ChannelVolume, value(100))
+        .event(0, Event::Midi(Message::ResetAllControllers(ch)))
+        .control_change(0, ch, Control::ChannelVolume, value(100))
+        .control_change(0, ch, Control::ChannelVolume, value(100))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    midi_file.dedup_controllers();
+    assert_eq!(controls(&midi_file), vec![(7, 100), (7, 100)]);
+
+    // so does a change of the selected parameter
+    let track = TrackBuilder::new()
+        .control_change(0, ch, Control::ChannelVolume, value(100))
+        .control_change(0, ch, Control::NonRegisteredParameterNumberMsb, value(3))
+        .control_change(0, ch, Control::ChannelVolume, value(100))
+        .control_change(0, ch, Control::NonRegisteredParameterNumberMsb, value(3))
+        .control_change(0, ch, Control::ChannelVolume, value(100))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    midi_file.dedup_controllers();
+    assert_eq!(controls(&midi_file), vec![(7, 100), (99, 3), (7, 100)]);
+}
+
+#[test]
+fn signature_maps() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let time_signatures = midi_file.time_signature_map();
+    let (tick, first) = time_signatures.first().unwrap();
+    assert_eq!(*tick, 0);
+    assert_eq!(first.numerator(), 4);
+    assert_eq!(first.denominator(), DurationName::Quarter);
+    assert!(time_signatures.windows(2).all(|w| w[0].0 <= w[1].0));
+    let key_signatures = midi_file.key_signature_map();
+    assert_eq!(key_signatures.first().unwrap().0, 0);
+
+    // defaults when there are no signatures
+    let empty = MidiFile::new();
+    let time_signatures = empty.time_signature_map();
+    assert_eq!(time_signatures.len(), 1);
+    assert_eq!(time_signatures[0].1.numerator(), 4);
+    assert_eq!(time_signatures[0].1.denominator(), DurationName::Quarter);
+    let key_signatures = empty.key_signature_map();
+    assert_eq!(key_signatures.len(), 1);
+    assert_eq!(key_signatures[0].1.accidentals().get(), 0);
+    assert_eq!(key_signatures[0].1.mode(), KeyMode::Major);
+}
+
+#[test]
+fn stuck_notes() {
+    enable_logging();
+    let ch = Channel::new(1);
+    let track = TrackBuilder::new()
+        .note(0, ch, NoteNumber::new(60), Velocity::new(64), 480)
+        .note_on(0, ch, NoteNumber::new(64), Velocity::new(64))
+        .note(240, ch, NoteNumber::new(67), Velocity::new(64), 480)
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+
+    let stuck = midi_file.stuck_notes();
+    assert_eq!(stuck.len(), 1);
+    assert_eq!(stuck[0].track(), 0);
+    assert_eq!(stuck[0].channel(), ch);
+    assert_eq!(stuck[0].note_number().get(), 64);
+    assert_eq!(stuck[0].on_tick(), 480);
+
+    midi_file.fix_stuck_notes();
+    assert!(midi_file.stuck_notes().is_empty());
+    let track = midi_file.track(0).unwrap();
+    assert_eq!(track.duration_ticks(), 1200);
+    let note_offs = track.find_events(
+        |event| matches!(event, Event::Midi(Message::NoteOff(m)) if m.note_number().get() == 64),
+    );
+    assert_eq!(note_offs.len(), 1);
+    assert_eq!(note_offs[0], (track.events_len() - 2, 1200));
+}
+
+#[test]
+fn meta_event_length_mismatch() {
+    enable_logging();
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 0, 1 track, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x60,
+        // track chunk, 11 bytes
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x0B,
+        // a track name that declares 32 bytes but only has 3
+        0x00, 0xFF, 0x03, 0x20, 0x61, 0x62, 0x63,
+        // end of track
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let err = MidiFile::from_bytes(&bytes).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidFile);
+    let message = err.to_string();
+    assert!(message.contains("declares 32 bytes of data but only 7 remain"));
+
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 0, 1 track, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x60,
+        // track chunk, 12 bytes
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x0C,
+        // a tempo with a length of 4 instead of 3
+        0x00, 0xFF, 0x51, 0x04, 0x07, 0xA1, 0x20, 0x00,
+        // end of track
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let err = MidiFile::from_bytes(&bytes).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidFile);
+    assert!(err
+        .to_string()
+        .contains("should have 3 bytes of data but declares 4"));
+}
+
+#[test]
+fn channels_used() {
+    enable_logging();
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let channels = midi_file.channels_used();
+    assert!(channels.contains(&Channel::new(0)));
+    assert_eq!(channels, midi_file.track(1).unwrap().channels_used());
+    assert!(midi_file.track(0).unwrap().channels_used().is_empty());
+
+    let track = TrackBuilder::new()
+        .note(
+            0,
+            Channel::new(3),
+            NoteNumber::new(60),
+            Velocity::new(64),
+            96,
+        )
+        .pitch_bend(0, Channel::new(1), PitchBendValue::new(0))
+        .build()
+        .unwrap();
+    let channels: Vec<u8> = track.channels_used().iter().map(|c| c.get()).collect();
+    assert_eq!(channels, vec![1, 3]);
+}
+
+#[test]
+fn program_at() {
+    enable_logging();
+    let ch = Channel::new(2);
+    let program_change = |program: u8| {
+        Event::Midi(Message::ProgramChange(ProgramChangeValue::new(
+            ch,
+            Program::new(program),
+        )))
+    };
+    let track = TrackBuilder::new()
+        .event(100, program_change(40))
+        .note(0, ch, NoteNumber::new(60), Velocity::new(64), 100)
+        .event(100, program_change(41))
+        .event(0, program_change(40))
+        .event(0, program_change(73))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+
+    assert_eq!(midi_file.program_at(ch, 99), None);
+    assert_eq!(midi_file.program_at(ch, 100), Some(Program::new(40)));
+    assert_eq!(midi_file.program_at(ch, 250), Some(Program::new(40)));
+    assert_eq!(midi_file.program_at(ch, 300), Some(Program::new(73)));
+    assert_eq!(midi_file.program_at(Channel::new(0), 300), None);
+
+    let used = midi_file.programs_used();
+    assert_eq!(used.len(), 1);
+    assert_eq!(
+        used[&ch],
+        vec![Program::new(40), Program::new(41), Program::new(73)]
+    );
+}
+
+#[test]
+fn ppq() {
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    assert_eq!(midi_file.ppq(), Some(1024));
+    assert_eq!(midi_file.header().ppq(), Some(1024));
+}
+
+#[test]
+fn add_click_track() {
+    let ch = Channel::new(9);
+    let click = NoteNumber::new(76);
+    let quarter = 1024;
+    // two bars of 4/4 followed by two bars of 6/8
+    let track = TrackBuilder::new()
+        .time_signature(0, 4, DurationName::Quarter, Clocks::Quarter)
+        .note(
+            0,
+            Channel::new(0),
+            NoteNumber::new(60),
+            Velocity::new(64),
+            8 * quarter,
+        )
+        .time_signature(0, 6, DurationName::Eighth, Clocks::DottedQuarter)
+        .note(
+            0,
+            Channel::new(0),
+            NoteNumber::new(62),
+            Velocity::new(64),
+            6 * quarter,
+        )
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    midi_file
+        .add_click_track(ch, click, Velocity::new(80))
+        .unwrap();
+    assert_eq!(midi_file.tracks_len(), 2);
+
+    let clicks: Vec<(u64, u8)> = midi_file
+        .iter_events()
+        .filter_map(|(track, tick, event)| match event {
+            Event::Midi(Message::NoteOn(m)) if track == 1 => Some((tick, m.velocity().get())),
+            _ => None,
+        })
+        .collect();
+    // 8 quarter note beats and 12 eighth note beats
+    assert_eq!(clicks.len(), 20);
+    let quarter = u64::from(quarter);
+    assert_eq!(clicks[0], (0, 100));
+    assert_eq!(clicks[1], (quarter, 80));
+    assert_eq!(clicks[4], (4 * quarter, 100));
+    assert_eq!(clicks[8], (8 * quarter, 100));
+    assert_eq!(clicks[9], (8 * quarter + quarter / 2, 80));
+    assert_eq!(clicks[14], (11 * quarter, 100));
+    assert!(midi_file.stuck_notes().is_empty());
+}
+
+#[test]
+fn diff() {
+    let original = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    assert!(original.diff(&original.clone()).is_empty());
+
+    let transpose = |m: &NoteMessage| {
+        NoteMessage::new(
+            m.channel(),
+            NoteNumber::new(m.note_number().get() + 12),
+            m.velocity(),
+        )
+    };
+    let mut transposed = original.clone();
+    transposed
+        .track_mut(1)
+        .unwrap()
+        .map_events(|delta_time, event| match event {
+            Event::Midi(Message::NoteOn(m)) => {
+                (delta_time, Event::Midi(Message::NoteOn(transpose(m))))
+            }
+            Event::Midi(Message::NoteOff(m)) => {
+                (delta_time, Event::Midi(Message::NoteOff(transpose(m))))
+            }
+            _ => (delta_time, event.clone()),
+        });
+    let note_count = original
+        .track(1)
+        .unwrap()
+        .events()
+        .filter(|e| {
+            matches!(
+                e.event(),
+                Event::Midi(Message::NoteOn(_) | Message::NoteOff(_))
+            )
+        })
+        .count();
+    let differences = original.diff(&transposed);
+    assert!(note_count > 0);
+    assert_eq!(differences.len(), note_count);
+    for difference in &differences {
+        assert_eq!(difference.track(), 1);
+        assert!(difference.description().starts_with("fields"));
+        assert!(difference.description().contains("Note"));
+    }
+
+    let mut shorter = original.clone();
+    shorter.remove_track(1).unwrap();
+    let differences = original.diff(&shorter);
+    assert_eq!(differences.len(), 1);
+    assert_eq!(
+        differences[0].to_string(),
+        "track 1, event 0: the track is missing from the second file"
+    );
+}
+
+#[test]
+fn write_at_offset() {
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let bytes = midi_file.write_to_vec().unwrap();
+    let mut buffer = b"a container header".to_vec();
+    let offset = buffer.len();
+    midi_file.write(&mut buffer).unwrap();
+    assert_eq!(&buffer[offset..], bytes.as_slice());
+    let reloaded = MidiFile::from_bytes(&buffer[offset..]).unwrap();
+    assert_eq!(reloaded, midi_file);
+}
+
+#[test]
+fn event_histogram() {
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let histogram = midi_file.event_histogram();
+    let notes = midi_file
+        .iter_events()
+        .filter(|(_, _, event)| {
+            matches!(event, Event::Midi(Message::NoteOn(m)) if m.velocity().get() > 0)
+        })
+        .count();
+    assert!(notes > 0);
+    assert_eq!(histogram.note_ons(), notes);
+    assert_eq!(histogram.note_offs(), notes);
+    assert_eq!(histogram.meta_events()[&0x2F], 2);
+    assert!(histogram.control_changes()[&Control::ChannelVolume] >= 1);
+    assert_eq!(histogram.sysex_events(), 0);
+
+    // velocity zero note-ons are counted as note-offs
+    let histogram = MidiFile::load(test_file(ALS_DIE_ROEMER))
+        .unwrap()
+        .event_histogram();
+    assert!(histogram.note_ons() > 0);
+    assert_eq!(histogram.note_ons(), histogram.note_offs());
+}
+
+#[test]
+fn trim_silence() {
+    let ch = Channel::new(0);
+    let conductor = TrackBuilder::new()
+        .tempo(0, QuartersPerMinute::new(100))
+        .time_signature(0, 3, DurationName::Quarter, Clocks::Quarter)
+        .tempo(3000, QuartersPerMinute::new(120))
+        .build()
+        .unwrap();
+    let notes = TrackBuilder::new()
+        .name("Piano")
+        .note(2000, ch, NoteNumber::new(60), Velocity::new(64), 1024)
+        .note(0, ch, NoteNumber::new(62), Velocity::new(64), 1024)
+        .event(5000, Event::Meta(MetaEvent::EndOfTrack))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(conductor).unwrap();
+    midi_file.push_track(notes).unwrap();
+
+    midi_file.trim_leading_silence();
+    let ticks_of = |midi_file: &MidiFile, track: u32| -> Vec<u64> {
+        midi_file
+            .iter_events()
+            .filter(|(t, _, _)| *t == track)
+            .map(|(_, tick, _)| tick)
+            .collect()
+    };
+    // the tempo change keeps its position relative to the first note
+    assert_eq!(ticks_of(&midi_file, 0), vec![0, 0, 1000, 1000]);
+    assert_eq!(ticks_of(&midi_file, 1), vec![0, 0, 1024, 1024, 2048, 7048]);
+
+    midi_file.trim_trailing_silence();
+    assert_eq!(ticks_of(&midi_file, 0), vec![0, 0, 1000, 2048]);
+    assert_eq!(ticks_of(&midi_file, 1), vec![0, 0, 1024, 1024, 2048, 2048]);
+    assert!(midi_file.validate().is_empty());
+}
+
+#[test]
+fn load_track() {
+    enable_logging();
+    let path = test_file(AVE_MARIS_STELLA);
+    let midi_file = MidiFile::load(&path).unwrap();
+    for index in 0..midi_file.tracks_len() {
+        let track = MidiFile::load_track(&path, index).unwrap();
+        assert_eq!(&track, midi_file.track(index).unwrap());
+    }
+    assert!(MidiFile::load_track(&path, midi_file.tracks_len()).is_err());
+}
+
+#[test]
+fn note_events_ms() {
+    let ch = Channel::new(1);
+    // 480 ticks per quarter at 120 quarters per minute is 500 ms per quarter
+    let conductor = TrackBuilder::new()
+        .tempo(0, QuartersPerMinute::new(120))
+        .tempo(960, QuartersPerMinute::new(60))
+        .build()
+        .unwrap();
+    let notes = TrackBuilder::new()
+        .note(0, ch, NoteNumber::new(60), Velocity::new(90), 480)
+        // starts at 500 ms, then one quarter at 120 and one at 60
+        .note(0, ch, NoteNumber::new(62), Velocity::new(80), 960)
+        .note_on(0, ch, NoteNumber::new(64), Velocity::new(70))
+        .event(480, Event::Meta(MetaEvent::EndOfTrack))
+        .build()
+        .unwrap();
+    let settings = Settings::new().divisions(Division::QuarterNote(QuarterNoteDivision::new(480)));
+    let mut midi_file = MidiFile::new_with_settings(settings);
+    midi_file.push_track(conductor).unwrap();
+    midi_file.push_track(notes).unwrap();
+
+    let found: Vec<(u32, u8, u8, u64, u64)> = midi_file
+        .note_events_ms()
+        .iter()
+        .map(|n| {
+            (
+                n.track(),
+                n.note_number().get(),
+                n.velocity().get(),
+                n.start_ms(),
+                n.end_ms(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, 60, 90, 0, 500),
+            (1, 62, 80, 500, 2000),
+            // never turned off, so it lasts until the end of the track
+            (1, 64, 70, 2000, 3000),
+        ]
+    );
+    assert!(midi_file.note_events_ms().iter().all(|n| n.channel() == ch));
+}
+
+#[test]
+fn is_conductor() {
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let conductors: Vec<bool> = midi_file.tracks().map(|t| t.is_conductor()).collect();
+    assert_eq!(conductors, vec![true, false]);
+    // a track with only a name is not a conductor track
+    let named = TrackBuilder::new().name("Piano").build().unwrap();
+    assert!(!named.is_conductor());
+}
+
+#[test]
+fn inflated_track_length() {
+    enable_logging();
+    let mut bytes = Vec::new();
+    File::open(test_file(AVE_MARIS_STELLA))
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    let original = MidiFile::from_bytes(&bytes).unwrap();
+    let last_track = bytes.windows(4).rposition(|w| w == b"MTrk").unwrap();
+    let length_bytes = last_track + 4..last_track + 8;
+    let length = <[u8; 4]>::try_from(&bytes[length_bytes.clone()]).unwrap();
+    let length = u32::from_be_bytes(length);
+    bytes[length_bytes].copy_from_slice(&(length + 100).to_be_bytes());
+    let tempdir = tempdir().unwrap();
+    let path = tempdir.path().join("file.mid");
+    std::fs::write(&path, &bytes).unwrap();
+
+    // the length of a slice or file is known before the chunk is parsed, the length of a stream is
+    // found when the data runs out
+    for result in [
+        MidiFile::from_bytes(&bytes),
+        MidiFile::load(&path),
+        MidiFile::read(bytes.as_slice()),
+    ] {
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidFile);
+        assert_eq!(error.byte_position(), Some(bytes.len() as u64 - 1));
+        let message = error.to_string();
+        assert!(message.contains(&format!("declares a length of {} bytes", length + 100)));
+        assert!(message.contains(&format!("only {} bytes are available", length)));
+    }
+
+    let settings = Settings::new().clamp_track_lengths(true);
+    let clamped = MidiFile::load_with_settings(&path, settings).unwrap();
+    assert_eq!(clamped, original);
+    let clamped = MidiFile::read_with_settings(bytes.as_slice(), settings).unwrap();
+    assert_eq!(clamped, original);
+
+    // a file that is cut off between two events is read up to the cut. the first event of the
+    // track is a 26 byte instrument name.
+    let cut = last_track + 8 + 26;
+    let clamped = MidiFile::read_with_settings(&bytes[..cut], settings).unwrap();
+    let track = clamped.track(1).unwrap();
+    assert_eq!(track.events_len(), 2);
+    assert_eq!(track[0], original.track(1).unwrap()[0]);
+    assert!(matches!(
+        track[1].event(),
+        Event::Meta(MetaEvent::EndOfTrack)
+    ));
+}
+
+#[test]
+fn all_notes_sorted() {
+    let ch = Channel::new(0);
+    let melody = TrackBuilder::new()
+        .name("Melody")
+        .note(0, ch, NoteNumber::new(72), Velocity::new(80), 480)
+        .note(0, ch, NoteNumber::new(74), Velocity::new(80), 480)
+        .build()
+        .unwrap();
+    let bass = TrackBuilder::new()
+        .name("Bass")
+        .note(
+            0,
+            Channel::new(1),
+            NoteNumber::new(48),
+            Velocity::new(60),
+            960,
+        )
+        .note(
+            0,
+            Channel::new(1),
+            NoteNumber::new(43),
+            Velocity::new(60),
+            240,
+        )
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(melody).unwrap();
+    midi_file.push_track(bass).unwrap();
+
+    let notes: Vec<(u64, u8, u32, u64)> = midi_file
+        .all_notes_sorted()
+        .iter()
+        .map(|n| {
+            (
+                n.start_tick(),
+                n.note_number().get(),
+                n.track(),
+                n.duration_ticks(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        notes,
+        vec![
+            (0, 48, 1, 960),
+            (0, 72, 0, 480),
+            (480, 74, 0, 480),
+            (960, 43, 1, 240)
+        ]
+    );
+}
+
+#[test]
+fn annotations() {
+    // the first track of examples/main.rs, with a copyright and markers added
+    const QUARTER: u32 = 1024;
+    const EIGHTH: u32 = QUARTER / 2;
+    const DOTTED_QUARTER: u32 = QUARTER + EIGHTH;
+    const C4: NoteNumber = NoteNumber::new(72);
+    const D4: NoteNumber = NoteNumber::new(74);
+    const E4: NoteNumber = NoteNumber::new(76);
+    const V: Velocity = Velocity::new(64);
+    const CH: Channel = Channel::new(0);
+    let mut midi_file = MidiFile::new();
+    assert!(midi_file.copyright().is_none());
+    let track = TrackBuilder::new()
+        .event(0, Event::Meta(MetaEvent::Copyright(Text::new("(c) 2020"))))
+        .name("Singer")
+        .tempo(0, QuartersPerMinute::new(116))
+        .event(0, Event::Meta(MetaEvent::Marker(Text::new("Verse"))))
+        .lyric(0, "Row")
+        .note(0, CH, C4, V, DOTTED_QUARTER)
+        .lyric(0, "row")
+        .note(0, CH, C4, V, DOTTED_QUARTER)
+        .lyric(0, "row")
+        .note(0, CH, C4, V, QUARTER)
+        .lyric(0, "your")
+        .note(0, CH, D4, V, EIGHTH)
+        .event(0, Event::Meta(MetaEvent::Marker(Text::new("Boat"))))
+        .lyric(0, "boat")
+        .note(0, CH, E4, V, DOTTED_QUARTER)
+        .build()
+        .unwrap();
+    midi_file.push_track(track).unwrap();
+
+    assert_eq!(midi_file.copyright().unwrap(), "(c) 2020");
+    let lyrics: Vec<(u64, String)> = midi_file
+        .lyrics()
+        .into_iter()
+        .map(|(tick, text)| (tick, text.into_owned()))
+        .collect();
+    assert_eq!(
+        lyrics,
+        vec![
+            (0, "Row".to_owned()),
+            (1536, "row".to_owned()),
+            (3072, "row".to_owned()),
+            (4096, "your".to_owned()),
+            (4608, "boat".to_owned())
+        ]
+    );
+    let markers = midi_file.markers();
+    assert_eq!(markers.len(), 2);
+    assert_eq!(markers[0], (0, "Verse".into()));
+    assert_eq!(markers[1], (4608, "Boat".into()));
+}
+
+#[test]
+fn track_programs() {
+    let mut midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let programs = midi_file.track_programs();
+    assert_eq!(programs.len(), 2);
+    assert_eq!(programs[0], None);
+
+    let ch = Channel::new(3);
+    let track = midi_file.track_mut(1).unwrap();
+    track.set_program(ch, Program::new(100)).unwrap();
+    assert_eq!(midi_file.track_programs()[1], Some((ch, Program::new(100))));
+    // the leading program change is replaced rather than added again
+    let events_len = midi_file.track(1).unwrap().events_len();
+    let track = midi_file.track_mut(1).unwrap();
+    track.set_program(ch, Program::new(7)).unwrap();
+    assert_eq!(midi_file.track(1).unwrap().events_len(), events_len);
+    assert_eq!(midi_file.track_programs()[1], Some((ch, Program::new(7))));
+}
+
+#[test]
+fn effective_resolution() {
+    let ch = Channel::new(0);
+    let mut midi_file = MidiFile::new();
+    assert_eq!(midi_file.effective_resolution(), None);
+    let track = TrackBuilder::new()
+        .name("Piano")
+        .note(0, ch, NoteNumber::new(60), Velocity::new(64), 768)
+        .note(256, ch, NoteNumber::new(62), Velocity::new(64), 1024)
+        .build()
+        .unwrap();
+    midi_file.push_track(track).unwrap();
+    assert_eq!(midi_file.ppq(), Some(1024));
+    assert_eq!(midi_file.effective_resolution(), Some(256));
+
+    let track = TrackBuilder::new()
+        .note(2048, ch, NoteNumber::new(64), Velocity::new(64), 1536)
+        .build()
+        .unwrap();
+    midi_file.push_track(track).unwrap();
+    assert_eq!(midi_file.effective_resolution(), Some(256));
+
+    let track = TrackBuilder::new()
+        .note(96, ch, NoteNumber::new(64), Velocity::new(64), 96)
+        .build()
+        .unwrap();
+    midi_file.push_track(track).unwrap();
+    assert_eq!(midi_file.effective_resolution(), Some(32));
+}
+
+#[test]
+fn empty_single_track() {
+    let midi_file = MidiFile::empty_single_track();
+    assert_eq!(*midi_file.header().format(), Format::Single);
+    assert_eq!(midi_file.tracks_len(), 1);
+    let bytes = midi_file.write_to_vec().unwrap();
+    // the header chunk and a track chunk holding only an EndOfTrack
+    assert_eq!(bytes.len(), 14 + 8 + 4);
+    let read = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(read, midi_file);
+    let track = read.track(0).unwrap();
+    assert_eq!(track.events_len(), 1);
+    assert!(matches!(
+        track[0].event(),
+        Event::Meta(MetaEvent::EndOfTrack)
+    ));
+    assert!(read.validate().is_empty());
+}
+
+#[test]
+fn set_format() {
+    let mut midi_file = MidiFile::new_with_settings(Settings::new().format(Format::Multi));
+    midi_file.push_track(Track::default()).unwrap();
+    midi_file.set_format(Format::Single).unwrap();
+    assert_eq!(*midi_file.header().format(), Format::Single);
+    let read = MidiFile::from_bytes(&midi_file.write_to_vec().unwrap()).unwrap();
+    assert_eq!(*read.header().format(), Format::Single);
+
+    let mut midi_file = MidiFile::new_with_settings(Settings::new().format(Format::Multi));
+    midi_file.push_track(Track::default()).unwrap();
+    midi_file.push_track(Track::default()).unwrap();
+    assert!(midi_file.set_format(Format::Single).is_err());
+    assert_eq!(*midi_file.header().format(), Format::Multi);
+    midi_file.set_format(Format::Sequential).unwrap();
+    assert_eq!(*midi_file.header().format(), Format::Sequential);
+}
+
+#[test]
+fn port_meta_event() {
+    enable_logging();
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 0, 1 track, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x60,
+        // track chunk, 9 bytes
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x09,
+        // port 3
+        0x00, 0xFF, 0x21, 0x01, 0x03,
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let midi_file = MidiFile::read(bytes.as_slice()).unwrap();
+    let track = midi_file.track(0).unwrap();
+    assert!(matches!(
+        track[0].event(),
+        Event::Meta(MetaEvent::Port(port)) if port.get() == 3
+    ));
+    assert_eq!(midi_file.write_to_vec().unwrap(), bytes);
+
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x60,
+        // track chunk, 10 bytes
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x0A,
+        // a port event with two bytes of data
+        0x00, 0xFF, 0x21, 0x02, 0x00, 0x03,
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let error = MidiFile::read(bytes.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidFile);
+    let message = error.to_string();
+    assert!(message.contains("MIDI port meta event"), "{}", message);
+    assert!(message.contains("declares 2"), "{}", message);
+}
+
+#[test]
+fn scheduled() {
+    let ch = Channel::new(0);
+    let conductor = TrackBuilder::new()
+        .tempo(0, QuartersPerMinute::new(120))
+        .tempo(960, QuartersPerMinute::new(60))
+        .build()
+        .unwrap();
+    let notes = TrackBuilder::new()
+        .event(0, Event::Meta(MetaEvent::TrackName(Text::new("Piano"))))
+        .note(1440, ch, NoteNumber::new(60), Velocity::new(90), 480)
+        .build()
+        .unwrap();
+    let settings = Settings::new().divisions(Division::QuarterNote(QuarterNoteDivision::new(480)));
+    let mut midi_file = MidiFile::new_with_settings(settings);
+    midi_file.push_track(conductor).unwrap();
+    midi_file.push_track(notes).unwrap();
+
+    let scheduled: Vec<_> = midi_file.scheduled().collect();
+    assert_eq!(scheduled.len(), 2);
+    // two quarters at 120 and one at 60
+    let first = &scheduled[0];
+    assert_eq!(first.time_ms(), 2000);
+    assert_eq!(first.track(), 1);
+    assert!(matches!(first.message(), Message::NoteOn(_)));
+    assert_eq!(first.time_ms(), midi_file.note_events_ms()[0].start_ms());
+    assert_eq!(scheduled[1].time_ms(), 3000);
+    assert!(matches!(scheduled[1].message(), Message::NoteOff(_)));
+
+    let midi_file = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let scheduled: Vec<_> = midi_file.scheduled().collect();
+    assert!(scheduled
+        .windows(2)
+        .all(|w| w[0].time_ms() <= w[1].time_ms()));
+    let first_note_on = scheduled
+        .iter()
+        .find(|e| matches!(e.message(), Message::NoteOn(_)))
+        .unwrap();
+    assert_eq!(
+        first_note_on.time_ms(),
+        midi_file.note_events_ms()[0].start_ms()
+    );
+}
+
+#[test]
+fn load_lossy() {
+    enable_logging();
+    #[rustfmt::skip]
+    let bytes: Vec<u8> = vec![
+        // header chunk: format 1, 3 tracks, 96 ticks per quarter
+        0x4D, 0x54, 0x68, 0x64, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x03, 0x00, 0x60,
+        // a note on and off, then end of track
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x0C,
+        0x00, 0x90, 0x3C, 0x40, 0x60, 0x80, 0x3C, 0x00, 0x00, 0xFF, 0x2F, 0x00,
+        // a tempo event with two bytes of data instead of three
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x0A,
+        0x00, 0xFF, 0x51, 0x02, 0x07, 0xA1, 0x00, 0xFF, 0x2F, 0x00,
+        // only an end of track
+        0x4D, 0x54, 0x72, 0x6B, 0x00, 0x00, 0x00, 0x04, 0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let tempdir = tempdir().unwrap();
+    let path = tempdir.path().join("file.mid");
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(MidiFile::load(&path).is_err());
+    let (midi_file, errors) = MidiFile::load_lossy(&path).unwrap();
+    assert_eq!(midi_file.tracks_len(), 2);
+    assert_eq!(midi_file.track(0).unwrap().events_len(), 3);
+    assert_eq!(midi_file.track(1).unwrap().events_len(), 1);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].track(), 1);
+    assert_eq!(errors[0].error().kind(), ErrorKind::InvalidFile);
+    let source = std::error::Error::source(&errors[0]).unwrap();
+    assert_eq!(source.to_string(), errors[0].error().to_string());
+
+    // the second track is cut off, so it and any tracks after it are lost
+    let bytes = std::fs::read(test_file(AVE_MARIS_STELLA)).unwrap();
+    let original = MidiFile::from_bytes(&bytes).unwrap();
+    let second_track = bytes
+        .windows(4)
+        .enumerate()
+        .filter(|(_, w)| *w == b"MTrk")
+        .nth(1)
+        .unwrap()
+        .0;
+    std::fs::write(&path, &bytes[..second_track + 40]).unwrap();
+    let (midi_file, errors) = MidiFile::load_lossy(&path).unwrap();
+    assert_eq!(midi_file.tracks_len(), 1);
+    assert_eq!(midi_file.track(0), original.track(0));
+    let failed: Vec<u32> = errors.iter().map(|e| e.track()).collect();
+    let expected: Vec<u32> = (1..original.tracks_len()).collect();
+    assert_eq!(failed, expected);
+    assert!(errors[0].to_string().starts_with("track 1: "));
+    assert!(errors
+        .iter()
+        .all(|e| e.error().kind() == ErrorKind::InvalidFile));
+}
+
+#[test]
+fn measure_count() {
+    let ch = Channel::new(0);
+    let settings = Settings::new().divisions(Division::QuarterNote(QuarterNoteDivision::new(1024)));
+    let mut midi_file = MidiFile::new_with_settings(settings);
+    let conductor = TrackBuilder::new()
+        .time_signature(0, 4, DurationName::Quarter, Clocks::Quarter)
+        .build()
+        .unwrap();
+    midi_file.push_track(conductor).unwrap();
+    // four and a half measures of 4/4
+    let notes = TrackBuilder::new()
+        .note(
+            0,
+            ch,
+            NoteNumber::new(60),
+            Velocity::new(90),
+            4 * 4096 + 2048,
+        )
+        .build()
+        .unwrap();
+    midi_file.push_track(notes).unwrap();
+    assert_eq!(midi_file.beat_count(), Some(18.0));
+    assert_eq!(midi_file.measure_count(), Some(4));
+
+    // two measures of 4/4, then three of 3/4 and a half measure of 6/8
+    let three_four_tick = 2 * 4096;
+    let six_eight_tick = three_four_tick + 3 * 3072;
+    let conductor = TrackBuilder::new()
+        .time_signature(0, 4, DurationName::Quarter, Clocks::Quarter)
+        .time_signature(three_four_tick, 3, DurationName::Quarter, Clocks::Quarter)
+        // the builder takes delta times, so this is relative to the 3/4 change
+        .time_signature(
+            six_eight_tick - three_four_tick,
+            6,
+            DurationName::Eighth,
+            Clocks::DottedQuarter,
+        )
+        .build()
+        .unwrap();
+    let notes = TrackBuilder::new()
+        .note(
+            0,
+            ch,
+            NoteNumber::new(60),
+            Velocity::new(90),
+            six_eight_tick + 1536,
+        )
+        .build()
+        .unwrap();
+    *midi_file.track_mut(0).unwrap() = conductor;
+    *midi_file.track_mut(1).unwrap() = notes;
+    let six_eight_ticks: Vec<u64> = midi_file
+        .iter_events()
+        .filter_map(|(_, tick, event)| match event {
+            Event::Meta(MetaEvent::TimeSignature(value)) if value.numerator() == 6 => Some(tick),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(six_eight_ticks, vec![8192 + 3 * 3072]);
+    assert_eq!(midi_file.beat_count(), Some(18.5));
+    assert_eq!(midi_file.measure_count(), Some(5));
+}
+
+#[test]
+fn set_tempo_at() {
+    let ch = Channel::new(0);
+    let conductor = TrackBuilder::new()
+        .tempo(0, QuartersPerMinute::new(100))
+        .time_signature(0, 4, DurationName::Quarter, Clocks::Quarter)
+        .tempo(3840, QuartersPerMinute::new(90))
+        .build()
+        .unwrap();
+    let notes = TrackBuilder::new()
+        .note(0, ch, NoteNumber::new(60), Velocity::new(90), 7680)
+        .build()
+        .unwrap();
+    let settings = Settings::new().divisions(Division::QuarterNote(QuarterNoteDivision::new(480)));
+    let mut midi_file = MidiFile::new_with_settings(settings);
+    midi_file.push_track(conductor).unwrap();
+    midi_file.push_track(notes).unwrap();
+
+    // bar 3 of 4/4 at 480 ticks per quarter
+    midi_file
+        .set_tempo_at(3840, QuartersPerMinute::new(140))
+        .unwrap();
+    midi_file
+        .set_tempo_at(1920, QuartersPerMinute::new(120))
+        .unwrap();
+    let tempos: Vec<(u64, u32)> = midi_file
+        .conductor_summary()
+        .tempo_segments()
+        .iter()
+        .map(|segment| (segment.start(), segment.tempo().get()))
+        .collect();
+    assert_eq!(tempos, vec![(0, 600_000), (1920, 500_000), (3840, 428_571)]);
+    let conductor = midi_file.track(0).unwrap();
+    assert_eq!(conductor.events_len(), 5);
+    assert_eq!(conductor[2].delta_time(), 1920);
+    assert_eq!(conductor[3].delta_time(), 1920);
+    assert!(matches!(
+        conductor[4].event(),
+        Event::Meta(MetaEvent::EndOfTrack)
+    ));
+
+    let mut midi_file = MidiFile::new();
+    midi_file
+        .set_tempo_at(0, QuartersPerMinute::new(60))
+        .unwrap();
+    assert_eq!(midi_file.tracks_len(), 1);
+    assert!(matches!(
+        midi_file.track(0).unwrap()[0].event(),
+        Event::Meta(MetaEvent::SetTempo(tempo)) if tempo.get() == 1_000_000
+    ));
+}
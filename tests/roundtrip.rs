@@ -1,6 +1,11 @@
 mod utils;
 
-use midi_file::MidiFile;
+use midi_file::core::{
+    Channel, ChannelPressureMessage, Message, NoteNumber, PitchBendMessage, PitchBendValue,
+    Velocity,
+};
+use midi_file::file::{Event, Format, QuartersPerMinute, Track, TrackBuilder};
+use midi_file::{ErrorKind, MidiFile, Settings};
 use std::fmt::{Debug, Display, Formatter};
 use std::path::{Path, PathBuf};
 use tempfile::TempDir;
@@ -255,3 +260,403 @@ fn pitch_bend_two_bytes() {
 fn tobeefree() {
     round_trip_test(TOBEFREE).unwrap();
 }
+
+/// Running status can be forced on or off when writing, regardless of how the file was read, and
+/// the output re-parses to the same tracks.
+#[test]
+fn b_guajeo_running_status_settings() {
+    enable_logging();
+    let original_bytes = std::fs::read(test_file(B_GUAJEO)).unwrap();
+    let original = MidiFile::from_bytes(&original_bytes).unwrap();
+    assert!(!original.running_status());
+
+    let mut compact = Vec::new();
+    original
+        .write_with_settings(&mut compact, Settings::new().running_status(true))
+        .unwrap();
+    assert!(compact.len() < original_bytes.len());
+    let compact = MidiFile::from_bytes(&compact).unwrap();
+    assert!(compact.running_status());
+    assert!(original.tracks().eq(compact.tracks()));
+
+    let mut expanded = Vec::new();
+    compact
+        .write_with_settings(&mut expanded, Settings::new().running_status(false))
+        .unwrap();
+    assert_eq!(original_bytes, expanded);
+}
+
+/// A file that was read with running status can be written with explicit status bytes.
+#[test]
+fn als_die_roemer_expand_running_status() {
+    enable_logging();
+    let original = MidiFile::load(test_file(ALS_DIE_ROEMER)).unwrap();
+    assert!(original.running_status());
+    let mut compact = Vec::new();
+    original.write(&mut compact).unwrap();
+    let mut expanded = Vec::new();
+    original
+        .write_with_settings(&mut expanded, Settings::new().running_status(false))
+        .unwrap();
+    assert!(expanded.len() > compact.len());
+    let reloaded = MidiFile::from_bytes(&expanded).unwrap();
+    assert!(!reloaded.running_status());
+    assert_eq!(original.header(), reloaded.header());
+    assert!(original.tracks().eq(reloaded.tracks()));
+}
+
+/// Toggling running status changes the bytes but not the decoded content.
+#[test]
+fn semantically_equal_running_status() {
+    enable_logging();
+    let original_bytes = std::fs::read(test_file(ALS_DIE_ROEMER)).unwrap();
+    let original = MidiFile::from_bytes(&original_bytes).unwrap();
+    assert!(original.running_status());
+    let mut toggled_bytes = Vec::new();
+    original
+        .write_with_settings(&mut toggled_bytes, Settings::new().running_status(false))
+        .unwrap();
+    assert_ne!(original_bytes.len(), toggled_bytes.len());
+    let toggled = MidiFile::from_bytes(&toggled_bytes).unwrap();
+    assert_ne!(original, toggled);
+    assert!(original.semantically_equal(&toggled));
+    assert!(!original.semantically_equal(&MidiFile::load(test_file(B_GUAJEO)).unwrap()));
+}
+
+/// A format 2 file can hold several independent patterns, each with its own tempo.
+#[test]
+fn sequential_patterns() {
+    enable_logging();
+    let ch = Channel::new(0);
+    let settings = Settings::new().format(Format::Sequential);
+    let mut midi_file = MidiFile::new_with_settings(settings);
+    for (tempo, note) in [(100, 60), (140, 67)] {
+        let pattern = TrackBuilder::new()
+            .tempo(0, QuartersPerMinute::new(tempo))
+            .note(0, ch, NoteNumber::new(note), Velocity::new(64), 1024)
+            .build()
+            .unwrap();
+        midi_file.push_track(pattern).unwrap();
+    }
+    assert_eq!(midi_file.patterns().count(), 2);
+
+    let mut bytes = Vec::new();
+    midi_file.write(&mut bytes).unwrap();
+    let reloaded = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(*reloaded.header().format(), Format::Sequential);
+    assert_eq!(midi_file, reloaded);
+    assert!(midi_file.patterns().eq(reloaded.patterns()));
+    let mut rewritten = Vec::new();
+    reloaded.write(&mut rewritten).unwrap();
+    assert_eq!(bytes, rewritten);
+}
+
+/// Consecutive pitch bend and channel pressure messages on the same channel share a status byte.
+#[test]
+fn pitch_bend_and_channel_pressure_running_status() {
+    enable_logging();
+    let ch = Channel::new(2);
+    let bends = [8192u16, 9000, 10000];
+    let pressures = [10u8, 20];
+    let mut track = Track::default();
+    for bend in bends {
+        let message = PitchBendMessage::new(ch, PitchBendValue::new(bend));
+        track
+            .push_event(10, Event::Midi(Message::PitchBend(message)))
+            .unwrap();
+    }
+    for pressure in pressures {
+        let message = ChannelPressureMessage::new(ch, Velocity::new(pressure));
+        track
+            .push_event(10, Event::Midi(Message::ChannelPressure(message)))
+            .unwrap();
+    }
+    let mut midi_file = MidiFile::new_with_settings(Settings::new().running_status(true));
+    midi_file.push_track(track).unwrap();
+    let mut bytes = Vec::new();
+    midi_file.write(&mut bytes).unwrap();
+
+    let expected = [
+        0x0A, 0xE2, 0x00, 0x40, // status byte and the first pitch bend
+        0x0A, 0x28, 0x46, // running status
+        0x0A, 0x10, 0x4E, // running status
+        0x0A, 0xD2, 0x0A, // status byte and the first channel pressure
+        0x0A, 0x14, // running status
+    ];
+    assert!(bytes.windows(expected.len()).any(|w| w == expected));
+
+    let reloaded = MidiFile::from_bytes(&bytes).unwrap();
+    assert!(reloaded.semantically_equal(&midi_file));
+    let events: Vec<_> = reloaded.track(0).unwrap().events().collect();
+    for (event, bend) in events.iter().zip(bends) {
+        match event.event() {
+            Event::Midi(Message::PitchBend(m)) => assert_eq!(m.pitch_bend().get(), bend),
+            other => panic!("expected a pitch bend, got {:?}", other),
+        }
+    }
+    for (event, pressure) in events[bends.len()..].iter().zip(pressures) {
+        match event.event() {
+            Event::Midi(Message::ChannelPressure(m)) => assert_eq!(m.pressure().get(), pressure),
+            other => panic!("expected channel pressure, got {:?}", other),
+        }
+    }
+}
+
+/// Note-offs with a velocity of zero can share the note-on status byte.
+#[test]
+fn compress_note_offs() {
+    enable_logging();
+    let ch = Channel::new(0);
+    let mut builder = TrackBuilder::new();
+    for note in 60..80 {
+        let note = NoteNumber::new(note);
+        builder = builder.note_on(0, ch, note, Velocity::new(64)).note_off(
+            96,
+            ch,
+            note,
+            Velocity::new(0),
+        );
+    }
+    let track = builder.build().unwrap();
+    let write = |settings: Settings| {
+        let mut midi_file = MidiFile::new_with_settings(settings);
+        midi_file.push_track(track.clone()).unwrap();
+        let mut bytes = Vec::new();
+        midi_file.write(&mut bytes).unwrap();
+        (midi_file, bytes)
+    };
+    let (original, uncompressed) = write(Settings::new().running_status(true));
+    let (_, compressed) = write(
+        Settings::new()
+            .running_status(true)
+            .compress_note_offs(true),
+    );
+    // only the first of the 40 note messages has a status byte
+    assert_eq!(uncompressed.len() - compressed.len(), 39);
+
+    let mut reloaded = MidiFile::from_bytes(&compressed).unwrap();
+    assert!(!reloaded.semantically_equal(&original));
+    reloaded.normalize_note_offs(true);
+    assert!(reloaded.semantically_equal(&original));
+}
+
+/// Each realtime message is written as its single status byte and read back unchanged, except for
+/// the system messages whose bytes mean something else in a file.
+#[test]
+fn realtime_messages() {
+    enable_logging();
+    let ch = Channel::new(0);
+    let note = NoteNumber::new(60);
+    let messages = [
+        (Message::TimingClock, 0xF8u8),
+        (Message::Undefined1, 0xF9),
+        (Message::Start, 0xFA),
+        (Message::Continue, 0xFB),
+        (Message::Stop, 0xFC),
+        (Message::Undefined2, 0xFD),
+        (Message::ActiveSensing, 0xFE),
+    ];
+    for (message, byte) in messages {
+        let track = TrackBuilder::new()
+            .note_on(0, ch, note, Velocity::new(64))
+            .event(10, Event::Midi(message))
+            .note_off(10, ch, note, Velocity::new(64))
+            .build()
+            .unwrap();
+        let mut midi_file = MidiFile::new_with_settings(Settings::new().running_status(true));
+        midi_file.push_track(track).unwrap();
+        let mut bytes = Vec::new();
+        midi_file.write(&mut bytes).unwrap();
+        let expected = [0x0A, byte, 0x0A, 0x80, 60, 64];
+        assert!(bytes.windows(expected.len()).any(|w| w == expected));
+        let reloaded = MidiFile::from_bytes(&bytes).unwrap();
+        assert!(reloaded.semantically_equal(&midi_file));
+        let event = reloaded.track(0).unwrap().events().nth(1).unwrap().event();
+        assert_eq!(*event, Event::Midi(message));
+    }
+
+    // the byte of a system reset would be read back as the start of a meta event
+    let track = TrackBuilder::new()
+        .event(0, Event::Midi(Message::SystemReset))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    let mut bytes = Vec::new();
+    let error = midi_file.write(&mut bytes).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Other);
+    assert!(error.to_string().contains("SystemReset"));
+
+    // a bare EOX would be read back as the start of an F7 sysex event
+    let track = TrackBuilder::new()
+        .event(0, Event::Midi(Message::EndOfSysexFlag))
+        .build()
+        .unwrap();
+    let mut midi_file = MidiFile::new();
+    midi_file.push_track(track).unwrap();
+    let error = midi_file.write_to_vec().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Other);
+    assert!(error.to_string().contains("EndOfSysexFlag"));
+}
+
+/// A track containing every realtime byte that can appear in a file is written back unchanged.
+#[test]
+fn realtime_bytes_identical() {
+    enable_logging();
+    #[rustfmt::skip]
+    let events: &[u8] = &[
+        0x00, 0x90, 0x3C, 0x40,
+        0x00, 0xF8,
+        0x01, 0xF9,
+        0x02, 0xFA,
+        0x03, 0xFB,
+        0x04, 0xFC,
+        0x05, 0xFD,
+        0x06, 0xFE,
+        0x07, 0x80, 0x3C, 0x40,
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let mut bytes = vec![
+        b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0, 96, b'M', b'T', b'r', b'k',
+    ];
+    bytes.extend_from_slice(&(events.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(events);
+
+    let midi_file = MidiFile::from_bytes(&bytes).unwrap();
+    assert_eq!(midi_file.track(0).unwrap().events_len(), 10);
+    let mut written = Vec::new();
+    midi_file.write(&mut written).unwrap();
+    assert_eq!(written, bytes);
+}
+
+/// A track that uses running status for some messages but repeats the status byte for others can
+/// only be reproduced exactly when the layout of each message is preserved.
+#[test]
+fn preserve_status_bytes() {
+    enable_logging();
+    #[rustfmt::skip]
+    let events: &[u8] = &[
+        0x00, 0x90, 0x3C, 0x40,
+        // running status
+        0x00, 0x3E, 0x40,
+        // a redundant status byte
+        0x00, 0x90, 0x40, 0x40,
+        0x10, 0x80, 0x3C, 0x40,
+        // running status across a meta event
+        0x00, 0xFF, 0x05, 0x02, b'l', b'a',
+        0x00, 0x3E, 0x40,
+        0x00, 0x80, 0x40, 0x40,
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+    let mut bytes = vec![
+        b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0, 96, b'M', b'T', b'r', b'k',
+    ];
+    bytes.extend_from_slice(&(events.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(events);
+
+    // neither global setting reproduces the mixed layout
+    let midi_file = MidiFile::from_bytes(&bytes).unwrap();
+    for running_status in [true, false] {
+        let mut written = Vec::new();
+        midi_file
+            .write_with_settings(&mut written, Settings::new().running_status(running_status))
+            .unwrap();
+        assert_ne!(written, bytes);
+    }
+
+    let settings = Settings::new().preserve_status_bytes(true);
+    let midi_file = MidiFile::read_with_settings(bytes.as_slice(), settings).unwrap();
+    let layout: Vec<Option<bool>> = midi_file
+        .track(0)
+        .unwrap()
+        .events()
+        .map(|e| e.explicit_status())
+        .collect();
+    assert_eq!(
+        layout,
+        vec![
+            Some(true),
+            Some(false),
+            Some(true),
+            Some(true),
+            None,
+            Some(false),
+            Some(true),
+            None
+        ]
+    );
+    for running_status in [true, false] {
+        let mut written = Vec::new();
+        midi_file
+            .write_with_settings(&mut written, Settings::new().running_status(running_status))
+            .unwrap();
+        assert_eq!(written, bytes);
+    }
+
+    // the recorded layout is an encoding detail, so it does not affect comparisons
+    let plain = MidiFile::from_bytes(&bytes).unwrap();
+    assert!(midi_file.semantically_equal(&plain));
+    assert_eq!(midi_file.track(0), plain.track(0));
+}
+
+/// Track chunks can be padded to a multiple of some number of bytes without changing the timing.
+#[test]
+fn pad_track_to() {
+    enable_logging();
+    let original = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    for alignment in [2u32, 4, 16, 512] {
+        let mut bytes = Vec::new();
+        original
+            .write_with_settings(&mut bytes, Settings::new().pad_track_to(alignment))
+            .unwrap();
+        let mut start = 14;
+        while start < bytes.len() {
+            assert_eq!(&bytes[start..start + 4], b"MTrk");
+            let length = u32::from_be_bytes([
+                bytes[start + 4],
+                bytes[start + 5],
+                bytes[start + 6],
+                bytes[start + 7],
+            ]);
+            assert_eq!(length % alignment, 0, "alignment {}", alignment);
+            start += 8 + length as usize;
+        }
+        assert_eq!(start, bytes.len());
+
+        let mut reloaded = MidiFile::from_bytes(&bytes).unwrap();
+        let mut expected = original.clone();
+        reloaded.strip_non_essential_meta();
+        expected.strip_non_essential_meta();
+        assert!(reloaded.semantically_equal(&expected));
+    }
+}
+
+#[test]
+fn rmid() {
+    enable_logging();
+    let original = MidiFile::load(test_file(AVE_MARIS_STELLA)).unwrap();
+    let mut rmid = Vec::new();
+    original.write_rmid(&mut rmid).unwrap();
+    assert_eq!(&rmid[..4], b"RIFF");
+    assert_eq!(&rmid[8..16], b"RMIDdata");
+    assert_eq!(rmid.len() % 2, 0);
+    let riff_size = u32::from_le_bytes([rmid[4], rmid[5], rmid[6], rmid[7]]) as usize;
+    assert_eq!(riff_size + 8, rmid.len());
+    let reloaded = MidiFile::read_rmid(rmid.as_slice()).unwrap();
+    assert_eq!(reloaded, original);
+
+    // unknown chunks, such as LIST/INFO and DISP, are skipped
+    let smf = original.write_to_vec().unwrap();
+    let mut with_info = b"RIFF\0\0\0\0RMID".to_vec();
+    with_info.extend_from_slice(b"LIST\x0f\0\0\0INFOINAM\x03\0\0\0ab\0");
+    with_info.push(0);
+    with_info.extend_from_slice(b"DISP\x04\0\0\0\x01\0\0\0");
+    with_info.extend_from_slice(b"data");
+    with_info.extend_from_slice(&(smf.len() as u32).to_le_bytes());
+    with_info.extend_from_slice(&smf);
+    let reloaded = MidiFile::read_rmid(with_info.as_slice()).unwrap();
+    assert_eq!(reloaded, original);
+
+    assert!(MidiFile::read_rmid(&b"RIFF\0\0\0\0RMID"[..]).is_err());
+    assert!(MidiFile::read_rmid(smf.as_slice()).is_err());
+}